@@ -0,0 +1,7 @@
+-- Per-user client preferences
+create table if not exists user_prefs (
+  user_id bigint primary key references users(id) on delete cascade,
+  signature text
+    check (signature is null or (char_length(signature) <= 64 and position(E'\n' in signature) = 0)),
+  updated_at timestamptz not null default now()
+);
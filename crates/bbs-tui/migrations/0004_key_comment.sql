@@ -0,0 +1,2 @@
+-- Optional SSH key comment (e.g. user@host) exposed by the front-end; display hint only
+alter table users add column if not exists key_comment text;
@@ -0,0 +1,4 @@
+-- insert_message now issues pg_notify itself inside its transaction; drop the trigger
+-- so each insert isn't announced twice.
+drop trigger if exists messages_notify on messages;
+drop function if exists notify_new_message();
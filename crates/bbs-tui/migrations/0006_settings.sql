@@ -0,0 +1,6 @@
+-- Instance-wide key/value settings (e.g. motd); admin-managed
+create table if not exists settings (
+  key text primary key,
+  value text not null,
+  updated_at timestamptz not null default now()
+);
@@ -0,0 +1,5 @@
+-- Denormalized body length (chars) for cheap verbosity stats; filled on insert
+alter table messages add column if not exists len int;
+update messages set len = char_length(body) where len is null;
+alter table messages alter column len set not null;
+create index if not exists messages_room_len_idx on messages(room_id, len);
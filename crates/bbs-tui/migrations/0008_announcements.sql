@@ -0,0 +1,7 @@
+-- Admin broadcasts (/shout); kept out of room history, announced on bbs_announce
+create table if not exists announcements (
+  id bigserial primary key,
+  user_id bigint not null references users(id) on delete cascade,
+  body text not null check (char_length(body) <= 1000 and length(btrim(body)) > 0),
+  created_at timestamptz not null default now()
+);
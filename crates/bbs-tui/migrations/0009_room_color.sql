@@ -0,0 +1,2 @@
+-- Optional per-room accent (a theme::ACCENTS name); null means derive from the room name
+alter table rooms add column if not exists color text;
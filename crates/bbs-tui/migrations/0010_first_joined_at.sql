@@ -0,0 +1,5 @@
+-- Keep the original join time; last_joined_at keeps moving on every re-join
+alter table room_members add column if not exists first_joined_at timestamptz;
+update room_members set first_joined_at = last_joined_at where first_joined_at is null;
+alter table room_members alter column first_joined_at set default now();
+alter table room_members alter column first_joined_at set not null;
@@ -0,0 +1,5 @@
+-- Deleting a user keeps their messages; the views show a placeholder handle instead
+alter table messages alter column user_id drop not null;
+alter table messages drop constraint if exists messages_user_id_fkey;
+alter table messages add constraint messages_user_id_fkey
+  foreign key (user_id) references users(id) on delete set null;
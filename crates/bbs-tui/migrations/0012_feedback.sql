@@ -0,0 +1,9 @@
+-- In-app bug reports (/feedback); admins review and mark them handled
+create table if not exists feedback (
+  id bigserial primary key,
+  user_id bigint references users(id) on delete set null,
+  body text not null check (char_length(body) <= 1000 and length(btrim(body)) > 0),
+  created_at timestamptz not null default now(),
+  handled boolean not null default false
+);
+create index if not exists feedback_open_idx on feedback(created_at) where not handled;
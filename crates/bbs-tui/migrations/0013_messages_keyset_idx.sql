@@ -0,0 +1,4 @@
+-- Keyset paging walks (created_at, id) within a room; id breaks created_at ties.
+-- The old (room_id, created_at) index is a prefix of this one.
+create index if not exists messages_room_created_id_idx on messages(room_id, created_at desc, id desc);
+drop index if exists messages_room_created_idx;
@@ -0,0 +1,3 @@
+-- Short self-description (pronouns, status) shown next to the handle in /who
+alter table user_prefs add column if not exists tagline text
+  check (tagline is null or (char_length(tagline) <= 24 and position(E'\n' in tagline) = 0));
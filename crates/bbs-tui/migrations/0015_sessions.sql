@@ -0,0 +1,9 @@
+-- Live TUI sessions per key, for BBS_MAX_SESSIONS_PER_FP; rows whose heartbeat
+-- stops (killed process, dropped SSH) are treated as gone and swept on connect
+create table if not exists sessions (
+  id bigserial primary key,
+  fingerprint text not null,
+  started_at timestamptz not null default now(),
+  heartbeat_at timestamptz not null default now()
+);
+create index if not exists sessions_fp_heartbeat_idx on sessions(fingerprint, heartbeat_at);
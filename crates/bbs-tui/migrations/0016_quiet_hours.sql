@@ -0,0 +1,5 @@
+-- Optional daily read-only window per room (/quiet); times are local to a fixed UTC offset
+alter table rooms add column if not exists quiet_start time;
+alter table rooms add column if not exists quiet_end time;
+alter table rooms add column if not exists quiet_offset_mins integer not null default 0
+  check (quiet_offset_mins between -840 and 840);
@@ -0,0 +1,13 @@
+-- Who did what to whom, for room moderation actions (/purge first)
+create table if not exists moderation_log(
+  id bigserial primary key,
+  room_id bigint not null references rooms(id) on delete cascade,
+  actor_id bigint references users(id) on delete set null,
+  action text not null,
+  target_user_id bigint references users(id) on delete set null,
+  detail text,
+  affected integer not null default 0,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists moderation_log_room_idx on moderation_log(room_id, created_at desc);
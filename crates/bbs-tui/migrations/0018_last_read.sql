@@ -0,0 +1,2 @@
+-- Newest message a member has seen in the room; drives the catch-up summary
+alter table room_members add column if not exists last_read_message_id bigint;
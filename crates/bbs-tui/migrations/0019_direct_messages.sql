@@ -0,0 +1,11 @@
+-- One-to-one messages outside rooms (/msg); senders may be deleted, recipients own the row
+create table if not exists direct_messages(
+  id bigserial primary key,
+  from_user_id bigint references users(id) on delete set null,
+  to_user_id bigint not null references users(id) on delete cascade,
+  body text not null check (char_length(body) <= 1000),
+  created_at timestamptz not null default now(),
+  constraint direct_messages_body_nonempty check (length(btrim(body)) > 0)
+);
+
+create index if not exists direct_messages_to_idx on direct_messages(to_user_id, created_at desc);
@@ -0,0 +1,2 @@
+-- Set by /edit; null for messages that were never changed
+alter table messages add column if not exists edited_at timestamptz;
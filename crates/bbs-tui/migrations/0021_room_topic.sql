@@ -0,0 +1,2 @@
+-- One-line description from /topic, shown in the messages pane title
+alter table rooms add column if not exists topic text check (char_length(topic) <= 120);
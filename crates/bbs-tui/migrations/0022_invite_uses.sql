@@ -0,0 +1,3 @@
+-- Multi-use invite codes; the row goes away with its last use
+alter table invites
+  add column if not exists uses_left integer not null default 1 check (uses_left > 0);
@@ -0,0 +1,2 @@
+-- Optional lifetime for invite codes; null never expires
+alter table invites add column if not exists expires_at timestamptz;
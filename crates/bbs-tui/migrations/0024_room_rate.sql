@@ -0,0 +1,2 @@
+-- Per-room send limit (messages per user per minute); null uses BBS_RATE_PER_MIN
+alter table rooms add column if not exists rate_per_min integer check (rate_per_min > 0);
@@ -0,0 +1,8 @@
+-- /ignore: per-user list of people whose room messages are hidden
+create table if not exists ignores(
+  user_id bigint not null references users(id) on delete cascade,
+  ignored_user_id bigint not null references users(id) on delete cascade,
+  created_at timestamptz not null default now(),
+  primary key (user_id, ignored_user_id),
+  constraint ignores_not_self check (user_id <> ignored_user_id)
+);
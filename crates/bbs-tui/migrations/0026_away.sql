@@ -0,0 +1,2 @@
+-- /away reason; null when present
+alter table users add column if not exists away_reason text check (char_length(away_reason) <= 40);
@@ -0,0 +1,2 @@
+-- room to reopen on the next login; cleared if the room row goes away
+alter table users add column if not exists last_room_id bigint references rooms(id) on delete set null;
@@ -0,0 +1,2 @@
+-- /reply parent. No foreign key: a reply outlives a purged parent and shows it as deleted.
+alter table messages add column if not exists reply_to bigint;
@@ -0,0 +1,8 @@
+-- /react: one row per (message, user, emoji); toggled on and off
+create table if not exists reactions(
+  message_id bigint not null references messages(id) on delete cascade,
+  user_id bigint not null references users(id) on delete cascade,
+  emoji text not null check (octet_length(emoji) between 1 and 32),
+  created_at timestamptz not null default now(),
+  primary key (message_id, user_id, emoji)
+);
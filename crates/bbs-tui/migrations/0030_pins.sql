@@ -0,0 +1,3 @@
+-- /pin: set while pinned, newest pin first in the bar above the pane
+alter table messages add column if not exists pinned_at timestamptz;
+create index if not exists messages_pinned_idx on messages(room_id) where pinned_at is not null;
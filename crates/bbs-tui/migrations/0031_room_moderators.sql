@@ -0,0 +1,8 @@
+-- /mod: users the room creator trusts to moderate alongside them
+create table if not exists room_moderators(
+  room_id bigint not null references rooms(id) on delete cascade,
+  user_id bigint not null references users(id) on delete cascade,
+  granted_by bigint references users(id) on delete set null,
+  created_at timestamptz not null default now(),
+  primary key (room_id, user_id)
+);
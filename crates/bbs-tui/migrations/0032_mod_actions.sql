@@ -0,0 +1,10 @@
+-- moderation audit trail: who did what to whom, per room
+create table if not exists mod_actions(
+  id bigserial primary key,
+  room_id bigint not null references rooms(id) on delete cascade,
+  actor_id bigint references users(id) on delete set null,
+  target_id bigint references users(id) on delete set null,
+  action text not null,
+  created_at timestamptz not null default now()
+);
+create index if not exists mod_actions_room_idx on mod_actions(room_id, created_at);
@@ -0,0 +1,9 @@
+-- /mute: sends from user_id to room_id are refused until muted_until passes;
+-- expired rows are simply ignored (and overwritten by the next mute)
+create table if not exists room_mutes(
+  room_id bigint not null references rooms(id) on delete cascade,
+  user_id bigint not null references users(id) on delete cascade,
+  muted_until timestamptz not null,
+  muted_by bigint references users(id) on delete set null,
+  primary key (room_id, user_id)
+);
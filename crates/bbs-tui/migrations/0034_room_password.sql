@@ -0,0 +1,3 @@
+-- /join <room> <password>: argon2 hash checked before a newcomer's first join;
+-- null means the room is open
+alter table rooms add column if not exists password_hash text;
@@ -0,0 +1,9 @@
+-- /access: invite rooms only take newcomers holding a code bound to them
+do $$ begin
+  create type room_access as enum ('public', 'invite');
+exception when duplicate_object then null;
+end $$;
+alter table rooms add column if not exists access room_access not null default 'public';
+-- /roominvite codes; null is a plain login invite
+alter table invites
+  add column if not exists room_id bigint references rooms(id) on delete cascade;
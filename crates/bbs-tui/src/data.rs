@@ -1,8 +1,11 @@
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveTime, Utc};
 use rand::Rng;
 use sqlx::PgPool;
 
+use crate::realtime;
+use crate::rooms::{self, QuietHours, RoomAccess};
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct User {
@@ -12,6 +15,9 @@ pub struct User {
     pub handle: String,
     pub created_at: DateTime<Utc>,
     pub last_seen_at: DateTime<Utc>,
+    pub key_comment: Option<String>,
+    // /away; None when present
+    pub away_reason: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -23,6 +29,28 @@ pub struct Room {
     pub is_deleted: bool,
     pub created_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    // accent override from /roomcolor; None derives one from the name
+    pub color: Option<String>,
+    // /quiet window; both set or both null
+    pub quiet_start: Option<NaiveTime>,
+    pub quiet_end: Option<NaiveTime>,
+    pub quiet_offset_mins: i32,
+    // /topic; shown in the messages pane title
+    pub topic: Option<String>,
+    // /roomrate; None falls back to BBS_RATE_PER_MIN
+    pub rate_per_min: Option<i32>,
+    // /access
+    pub access: RoomAccess,
+}
+
+impl Room {
+    pub fn quiet_hours(&self) -> Option<QuietHours> {
+        Some(QuietHours {
+            start: self.quiet_start?,
+            end: self.quiet_end?,
+            offset_mins: self.quiet_offset_mins,
+        })
+    }
 }
 
 #[allow(dead_code)]
@@ -34,34 +62,49 @@ pub struct Message {
     pub body: String,
     pub created_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub edited_at: Option<DateTime<Utc>>,
 }
 
+// Shown in place of a handle once the author's user row is gone.
+pub const DELETED_HANDLE: &str = "[deleted-user]";
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct MessageView {
     pub id: i64,
     pub room_id: i64,
-    pub user_id: i64,
+    // None once the author is deleted (messages.user_id is set null)
+    pub user_id: Option<i64>,
     pub user_handle: String,
     pub body: String,
     pub created_at: DateTime<Utc>,
+    pub tagline: Option<String>,
+    // messages.edited_at is set (read as `edited_at is not null as edited`)
+    pub edited: bool,
+    // /reply parent; the quote fields are None once it's deleted or purged
+    pub reply_to: Option<i64>,
+    pub quote_handle: Option<String>,
+    pub quote_body: Option<String>,
 }
 
-pub async fn upsert_user_by_fp(pool: &PgPool, fp: &str, key_type: &str) -> Result<User> {
-    // try select existing first
+pub async fn upsert_user_by_fp(
+    pool: &PgPool,
+    fp: &str,
+    key_type: &str,
+    key_comment: Option<&str>,
+) -> Result<User> {
+    // existing user: touch last_seen_at and refresh the key comment hint if provided
     if let Some(u) = sqlx::query_as::<_, User>(
-        r#"select id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at
-           from users where fingerprint_sha256 = $1"#,
+        r#"update users
+              set last_seen_at = now(), key_comment = coalesce($2, key_comment)
+            where fingerprint_sha256 = $1
+           returning id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason"#,
     )
     .bind(fp)
+    .bind(key_comment)
     .fetch_optional(pool)
     .await?
     {
-        // touch last_seen_at
-        sqlx::query("update users set last_seen_at = now() where id = $1")
-            .bind(u.id)
-            .execute(pool)
-            .await?;
         return Ok(u);
     }
 
@@ -70,13 +113,14 @@ pub async fn upsert_user_by_fp(pool: &PgPool, fp: &str, key_type: &str) -> Resul
     while tries < 10 {
         let handle = random_handle();
         let rec = sqlx::query_as::<_, User>(
-            r#"insert into users(fingerprint_sha256, pubkey_type, handle)
-               values($1,$2,$3)
-               returning id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at"#,
+            r#"insert into users(fingerprint_sha256, pubkey_type, handle, key_comment)
+               values($1,$2,$3,$4)
+               returning id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason"#,
         )
         .bind(fp)
         .bind(key_type)
         .bind(&handle)
+        .bind(key_comment)
         .fetch_one(pool)
         .await;
         match rec {
@@ -98,9 +142,34 @@ pub async fn upsert_user_by_fp(pool: &PgPool, fp: &str, key_type: &str) -> Resul
     Err(anyhow!("failed to create unique handle after retries"))
 }
 
+// Owner of BBS_SYSTEM_OWNED_DEFAULT rooms. '!' never appears in a base64 SSH
+// fingerprint, so no key can log in as this user.
+pub const SYSTEM_FP: &str = "!system";
+
+// Prefer the plain "system" handle; if a person already took it the row still
+// gets created, just with a random handle.
+pub async fn ensure_system_user(pool: &PgPool) -> Result<User> {
+    if let Some(u) = get_user_by_fp(pool, SYSTEM_FP).await? {
+        return Ok(u);
+    }
+    let rec = sqlx::query_as::<_, User>(
+        r#"insert into users(fingerprint_sha256, pubkey_type, handle)
+           values($1, 'system', 'system')
+           on conflict do nothing
+           returning id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason"#,
+    )
+    .bind(SYSTEM_FP)
+    .fetch_optional(pool)
+    .await?;
+    match rec {
+        Some(u) => Ok(u),
+        None => upsert_user_by_fp(pool, SYSTEM_FP, "system", None).await,
+    }
+}
+
 pub async fn get_user_by_fp(pool: &PgPool, fp: &str) -> Result<Option<User>> {
     let u = sqlx::query_as::<_, User>(
-        r#"select id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at
+        r#"select id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason
            from users where fingerprint_sha256 = $1"#,
     )
     .bind(fp)
@@ -109,122 +178,750 @@ pub async fn get_user_by_fp(pool: &PgPool, fp: &str) -> Result<Option<User>> {
     Ok(u)
 }
 
-pub async fn ensure_room_exists(pool: &PgPool, name: &str, created_by: i64) -> Result<Room> {
-    if let Some(r) = sqlx::query_as::<_, Room>(
-        r#"select id, name, created_by, is_deleted, created_at, deleted_at
-           from rooms where name = $1"#,
+pub async fn get_user_by_handle(pool: &PgPool, handle: &str) -> Result<Option<User>> {
+    let u = sqlx::query_as::<_, User>(
+        r#"select id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason
+           from users where handle = $1"#,
+    )
+    .bind(handle)
+    .fetch_optional(pool)
+    .await?;
+    Ok(u)
+}
+
+// Look up a room by name, creating it if missing. The flag is true when this call
+// created the room, so callers can tell a brand-new room from an existing one.
+pub async fn ensure_room_exists(
+    pool: &PgPool,
+    name: &str,
+    created_by: i64,
+) -> Result<(Room, bool)> {
+    // `on conflict do nothing` covers two sessions creating the same room at once
+    let created = sqlx::query_as::<_, Room>(
+        r#"insert into rooms(name, created_by) values($1,$2)
+           on conflict(name) do nothing
+           returning id, name, created_by, is_deleted, created_at, deleted_at, color,
+                     quiet_start, quiet_end, quiet_offset_mins, topic, rate_per_min, access"#,
     )
     .bind(name)
+    .bind(created_by)
     .fetch_optional(pool)
-    .await?
-    {
-        if r.is_deleted {
-            return Err(anyhow!("room_deleted"));
-        }
-        return Ok(r);
+    .await?;
+    if let Some(r) = created {
+        return Ok((r, true));
     }
 
     let r = sqlx::query_as::<_, Room>(
-        r#"insert into rooms(name, created_by) values($1,$2)
-           returning id, name, created_by, is_deleted, created_at, deleted_at"#,
+        r#"select id, name, created_by, is_deleted, created_at, deleted_at, color,
+                  quiet_start, quiet_end, quiet_offset_mins, topic, rate_per_min, access
+           from rooms where name = $1"#,
     )
     .bind(name)
-    .bind(created_by)
     .fetch_one(pool)
     .await?;
+    if r.is_deleted {
+        return Err(anyhow!("room_deleted"));
+    }
+    Ok((r, false))
+}
+
+// A live room by name, without creating it.
+pub async fn room_by_name(pool: &PgPool, name: &str) -> Result<Option<Room>> {
+    let r = sqlx::query_as::<_, Room>(
+        r#"select id, name, created_by, is_deleted, created_at, deleted_at, color,
+                  quiet_start, quiet_end, quiet_offset_mins, topic, rate_per_min, access
+           from rooms where name = $1 and is_deleted = false"#,
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
     Ok(r)
 }
 
-pub async fn join_room(pool: &PgPool, room_id: i64, user_id: i64) -> Result<()> {
-    sqlx::query(
+// Result of `/join <room> [secret]`. One refusal for a bad password, a missing
+// or wrong invite code, and a room code aimed at a room that doesn't exist, so
+// it never tells which rooms exist or how they're guarded; it joins nothing.
+#[derive(Debug, Clone)]
+pub enum JoinOutcome {
+    // the room, and whether this call created it
+    Joined(Room, bool),
+    WrongPassword,
+}
+
+// `/join <room> [secret]`: like ensure_room_exists + join_room, but a new room
+// created with a secret keeps it as its password hash. A newcomer to an existing
+// room (not its creator, not yet a member) needs the password of a protected
+// room, or a /roominvite code for an invite room, spent by consume_invite.
+pub async fn join_protected_room(
+    pool: &PgPool,
+    channel: &str,
+    name: &str,
+    user_id: i64,
+    secret: Option<&str>,
+) -> Result<JoinOutcome> {
+    let created = match secret {
+        // argon2 only for a room this call is about to create
+        Some(secret) if room_by_name(pool, name).await?.is_none() => {
+            // a /roominvite code isn't a password; don't found a room on one
+            if room_code_is_live(pool, secret).await? {
+                return Ok(JoinOutcome::WrongPassword);
+            }
+            let secret = secret.to_string();
+            let hash =
+                tokio::task::spawn_blocking(move || rooms::hash_room_password(&secret)).await??;
+            sqlx::query_as::<_, Room>(
+                r#"insert into rooms(name, created_by, password_hash) values($1,$2,$3)
+                   on conflict(name) do nothing
+                   returning id, name, created_by, is_deleted, created_at, deleted_at, color,
+                             quiet_start, quiet_end, quiet_offset_mins, topic, rate_per_min, access"#,
+            )
+            .bind(name)
+            .bind(user_id)
+            .bind(&hash)
+            .fetch_optional(pool)
+            .await?
+        }
+        _ => None,
+    };
+    let (room, created) = match created {
+        Some(r) => (r, true),
+        // also the race where someone else created it since the lookup
+        None => ensure_room_exists(pool, name, user_id).await?,
+    };
+    if !created && room.created_by != user_id {
+        let (stored, member): (Option<String>, bool) = sqlx::query_as(
+            r#"select r.password_hash,
+                      exists (select 1 from room_members rm
+                               where rm.room_id = r.id and rm.user_id = $2)
+               from rooms r where r.id = $1"#,
+        )
+        .bind(room.id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+        if !member {
+            if room.access == RoomAccess::Invite {
+                let join = InviteJoin {
+                    channel,
+                    room_id: room.id,
+                    user_id,
+                };
+                let spent = match secret {
+                    Some(code) => consume_invite(pool, code, Some(join)).await?,
+                    None => false,
+                };
+                if !spent {
+                    return Ok(JoinOutcome::WrongPassword);
+                }
+            } else if let Some(stored) = stored {
+                let Some(secret) = secret.map(str::to_string) else {
+                    return Ok(JoinOutcome::WrongPassword);
+                };
+                let ok = tokio::task::spawn_blocking(move || {
+                    rooms::verify_room_password(&secret, &stored)
+                })
+                .await?;
+                if !ok {
+                    return Ok(JoinOutcome::WrongPassword);
+                }
+            }
+        }
+    }
+    join_room(pool, channel, room.id, user_id).await?;
+    Ok(JoinOutcome::Joined(room, created))
+}
+
+// A room-scoped invite code that could still be spent.
+async fn room_code_is_live(pool: &PgPool, code: &str) -> Result<bool> {
+    let live: bool = sqlx::query_scalar(
+        r#"select exists (select 1 from invites
+                           where code = $1 and room_id is not null
+                             and (expires_at is null or expires_at >= now()))"#,
+    )
+    .bind(code)
+    .fetch_one(pool)
+    .await?;
+    Ok(live)
+}
+
+// Returns true on a first join. Only that announces a join; re-joining (room
+// switches, reconnects) just bumps last_joined_at. first_joined_at is set once.
+pub async fn join_room(pool: &PgPool, channel: &str, room_id: i64, user_id: i64) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let inserted = add_member(&mut tx, channel, room_id, user_id).await?;
+    tx.commit().await?;
+    Ok(inserted)
+}
+
+// join_room's upsert on the caller's transaction.
+async fn add_member(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    channel: &str,
+    room_id: i64,
+    user_id: i64,
+) -> Result<bool> {
+    // xmax = 0 only on a freshly inserted row, not an upsert's update
+    let inserted: bool = sqlx::query_scalar(
         r#"insert into room_members(room_id, user_id)
            values($1,$2)
            on conflict(room_id, user_id)
-           do update set last_joined_at = now()"#,
+           do update set last_joined_at = now()
+           returning (xmax = 0)"#,
     )
     .bind(room_id)
     .bind(user_id)
-    .execute(pool)
+    .fetch_one(&mut **tx)
     .await?;
-    Ok(())
+    if inserted {
+        notify(tx, channel, realtime::NotifyPayload::join(room_id, user_id)).await?;
+    }
+    Ok(inserted)
 }
 
-pub async fn leave_room(pool: &PgPool, room_id: i64, user_id: i64) -> Result<bool> {
+pub async fn leave_room(pool: &PgPool, channel: &str, room_id: i64, user_id: i64) -> Result<bool> {
+    let mut tx = pool.begin().await?;
     let res = sqlx::query(r#"delete from room_members where room_id = $1 and user_id = $2"#)
         .bind(room_id)
         .bind(user_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
-    Ok(res.rows_affected() > 0)
+    let left = res.rows_affected() > 0;
+    if left {
+        notify(
+            &mut tx,
+            channel,
+            realtime::NotifyPayload::leave(room_id, user_id),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(left)
+}
+
+// Queue a NOTIFY on the caller's transaction; Postgres delivers it only on commit.
+async fn notify(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    channel: &str,
+    payload: realtime::NotifyPayload,
+) -> Result<()> {
+    sqlx::query("select pg_notify($1, $2)")
+        .bind(channel)
+        .bind(payload.to_json())
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+pub async fn user_handle(pool: &PgPool, user_id: i64) -> Result<Option<String>> {
+    let handle = sqlx::query_scalar(r#"select handle from users where id = $1"#)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(handle)
+}
+
+// Position of a message in a room's history; pages are keyed on it instead of an
+// OFFSET so deep pages cost the same as the first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl MessageView {
+    pub fn cursor(&self) -> MessageCursor {
+        MessageCursor {
+            created_at: self.created_at,
+            id: self.id,
+        }
+    }
 }
 
+// The select every MessageView query starts from: the message, its author
+// (DELETED_HANDLE, bound as $1, once the account is gone), the author's tagline,
+// and the quoted parent of a reply. Callers append their own where/order/limit.
+const MESSAGE_VIEW_SELECT: &str = r#"select m.id, m.room_id, m.user_id,
+          coalesce(u.handle, $1) as user_handle,
+          m.body, m.created_at, p.tagline, m.edited_at is not null as edited,
+          m.reply_to, q.body as quote_body,
+          case when q.id is not null then coalesce(qu.handle, $1) end as quote_handle
+   from messages m
+   left join users u on u.id = m.user_id
+   left join user_prefs p on p.user_id = m.user_id
+   left join messages q on q.id = m.reply_to and q.deleted_at is null
+   left join users qu on qu.id = q.user_id"#;
+
+// Newest `limit` messages, returned oldest first.
 pub async fn recent_messages_view(
     pool: &PgPool,
     room_id: i64,
     limit: i64,
 ) -> Result<Vec<MessageView>> {
-    let rows = sqlx::query_as::<_, MessageView>(
-        r#"select m.id, m.room_id, m.user_id, u.handle as user_handle, m.body, m.created_at
-           from messages m
-           join users u on u.id = m.user_id
-           where m.room_id = $1 and m.deleted_at is null
-           order by m.created_at desc
-           limit $2"#,
+    let sql = format!(
+        r#"{}
+           where m.room_id = $2 and m.deleted_at is null
+           order by m.created_at desc, m.id desc
+           limit $3"#,
+        MESSAGE_VIEW_SELECT
+    );
+    let rows = sqlx::query_as::<_, MessageView>(&sql)
+        .bind(DELETED_HANDLE)
+        .bind(room_id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().rev().collect())
+}
+
+// Up to `limit` messages just newer than `after` (None starts at the oldest),
+// oldest first: recent_messages_view walked forward, for --export.
+pub async fn messages_after(
+    pool: &PgPool,
+    room_id: i64,
+    after: Option<MessageCursor>,
+    limit: i64,
+) -> Result<Vec<MessageView>> {
+    let sql = format!(
+        r#"{}
+           where m.room_id = $2 and m.deleted_at is null
+             and ($3::timestamptz is null or (m.created_at, m.id) > ($3, $4))
+           order by m.created_at, m.id
+           limit $5"#,
+        MESSAGE_VIEW_SELECT
+    );
+    let rows = sqlx::query_as::<_, MessageView>(&sql)
+        .bind(DELETED_HANDLE)
+        .bind(room_id)
+        .bind(after.map(|c| c.created_at))
+        .bind(after.map_or(0, |c| c.id))
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+// The page just older than `before` (pass the oldest loaded message's cursor),
+// oldest first like recent_messages_view.
+pub async fn messages_before(
+    pool: &PgPool,
+    room_id: i64,
+    before: MessageCursor,
+    limit: i64,
+) -> Result<Vec<MessageView>> {
+    let sql = format!(
+        r#"{}
+           where m.room_id = $2 and m.deleted_at is null
+             and (m.created_at, m.id) < ($3, $4)
+           order by m.created_at desc, m.id desc
+           limit $5"#,
+        MESSAGE_VIEW_SELECT
+    );
+    let rows = sqlx::query_as::<_, MessageView>(&sql)
+        .bind(DELETED_HANDLE)
+        .bind(room_id)
+        .bind(before.created_at)
+        .bind(before.id)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().rev().collect())
+}
+
+// Up to `radius` live messages either side of `center_id` in `room_id`, plus the
+// center itself, oldest first. Empty when the center isn't in that room.
+pub async fn messages_around(
+    pool: &PgPool,
+    room_id: i64,
+    center_id: i64,
+    radius: i64,
+) -> Result<Vec<MessageView>> {
+    let sql = format!(
+        r#"with c as (select created_at, id from messages where id = $3 and room_id = $2)
+           select * from (
+             ({select}
+              cross join c
+              where m.room_id = $2 and m.deleted_at is null
+                and (m.created_at, m.id) < (c.created_at, c.id)
+              order by m.created_at desc, m.id desc
+              limit $4)
+             union all
+             ({select}
+              cross join c
+              where m.room_id = $2 and m.deleted_at is null
+                and (m.created_at, m.id) >= (c.created_at, c.id)
+              order by m.created_at, m.id
+              limit $4 + 1)
+           ) w
+           order by created_at, id"#,
+        select = MESSAGE_VIEW_SELECT
+    );
+    let rows = sqlx::query_as::<_, MessageView>(&sql)
+        .bind(DELETED_HANDLE)
+        .bind(room_id)
+        .bind(center_id)
+        .bind(radius)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+// Prefix of the placeholder users --import creates for handles it doesn't know;
+// like SYSTEM_FP, no SSH key fingerprint can match it.
+pub const IMPORT_FP_PREFIX: &str = "!import:";
+
+// Bulk-load one message with its original timestamp, on the caller's
+// transaction: no rate window, no quiet hours, no NOTIFY. The author is the user
+// with that handle, a new placeholder user if there's none, or nobody for
+// DELETED_HANDLE. Returns the new message id.
+pub async fn import_message(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    room_id: i64,
+    handle: &str,
+    body: &str,
+    created_at: DateTime<Utc>,
+) -> Result<i64> {
+    let user_id = if handle == DELETED_HANDLE {
+        None
+    } else {
+        let existing: Option<i64> = sqlx::query_scalar(r#"select id from users where handle = $1"#)
+            .bind(handle)
+            .fetch_optional(&mut **tx)
+            .await?;
+        match existing {
+            Some(id) => Some(id),
+            None => Some(
+                sqlx::query_scalar(
+                    r#"insert into users(fingerprint_sha256, pubkey_type, handle)
+                       values($1, 'import', $2)
+                       returning id"#,
+                )
+                .bind(format!("{}{}", IMPORT_FP_PREFIX, handle))
+                .bind(handle)
+                .fetch_one(&mut **tx)
+                .await?,
+            ),
+        }
+    };
+    let id = sqlx::query_scalar(
+        r#"insert into messages(room_id, user_id, body, len, created_at)
+           values($1, $2, $3, char_length($3), $4)
+           returning id"#,
     )
     .bind(room_id)
-    .bind(limit)
-    .fetch_all(pool)
+    .bind(user_id)
+    .bind(body)
+    .bind(created_at)
+    .fetch_one(&mut **tx)
     .await?;
-    Ok(rows.into_iter().rev().collect())
+    Ok(id)
+}
+
+// Result of a rate-gated insert. `message: None` means it was rejected: by the
+// room's quiet hours when `quiet_until` is set, by a /mute when `muted_until` is,
+// else by the server-side rate limit (`remaining: 0`). `remaining` is what's left in the current window.
+#[derive(Debug, Clone)]
+pub struct InsertOutcome {
+    pub message: Option<Message>,
+    pub remaining: i64,
+    pub quiet_until: Option<NaiveTime>,
+    pub muted_until: Option<DateTime<Utc>>,
+}
+
+#[derive(sqlx::FromRow)]
+struct InsertRow {
+    id: Option<i64>,
+    room_id: Option<i64>,
+    user_id: Option<i64>,
+    body: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+    edited_at: Option<DateTime<Utc>>,
+    recent: i64,
+    rate_limit: i64,
+    quiet_until: Option<NaiveTime>,
+    muted_until: Option<DateTime<Utc>>,
 }
 
 pub async fn insert_message(
     pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    user_id: i64,
+    body: &str,
+) -> Result<InsertOutcome> {
+    insert_message_row(pool, channel, room_id, user_id, body, None).await
+}
+
+// Same gates as insert_message; callers check the parent is live and in this room.
+pub async fn insert_reply(
+    pool: &PgPool,
+    channel: &str,
     room_id: i64,
     user_id: i64,
     body: &str,
-) -> Result<Message> {
-    // Server-side rate gate using CTE counting last-minute messages.
-    // The limit is provided via current_setting('bbs.rate_per_min', true) or env elsewhere.
-    // Here we pass the limit explicitly via SET LOCAL when available; otherwise default 10.
-    // Simpler: inline $4 limit param.
-    let rate_limit: i64 = std::env::var("BBS_RATE_PER_MIN")
+    reply_to: i64,
+) -> Result<InsertOutcome> {
+    insert_message_row(pool, channel, room_id, user_id, body, Some(reply_to)).await
+}
+
+async fn insert_message_row(
+    pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    user_id: i64,
+    body: &str,
+    reply_to: Option<i64>,
+) -> Result<InsertOutcome> {
+    // Server-side rate gate: count this user's last-minute messages in this room
+    // in a CTE and only insert under the room's limit (the env default unless
+    // /roomrate set one). The left join returns the count either way.
+    let default_rate: i64 = std::env::var("BBS_RATE_PER_MIN")
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(10);
+    // quiet hours hold for everyone but the admin key
+    let admin_fp = std::env::var("BBS_ADMIN_FP").ok();
 
-    let rec = sqlx::query_as::<_, Message>(
+    let mut tx = pool.begin().await?;
+    let row = sqlx::query_as::<_, InsertRow>(
         r#"
 with recent as (
   select count(*)::bigint as c
   from messages
-  where user_id = $2 and created_at > now() - interval '1 minute'
+  where user_id = $2 and room_id = $1 and created_at > now() - interval '1 minute'
+),
+lim as (
+  select coalesce((select rate_per_min from rooms where id = $1), $4)::bigint as n
+),
+quiet as (
+  select r.quiet_end
+  from rooms r,
+       lateral (select ((now() at time zone 'UTC')
+                        + make_interval(mins => r.quiet_offset_mins))::time as t) l
+  where r.id = $1 and r.quiet_start is not null and r.quiet_end is not null
+    and (select fingerprint_sha256 from users where id = $2) is distinct from $6
+    and case when r.quiet_start <= r.quiet_end
+             then l.t >= r.quiet_start and l.t < r.quiet_end
+             else l.t >= r.quiet_start or l.t < r.quiet_end end
+),
+muted as (
+  select muted_until from room_mutes
+  where room_id = $1 and user_id = $2 and muted_until > now()
+),
+ins as (
+  insert into messages(room_id, user_id, body, len, reply_to)
+  select $1, $2, $3, $5, $7
+  where (select c from recent) < (select n from lim) and not exists (select 1 from quiet)
+    and not exists (select 1 from muted)
+  returning id, room_id, user_id, body, created_at, deleted_at, edited_at
 )
-insert into messages(room_id, user_id, body)
-select $1, $2, $3
-where (select c from recent) < $4
-returning id, room_id, user_id, body, created_at, deleted_at
+select ins.id, ins.room_id, ins.user_id, ins.body, ins.created_at, ins.deleted_at,
+       ins.edited_at,
+       (select c from recent) as recent,
+       (select n from lim) as rate_limit,
+       (select quiet_end from quiet) as quiet_until,
+       (select muted_until from muted) as muted_until
+from (select 1) one
+left join ins on true
         "#,
     )
     .bind(room_id)
     .bind(user_id)
     .bind(body)
-    .bind(rate_limit)
+    .bind(default_rate)
+    .bind(body.chars().count() as i32)
+    .bind(admin_fp)
+    .bind(reply_to)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let message = match (row.id, row.room_id, row.user_id, row.body, row.created_at) {
+        (Some(id), Some(room_id), Some(user_id), Some(body), Some(created_at)) => Some(Message {
+            id,
+            room_id,
+            user_id,
+            body,
+            created_at,
+            deleted_at: row.deleted_at,
+            edited_at: row.edited_at,
+        }),
+        _ => None,
+    };
+    let Some(m) = message else {
+        tx.rollback().await?;
+        // a quiet-hours or mute rejection didn't use up the rate window
+        let remaining = match (row.quiet_until, row.muted_until) {
+            (None, None) => 0,
+            _ => (row.rate_limit - row.recent).max(0),
+        };
+        return Ok(InsertOutcome {
+            message: None,
+            remaining,
+            quiet_until: row.quiet_until,
+            muted_until: row.muted_until,
+        });
+    };
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::msg(m.room_id, m.id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(InsertOutcome {
+        message: Some(m),
+        remaining: (row.rate_limit - row.recent - 1).max(0),
+        quiet_until: None,
+        muted_until: None,
+    })
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DirectMessage {
+    pub id: i64,
+    pub from_user_id: Option<i64>,
+    pub from_handle: String,
+    pub to_user_id: i64,
+    pub to_handle: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// None when no user has `to_handle`.
+pub async fn insert_dm(
+    pool: &PgPool,
+    channel: &str,
+    from_user: i64,
+    to_handle: &str,
+    body: &str,
+) -> Result<Option<DirectMessage>> {
+    let mut tx = pool.begin().await?;
+    let dm = sqlx::query_as::<_, DirectMessage>(
+        r#"with target as (select id, handle from users where handle = $2),
+           ins as (
+             insert into direct_messages(from_user_id, to_user_id, body)
+             select $1, t.id, $3 from target t
+             returning id, from_user_id, to_user_id, body, created_at
+           )
+           select ins.id, ins.from_user_id, f.handle as from_handle, ins.to_user_id,
+                  t.handle as to_handle, ins.body, ins.created_at
+             from ins
+             join target t on t.id = ins.to_user_id
+             join users f on f.id = ins.from_user_id"#,
+    )
+    .bind(from_user)
+    .bind(to_handle)
+    .bind(body)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(dm) = dm else {
+        tx.rollback().await?;
+        return Ok(None);
+    };
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::dm(dm.id, dm.to_user_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(dm))
+}
+
+pub async fn dm_by_id(pool: &PgPool, id: i64) -> Result<Option<DirectMessage>> {
+    let dm = sqlx::query_as::<_, DirectMessage>(
+        r#"select d.id, d.from_user_id, coalesce(f.handle, $2) as from_handle, d.to_user_id,
+                  t.handle as to_handle, d.body, d.created_at
+             from direct_messages d
+             left join users f on f.id = d.from_user_id
+             join users t on t.id = d.to_user_id
+            where d.id = $1"#,
+    )
+    .bind(id)
+    .bind(DELETED_HANDLE)
     .fetch_optional(pool)
     .await?;
+    Ok(dm)
+}
 
-    match rec {
-        Some(m) => Ok(m),
-        None => Err(anyhow!("rate_limited")),
-    }
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Announcement {
+    pub user_handle: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
 }
 
-pub async fn message_view_by_id(pool: &PgPool, id: i64) -> Result<Option<MessageView>> {
-    let row = sqlx::query_as::<_, MessageView>(
-        r#"select m.id, m.room_id, m.user_id, u.handle as user_handle, m.body, m.created_at
-           from messages m
-           join users u on u.id = m.user_id
-           where m.id = $1"#,
+// Store an admin broadcast and announce it to every session in one transaction.
+pub async fn insert_announcement(
+    pool: &PgPool,
+    channel: &str,
+    user_id: i64,
+    body: &str,
+) -> Result<i64> {
+    let mut tx = pool.begin().await?;
+    let id: i64 = sqlx::query_scalar(
+        r#"insert into announcements(user_id, body) values($1,$2) returning id"#,
+    )
+    .bind(user_id)
+    .bind(body)
+    .fetch_one(&mut *tx)
+    .await?;
+    notify(&mut tx, channel, realtime::NotifyPayload::shout(id)).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct Feedback {
+    pub id: i64,
+    pub user_handle: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn submit_feedback(pool: &PgPool, user_id: i64, body: &str) -> Result<i64> {
+    let id =
+        sqlx::query_scalar(r#"insert into feedback(user_id, body) values($1,$2) returning id"#)
+            .bind(user_id)
+            .bind(body)
+            .fetch_one(pool)
+            .await?;
+    Ok(id)
+}
+
+// Oldest first, so the review queue reads in the order reports came in.
+pub async fn list_open_feedback(pool: &PgPool, limit: i64) -> Result<Vec<Feedback>> {
+    let rows = sqlx::query_as::<_, Feedback>(
+        r#"select f.id, coalesce(u.handle, $2) as user_handle, f.body, f.created_at
+           from feedback f
+           left join users u on u.id = f.user_id
+           where not f.handled
+           order by f.created_at asc, f.id asc
+           limit $1"#,
+    )
+    .bind(limit)
+    .bind(DELETED_HANDLE)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// False if the id doesn't exist or was already handled.
+pub async fn mark_feedback_handled(pool: &PgPool, id: i64) -> Result<bool> {
+    let res = sqlx::query(r#"update feedback set handled = true where id = $1 and not handled"#)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn announcement_by_id(pool: &PgPool, id: i64) -> Result<Option<Announcement>> {
+    let row = sqlx::query_as::<_, Announcement>(
+        r#"select u.handle as user_handle, a.body, a.created_at
+           from announcements a
+           join users u on u.id = a.user_id
+           where a.id = $1"#,
     )
     .bind(id)
     .fetch_optional(pool)
@@ -232,10 +929,39 @@ pub async fn message_view_by_id(pool: &PgPool, id: i64) -> Result<Option<Message
     Ok(row)
 }
 
+// True for errors that mean the DB is unreachable (vs. a rejected query).
+pub fn is_connection_error(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<sqlx::Error>(),
+        Some(
+            sqlx::Error::Io(_)
+                | sqlx::Error::PoolTimedOut
+                | sqlx::Error::PoolClosed
+                | sqlx::Error::Tls(_)
+        )
+    )
+}
+
+// Same visibility as recent_messages_view: a message deleted before the realtime
+// fetch lands must not slip into the pane.
+pub async fn message_view_by_id(pool: &PgPool, id: i64) -> Result<Option<MessageView>> {
+    let sql = format!(
+        r#"{}
+           where m.id = $2 and m.deleted_at is null"#,
+        MESSAGE_VIEW_SELECT
+    );
+    let row = sqlx::query_as::<_, MessageView>(&sql)
+        .bind(DELETED_HANDLE)
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
 pub async fn change_handle(pool: &PgPool, user_id: i64, new_handle: &str) -> Result<User> {
     let mut tx = pool.begin().await?;
     let old = sqlx::query_as::<_, User>(
-        r#"select id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at
+        r#"select id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason
            from users where id=$1 for update"#,
     )
     .bind(user_id)
@@ -244,7 +970,7 @@ pub async fn change_handle(pool: &PgPool, user_id: i64, new_handle: &str) -> Res
 
     let updated = sqlx::query_as::<_, User>(
         r#"update users set handle=$1 where id=$2
-           returning id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at"#,
+           returning id, fingerprint_sha256, pubkey_type, handle, created_at, last_seen_at, key_comment, away_reason"#,
     )
     .bind(new_handle)
     .bind(user_id)
@@ -265,33 +991,367 @@ pub async fn change_handle(pool: &PgPool, user_id: i64, new_handle: &str) -> Res
     Ok(updated)
 }
 
-pub async fn soft_delete_room_by_creator(
+// The creator or one of the room's moderators may delete it.
+pub async fn soft_delete_room_by_moderator(
     pool: &PgPool,
     name: &str,
-    creator_id: i64,
-) -> Result<bool> {
-    let res = sqlx::query(
+    user_id: i64,
+) -> Result<Option<i64>> {
+    let id = sqlx::query_scalar(
         r#"update rooms
             set is_deleted = true, deleted_at = now()
-          where name = $1 and created_by = $2 and is_deleted = false"#,
+          where name = $1 and is_deleted = false
+            and (created_by = $2
+                 or exists (select 1 from room_moderators rm
+                             where rm.room_id = rooms.id and rm.user_id = $2))
+            and created_by not in (select id from users where fingerprint_sha256 = $3)
+          returning id"#,
     )
     .bind(name)
-    .bind(creator_id)
-    .execute(pool)
+    .bind(user_id)
+    .bind(SYSTEM_FP)
+    .fetch_optional(pool)
     .await?;
-    Ok(res.rows_affected() > 0)
+    Ok(id)
 }
 
-pub async fn soft_delete_room_any(pool: &PgPool, name: &str) -> Result<bool> {
+// Set or clear a room's accent; `creator_id` None means any room (admin).
+pub async fn set_room_color(
+    pool: &PgPool,
+    room_id: i64,
+    color: Option<&str>,
+    creator_id: Option<i64>,
+) -> Result<bool> {
+    let res = sqlx::query(
+        r#"update rooms set color = $2
+          where id = $1 and is_deleted = false
+            and ($3::bigint is null or created_by = $3)"#,
+    )
+    .bind(room_id)
+    .bind(color)
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Set or clear a room's topic; `creator_id` None means any room (admin).
+pub async fn set_room_topic(
+    pool: &PgPool,
+    room_id: i64,
+    topic: Option<&str>,
+    creator_id: Option<i64>,
+) -> Result<bool> {
+    let res = sqlx::query(
+        r#"update rooms set topic = $2
+          where id = $1 and is_deleted = false
+            and ($3::bigint is null or created_by = $3)"#,
+    )
+    .bind(room_id)
+    .bind(topic)
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Set or clear a room's send limit; `creator_id` None means any room (admin).
+pub async fn set_room_rate(
+    pool: &PgPool,
+    room_id: i64,
+    rate_per_min: Option<u32>,
+    creator_id: Option<i64>,
+) -> Result<bool> {
     let res = sqlx::query(
+        r#"update rooms set rate_per_min = $2
+          where id = $1 and is_deleted = false
+            and ($3::bigint is null or created_by = $3)"#,
+    )
+    .bind(room_id)
+    .bind(rate_per_min.map(|n| n as i32))
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// `creator_id` None means any room (admin).
+pub async fn set_room_access(
+    pool: &PgPool,
+    room_id: i64,
+    access: RoomAccess,
+    creator_id: Option<i64>,
+) -> Result<bool> {
+    let res = sqlx::query(
+        r#"update rooms set access = $2
+          where id = $1 and is_deleted = false
+            and ($3::bigint is null or created_by = $3)"#,
+    )
+    .bind(room_id)
+    .bind(access)
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Set or clear a room's quiet hours; `creator_id` None means any room (admin).
+pub async fn set_room_quiet(
+    pool: &PgPool,
+    room_id: i64,
+    quiet: Option<QuietHours>,
+    creator_id: Option<i64>,
+) -> Result<bool> {
+    let res = sqlx::query(
+        r#"update rooms set quiet_start = $2, quiet_end = $3, quiet_offset_mins = $4
+          where id = $1 and is_deleted = false
+            and ($5::bigint is null or created_by = $5)"#,
+    )
+    .bind(room_id)
+    .bind(quiet.map(|q| q.start))
+    .bind(quiet.map(|q| q.end))
+    .bind(quiet.map_or(0, |q| q.offset_mins))
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Most messages a single /purge may remove.
+pub const PURGE_MAX: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PurgeCriteria {
+    // the room's newest n messages
+    Last(i64),
+    // one author's newest messages in the room
+    User(i64),
+}
+
+// Soft-delete recent messages in a room, newest first and never more than PURGE_MAX.
+// Only the room creator or the admin key may purge; None means the actor can't.
+// Each removal is broadcast so open clients drop it, and the purge is logged.
+pub async fn purge_messages(
+    pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    actor_id: i64,
+    criteria: PurgeCriteria,
+) -> Result<Option<Vec<i64>>> {
+    let admin_fp = std::env::var("BBS_ADMIN_FP").ok();
+    let mut tx = pool.begin().await?;
+    let allowed: bool = sqlx::query_scalar(
+        r#"select exists(
+             select 1 from rooms r
+              where r.id = $1 and r.is_deleted = false
+                and (r.created_by = $2
+                     or (select fingerprint_sha256 from users where id = $2) = $3))"#,
+    )
+    .bind(room_id)
+    .bind(actor_id)
+    .bind(admin_fp)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !allowed {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    let (target, limit) = match criteria {
+        PurgeCriteria::Last(n) => (None, n.clamp(0, PURGE_MAX)),
+        PurgeCriteria::User(user_id) => (Some(user_id), PURGE_MAX),
+    };
+    let ids: Vec<i64> = sqlx::query_scalar(
+        r#"with victims as (
+             select id from messages
+              where room_id = $1 and deleted_at is null
+                and ($2::bigint is null or user_id = $2)
+              order by created_at desc, id desc
+              limit $3
+           )
+           update messages m set deleted_at = now()
+             from victims v
+            where m.id = v.id
+           returning m.id"#,
+    )
+    .bind(room_id)
+    .bind(target)
+    .bind(limit)
+    .fetch_all(&mut *tx)
+    .await?;
+    for &id in &ids {
+        notify(&mut tx, channel, realtime::NotifyPayload::del(room_id, id)).await?;
+    }
+    let detail = match criteria {
+        PurgeCriteria::Last(_) => format!("last {}", limit),
+        PurgeCriteria::User(_) => "by author".to_string(),
+    };
+    sqlx::query(
+        r#"insert into moderation_log(room_id, actor_id, action, target_user_id, detail, affected)
+           values ($1, $2, 'purge', $3, $4, $5)"#,
+    )
+    .bind(room_id)
+    .bind(actor_id)
+    .bind(target)
+    .bind(detail)
+    .bind(ids.len() as i32)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(Some(ids))
+}
+
+pub async fn soft_delete_room_any(pool: &PgPool, name: &str) -> Result<Option<i64>> {
+    let id = sqlx::query_scalar(
         r#"update rooms
             set is_deleted = true, deleted_at = now()
-          where name = $1 and is_deleted = false"#,
+          where name = $1 and is_deleted = false
+          returning id"#,
     )
     .bind(name)
-    .execute(pool)
+    .fetch_optional(pool)
     .await?;
-    Ok(res.rows_affected() > 0)
+    Ok(id)
+}
+
+// Undo a soft delete made within the last `window`; `moderator_id` None means any
+// room (admin). The window is checked against the DB clock, not the client's.
+pub async fn restore_room(
+    pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    moderator_id: Option<i64>,
+    window: std::time::Duration,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query(
+        r#"update rooms
+            set is_deleted = false, deleted_at = null
+          where id = $1 and is_deleted = true
+            and deleted_at > now() - make_interval(secs => $2)
+            and ($3::bigint is null or created_by = $3
+                 or exists (select 1 from room_moderators rm
+                             where rm.room_id = rooms.id and rm.user_id = $3))"#,
+    )
+    .bind(room_id)
+    .bind(window.as_secs_f64())
+    .bind(moderator_id)
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::room_restore(room_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+// Rewrites the caller's newest live message in the room; None if there isn't one.
+pub async fn edit_last_message(
+    pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    user_id: i64,
+    body: &str,
+) -> Result<Option<Message>> {
+    let mut tx = pool.begin().await?;
+    let msg = sqlx::query_as::<_, Message>(
+        r#"update messages set body = $3, len = $4, edited_at = now()
+          where id = (select id from messages
+                       where room_id = $1 and user_id = $2 and deleted_at is null
+                       order by created_at desc, id desc
+                       limit 1)
+          returning id, room_id, user_id, body, created_at, deleted_at, edited_at"#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(body)
+    .bind(body.chars().count() as i32)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(m) = msg else {
+        tx.rollback().await?;
+        return Ok(None);
+    };
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::edit(m.room_id, m.id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(m))
+}
+
+// Authors only; the broadcast lets open clients drop the message.
+pub async fn soft_delete_message(
+    pool: &PgPool,
+    channel: &str,
+    msg_id: i64,
+    user_id: i64,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let room_id: Option<i64> = sqlx::query_scalar(
+        r#"update messages set deleted_at = now()
+          where id = $1 and user_id = $2 and deleted_at is null
+          returning room_id"#,
+    )
+    .bind(msg_id)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(room_id) = room_id else {
+        tx.rollback().await?;
+        return Ok(false);
+    };
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::del(room_id, msg_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+// Undo of soft_delete_message within `window`; announced as a restore, since the
+// listener drops repeats of a message id it has already delivered.
+pub async fn restore_message(
+    pool: &PgPool,
+    channel: &str,
+    msg_id: i64,
+    user_id: i64,
+    window: std::time::Duration,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let room_id: Option<i64> = sqlx::query_scalar(
+        r#"update messages set deleted_at = null
+          where id = $1 and user_id = $2
+            and deleted_at > now() - make_interval(secs => $3)
+          returning room_id"#,
+    )
+    .bind(msg_id)
+    .bind(user_id)
+    .bind(window.as_secs_f64())
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(room_id) = room_id else {
+        tx.rollback().await?;
+        return Ok(false);
+    };
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::restore(room_id, msg_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
 }
 
 pub async fn prune_old_messages(
@@ -336,16 +1396,67 @@ pub async fn list_joined_rooms(pool: &PgPool, user_id: i64) -> Result<Vec<RoomSu
     Ok(rows)
 }
 
+// One row of the /list directory.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RoomListing {
+    pub id: i64,
+    pub name: String,
+    pub topic: Option<String>,
+    pub members: i64,
+}
+
+// Password and invite-only rooms show up only for their members, so outsiders
+// can't learn they exist. `$1` is the viewer.
+const VISIBLE_TO_VIEWER: &str = "((r.password_hash is null and r.access = 'public')
+    or exists (select 1 from room_members v where v.room_id = r.id and v.user_id = $1))";
+
+// Every live room, busiest first, for /list.
+pub async fn list_rooms(pool: &PgPool, viewer: i64) -> Result<Vec<RoomListing>> {
+    let sql = format!(
+        r#"select r.id, r.name, r.topic, count(rm.user_id) as members
+           from rooms r
+           left join room_members rm on rm.room_id = r.id
+           where r.is_deleted = false and {}
+           group by r.id
+           order by members desc, r.name"#,
+        VISIBLE_TO_VIEWER
+    );
+    let rows = sqlx::query_as::<_, RoomListing>(&sql)
+        .bind(viewer)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+// Rooms a user is a member of, for /find, less the guarded ones `viewer` isn't in.
+pub async fn user_rooms(pool: &PgPool, user_id: i64, viewer: i64) -> Result<Vec<RoomSummary>> {
+    let sql = format!(
+        r#"select r.id, r.name
+           from room_members rm
+           join rooms r on r.id = rm.room_id
+           where rm.user_id = $2 and r.is_deleted = false and {}
+           order by r.name"#,
+        VISIBLE_TO_VIEWER
+    );
+    let rows = sqlx::query_as::<_, RoomSummary>(&sql)
+        .bind(viewer)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
 #[derive(Debug, Clone, sqlx::FromRow)]
 pub struct RoomJoined {
     pub id: i64,
     pub name: String,
+    pub first_joined_at: chrono::DateTime<Utc>,
     pub last_joined_at: chrono::DateTime<Utc>,
 }
 
 pub async fn list_joined_rooms_with_times(pool: &PgPool, user_id: i64) -> Result<Vec<RoomJoined>> {
     let rows = sqlx::query_as::<_, RoomJoined>(
-        r#"select r.id, r.name, rm.last_joined_at
+        r#"select r.id, r.name, rm.first_joined_at, rm.last_joined_at
            from room_members rm
            join rooms r on r.id = rm.room_id
            where rm.user_id = $1 and r.is_deleted = false
@@ -362,6 +1473,11 @@ pub async fn list_joined_rooms_with_times(pool: &PgPool, user_id: i64) -> Result
 pub struct WhoSummary {
     pub id: i64,
     pub handle: String,
+    pub first_joined_at: chrono::DateTime<Utc>,
+    pub tagline: Option<String>,
+    // last login, or the newest heartbeat of a session still open
+    pub last_seen_at: DateTime<Utc>,
+    pub away_reason: Option<String>,
 }
 
 pub async fn list_recent_members(
@@ -370,11 +1486,16 @@ pub async fn list_recent_members(
     limit: i64,
 ) -> Result<Vec<WhoSummary>> {
     let rows = sqlx::query_as::<_, WhoSummary>(
-        r#"select u.id, u.handle
+        r#"select u.id, u.handle, rm.first_joined_at, p.tagline,
+                  greatest(u.last_seen_at,
+                           (select max(s.heartbeat_at) from sessions s
+                             where s.fingerprint = u.fingerprint_sha256)) as last_seen_at,
+                  u.away_reason
            from room_members rm
            join users u on u.id = rm.user_id
+           left join user_prefs p on p.user_id = u.id
            where rm.room_id = $1
-           order by rm.last_joined_at desc
+           order by last_seen_at desc, rm.last_joined_at desc
            limit $2"#,
     )
     .bind(room_id)
@@ -384,12 +1505,244 @@ pub async fn list_recent_members(
     Ok(rows)
 }
 
+// What a member missed: messages by others after their last-read mark.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct UnreadSummary {
+    // the last-read mark; unread messages are the ones after it
+    pub after_id: i64,
+    pub count: i64,
+    // in order of each sender's first unread message
+    pub senders: Vec<String>,
+    pub since: DateTime<Utc>,
+}
+
+// None until the member has a last-read mark, or when nothing new arrived since.
+pub async fn unread_summary(
+    pool: &PgPool,
+    room_id: i64,
+    user_id: i64,
+) -> Result<Option<UnreadSummary>> {
+    let row = sqlx::query_as::<_, UnreadSummary>(
+        r#"with mark as (
+             select last_read_message_id as after_id
+               from room_members
+              where room_id = $1 and user_id = $2 and last_read_message_id is not null
+           ),
+           unread as (
+             select m.created_at, coalesce(u.handle, $3) as handle
+               from messages m
+               join mark on m.id > mark.after_id
+               left join users u on u.id = m.user_id
+              where m.room_id = $1 and m.deleted_at is null
+                and m.user_id is distinct from $2
+           ),
+           senders as (
+             select handle, min(created_at) as first_at from unread group by handle
+           )
+           select mark.after_id,
+                  (select count(*) from unread)::bigint as count,
+                  coalesce((select array_agg(handle order by first_at) from senders),
+                           '{}') as senders,
+                  (select min(created_at) from unread) as since
+             from mark
+            where exists (select 1 from unread)"#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(DELETED_HANDLE)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+// Advance the member's last-read mark; never moves it backwards.
+pub async fn mark_read(pool: &PgPool, room_id: i64, user_id: i64, message_id: i64) -> Result<()> {
+    sqlx::query(
+        r#"update room_members
+              set last_read_message_id = greatest(coalesce(last_read_message_id, 0), $3)
+            where room_id = $1 and user_id = $2"#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(message_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MemberFingerprint {
+    pub handle: String,
+    pub fingerprint_sha256: String,
+    pub pubkey_type: String,
+}
+
+// Full fingerprints of every member, for /audit; sorted so dumps diff cleanly.
+pub async fn room_member_fingerprints(
+    pool: &PgPool,
+    room_id: i64,
+) -> Result<Vec<MemberFingerprint>> {
+    let rows = sqlx::query_as::<_, MemberFingerprint>(
+        r#"select u.handle, u.fingerprint_sha256, u.pubkey_type
+           from room_members rm
+           join users u on u.id = rm.user_id
+           where rm.room_id = $1
+           order by u.handle"#,
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// (room id, member count) for every live room the user has joined, for the sidebar.
+pub async fn room_member_counts(pool: &PgPool, user_id: i64) -> Result<Vec<(i64, i64)>> {
+    let rows: Vec<(i64, i64)> = sqlx::query_as(
+        r#"select rm.room_id, count(*)
+           from room_members mine
+           join room_members rm on rm.room_id = mine.room_id
+           join rooms r on r.id = mine.room_id
+           where mine.user_id = $1 and r.is_deleted = false
+           group by rm.room_id"#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// Message counts for each of `room_ids` over the trailing `window`, split into
+// `buckets` equal slices (oldest first), in one query. Every requested room gets an
+// entry, in order; buckets with no messages are returned as 0.
+pub async fn room_activity_buckets(
+    pool: &PgPool,
+    room_ids: &[i64],
+    buckets: i32,
+    window: std::time::Duration,
+) -> Result<Vec<(i64, Vec<i64>)>> {
+    let rows: Vec<(i64, i32, i64)> = sqlx::query_as(
+        r#"select m.room_id,
+                  least(
+                    floor(extract(epoch from m.created_at - (now() - make_interval(secs => $2)))
+                          / ($2 / $3))::int,
+                    $3 - 1) as b,
+                  count(*)
+           from messages m
+           where m.room_id = any($1)
+             and m.deleted_at is null
+             and m.created_at > now() - make_interval(secs => $2)
+           group by m.room_id, b"#,
+    )
+    .bind(room_ids)
+    .bind(window.as_secs_f64())
+    .bind(buckets)
+    .fetch_all(pool)
+    .await?;
+    let mut out: Vec<(i64, Vec<i64>)> = room_ids
+        .iter()
+        .map(|&id| (id, vec![0; buckets.max(0) as usize]))
+        .collect();
+    for (room_id, b, c) in rows {
+        let Some((_, counts)) = out.iter_mut().find(|(id, _)| *id == room_id) else {
+            continue;
+        };
+        if let Some(slot) = counts.get_mut(b.max(0) as usize) {
+            *slot = c;
+        }
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct UserMessage {
+    pub id: i64,
+    pub room_name: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A user's own messages across all rooms (including ones they've left), newest first.
+pub async fn user_messages(
+    pool: &PgPool,
+    user_id: i64,
+    offset: i64,
+    limit: i64,
+) -> Result<Vec<UserMessage>> {
+    let rows = sqlx::query_as::<_, UserMessage>(
+        r#"select m.id, r.name as room_name, m.body, m.created_at
+           from messages m
+           join rooms r on r.id = m.room_id
+           where m.user_id = $1 and m.deleted_at is null
+           order by m.created_at desc, m.id desc
+           offset $2
+           limit $3"#,
+    )
+    .bind(user_id)
+    .bind(offset)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RoomStats {
+    pub messages: i64,
+    pub posters: i64,
+    pub avg_len: Option<f64>,
+    pub max_len: Option<i32>,
+}
+
+// Uses the stored `len` column so verbosity stats never scan bodies.
+pub async fn room_stats(pool: &PgPool, room_id: i64) -> Result<RoomStats> {
+    let stats = sqlx::query_as::<_, RoomStats>(
+        r#"select count(*) as messages,
+                  count(distinct user_id) as posters,
+                  avg(len)::float8 as avg_len,
+                  max(len) as max_len
+           from messages
+           where room_id = $1 and deleted_at is null"#,
+    )
+    .bind(room_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(stats)
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ServerInfo {
+    pub users: i64,
+    pub rooms: i64,
+    pub messages: i64,
+    pub messages_last_hour: i64,
+    pub oldest_message_at: Option<DateTime<Utc>>,
+}
+
+pub async fn server_info(pool: &PgPool) -> Result<ServerInfo> {
+    let info = sqlx::query_as::<_, ServerInfo>(
+        r#"select
+             (select count(*) from users) as users,
+             (select count(*) from rooms where is_deleted = false) as rooms,
+             (select count(*) from messages where deleted_at is null) as messages,
+             (select count(*) from messages
+               where deleted_at is null and created_at > now() - interval '1 hour')
+               as messages_last_hour,
+             (select min(created_at) from messages) as oldest_message_at"#,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(info)
+}
+
 fn random_handle() -> String {
-    // simple: usr-<8hex> from random u32
-    let n: u32 = rand::thread_rng().gen();
-    let hex = format!("{:08x}", n);
-    let s = format!("usr-{}", hex);
-    s.chars().take(16).collect()
+    // usr-<12hex> from 48 random bits: exactly 16 chars, the valid_nick maximum
+    let n: u64 = rand::thread_rng().gen::<u64>() & 0xffff_ffff_ffff;
+    let s = format!("usr-{:012x}", n);
+    debug_assert!(
+        crate::nick::valid_nick(&s),
+        "generated handle {s:?} is invalid"
+    );
+    s
 }
 
 // Invites
@@ -400,21 +1753,49 @@ pub struct Invite {
     pub code: String,
     pub created_by: Option<i64>,
     pub created_at: DateTime<Utc>,
+    pub uses_left: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    // /roominvite: only good for joining this room; None is a login invite
+    pub room_id: Option<i64>,
 }
 
-pub async fn create_invite(pool: &PgPool, code: &str, created_by: i64) -> Result<Invite> {
+pub async fn insert_invite(
+    pool: &PgPool,
+    code: &str,
+    created_by: i64,
+    uses: u32,
+    ttl: Option<chrono::Duration>,
+    room_id: Option<i64>,
+) -> Result<Invite> {
     let inv = sqlx::query_as::<_, Invite>(
-        r#"insert into invites(code, created_by)
-           values($1,$2)
-           returning code, created_by, created_at"#,
+        r#"insert into invites(code, created_by, uses_left, expires_at, room_id)
+           values($1,$2,$3, now() + make_interval(secs => $4), $5)
+           returning code, created_by, created_at, uses_left, expires_at, room_id"#,
     )
     .bind(code)
     .bind(created_by)
+    .bind(uses as i32)
+    .bind(ttl.map(|d| d.num_seconds() as f64))
+    .bind(room_id)
     .fetch_one(pool)
     .await?;
     Ok(inv)
 }
 
+// Mint a random code good for `max_uses` logins (within `ttl`, if given) and return it.
+// With `room_id` it's good for joining that room instead.
+pub async fn create_invite(
+    pool: &PgPool,
+    created_by: i64,
+    max_uses: u32,
+    ttl: Option<chrono::Duration>,
+    room_id: Option<i64>,
+) -> Result<String> {
+    let code = crate::util::random_invite_code();
+    insert_invite(pool, &code, created_by, max_uses, ttl, room_id).await?;
+    Ok(code)
+}
+
 pub async fn delete_invite(pool: &PgPool, code: &str) -> Result<bool> {
     let res = sqlx::query(r#"delete from invites where code=$1"#)
         .bind(code)
@@ -425,7 +1806,7 @@ pub async fn delete_invite(pool: &PgPool, code: &str) -> Result<bool> {
 
 pub async fn list_invites(pool: &PgPool, limit: i64) -> Result<Vec<Invite>> {
     let rows = sqlx::query_as::<_, Invite>(
-        r#"select code, created_by, created_at
+        r#"select code, created_by, created_at, uses_left, expires_at, room_id
            from invites
            order by created_at desc
            limit $1"#,
@@ -436,10 +1817,608 @@ pub async fn list_invites(pool: &PgPool, limit: i64) -> Result<Vec<Invite>> {
     Ok(rows)
 }
 
-pub async fn consume_invite(pool: &PgPool, code: &str) -> Result<bool> {
-    let res = sqlx::query(r#"delete from invites where code=$1"#)
-        .bind(code)
+// A /roominvite code being spent on `/join`.
+pub struct InviteJoin<'a> {
+    pub channel: &'a str,
+    pub room_id: i64,
+    pub user_id: i64,
+}
+
+// Spend one use; the last one deletes the code. The row lock keeps two logins
+// from sharing a single remaining use. Expired codes read as unknown ones.
+// `join` None (the login prompt) takes only login invites; Some takes only codes
+// bound to its room and makes the user a member in the same transaction.
+pub async fn consume_invite(
+    pool: &PgPool,
+    code: &str,
+    join: Option<InviteJoin<'_>>,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let left: Option<i32> = sqlx::query_scalar(
+        r#"select uses_left from invites
+           where code=$1 and room_id is not distinct from $2
+             and (expires_at is null or expires_at >= now())
+           for update"#,
+    )
+    .bind(code)
+    .bind(join.as_ref().map(|j| j.room_id))
+    .fetch_optional(&mut *tx)
+    .await?;
+    match left {
+        None => return Ok(false),
+        Some(n) if n > 1 => {
+            sqlx::query(r#"update invites set uses_left = uses_left - 1 where code=$1"#)
+                .bind(code)
+                .execute(&mut *tx)
+                .await?;
+        }
+        Some(_) => {
+            sqlx::query(r#"delete from invites where code=$1"#)
+                .bind(code)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    if let Some(j) = join {
+        add_member(&mut tx, j.channel, j.room_id, j.user_id).await?;
+    }
+    tx.commit().await?;
+    Ok(true)
+}
+
+// Sessions
+
+// Register a session unless `fp` already has `max` live ones (0 = unlimited);
+// None means refused. Sessions with no heartbeat within `stale` don't count.
+pub async fn open_session(
+    pool: &PgPool,
+    fp: &str,
+    max: u32,
+    stale: std::time::Duration,
+) -> Result<Option<i64>> {
+    let mut tx = pool.begin().await?;
+    // serialize concurrent logins of one key so they can't both see room for one more
+    sqlx::query("select pg_advisory_xact_lock(hashtext($1))")
+        .bind(fp)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(r#"delete from sessions where heartbeat_at < now() - make_interval(secs => $1)"#)
+        .bind(stale.as_secs_f64())
+        .execute(&mut *tx)
+        .await?;
+    if max > 0 {
+        let live: i64 =
+            sqlx::query_scalar(r#"select count(*) from sessions where fingerprint = $1"#)
+                .bind(fp)
+                .fetch_one(&mut *tx)
+                .await?;
+        if live >= max as i64 {
+            return Ok(None);
+        }
+    }
+    let id: i64 =
+        sqlx::query_scalar(r#"insert into sessions(fingerprint) values($1) returning id"#)
+            .bind(fp)
+            .fetch_one(&mut *tx)
+            .await?;
+    tx.commit().await?;
+    Ok(Some(id))
+}
+
+pub async fn heartbeat_session(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query(r#"update sessions set heartbeat_at = now() where id = $1"#)
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn close_session(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query(r#"delete from sessions where id = $1"#)
+        .bind(id)
         .execute(pool)
         .await?;
+    Ok(())
+}
+
+// User prefs
+
+pub async fn get_signature(pool: &PgPool, user_id: i64) -> Result<Option<String>> {
+    let sig: Option<Option<String>> =
+        sqlx::query_scalar(r#"select signature from user_prefs where user_id = $1"#)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(sig.flatten())
+}
+
+pub async fn set_signature(pool: &PgPool, user_id: i64, signature: Option<&str>) -> Result<()> {
+    sqlx::query(
+        r#"insert into user_prefs(user_id, signature)
+           values($1,$2)
+           on conflict(user_id)
+           do update set signature = excluded.signature, updated_at = now()"#,
+    )
+    .bind(user_id)
+    .bind(signature)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Set or clear (None) the /away reason.
+pub async fn set_away(pool: &PgPool, user_id: i64, reason: Option<&str>) -> Result<()> {
+    sqlx::query(r#"update users set away_reason = $2 where id = $1"#)
+        .bind(user_id)
+        .bind(reason)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// Reactions
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReactionCount {
+    pub message_id: i64,
+    pub emoji: String,
+    pub n: i64,
+}
+
+// Add the reaction, or remove it if this user already left it. Some(true) when
+// added; None when the message is gone.
+pub async fn toggle_reaction(
+    pool: &PgPool,
+    channel: &str,
+    message_id: i64,
+    user_id: i64,
+    emoji: &str,
+) -> Result<Option<bool>> {
+    let mut tx = pool.begin().await?;
+    let room_id: Option<i64> = sqlx::query_scalar(
+        r#"select room_id from messages where id = $1 and deleted_at is null for share"#,
+    )
+    .bind(message_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some(room_id) = room_id else {
+        tx.rollback().await?;
+        return Ok(None);
+    };
+    let removed = sqlx::query(
+        r#"delete from reactions where message_id = $1 and user_id = $2 and emoji = $3"#,
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .bind(emoji)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+    if !removed {
+        sqlx::query(
+            r#"insert into reactions(message_id, user_id, emoji) values($1,$2,$3)
+               on conflict do nothing"#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(emoji)
+        .execute(&mut *tx)
+        .await?;
+    }
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::react(room_id, message_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(Some(!removed))
+}
+
+// Per-emoji totals for a batch of messages, each message's emoji in the order
+// they were first used.
+pub async fn reaction_counts(pool: &PgPool, msg_ids: &[i64]) -> Result<Vec<ReactionCount>> {
+    let rows = sqlx::query_as::<_, ReactionCount>(
+        r#"select message_id, emoji, count(*) as n
+           from reactions
+           where message_id = any($1)
+           group by message_id, emoji
+           order by message_id, min(created_at), emoji"#,
+    )
+    .bind(msg_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+// The creator counts as a moderator of their own room.
+pub async fn is_moderator(pool: &PgPool, room_id: i64, user_id: i64) -> Result<bool> {
+    let yes = sqlx::query_scalar(
+        r#"select exists (select 1 from rooms where id = $1 and created_by = $2)
+               or exists (select 1 from room_moderators where room_id = $1 and user_id = $2)"#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(yes)
+}
+
+// Make `user_id` a moderator; `creator_id` None means any room (admin). False
+// when they already are one or the caller doesn't own the room.
+pub async fn grant_moderator(
+    pool: &PgPool,
+    room_id: i64,
+    user_id: i64,
+    granted_by: i64,
+    creator_id: Option<i64>,
+) -> Result<bool> {
+    let res = sqlx::query(
+        r#"insert into room_moderators(room_id, user_id, granted_by)
+           select id, $2, $3 from rooms
+            where id = $1 and is_deleted = false
+              and ($4::bigint is null or created_by = $4)
+           on conflict do nothing"#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(granted_by)
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
     Ok(res.rows_affected() > 0)
 }
+
+// False when `user_id` wasn't a moderator or the caller doesn't own the room.
+pub async fn revoke_moderator(
+    pool: &PgPool,
+    room_id: i64,
+    user_id: i64,
+    creator_id: Option<i64>,
+) -> Result<bool> {
+    let res = sqlx::query(
+        r#"delete from room_moderators rm
+            using rooms r
+            where rm.room_id = $1 and rm.user_id = $2 and r.id = rm.room_id
+              and ($3::bigint is null or r.created_by = $3)"#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(creator_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+// Remove `target_user_id` from the room and log it in mod_actions; `moderator_id`
+// None means any room (admin). The creator can't be kicked. False when the
+// target isn't a member or the actor lacks the authority.
+pub async fn kick_member(
+    pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    target_user_id: i64,
+    actor_id: i64,
+    moderator_id: Option<i64>,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let res = sqlx::query(
+        r#"delete from room_members rm
+            using rooms r
+            where rm.room_id = $1 and rm.user_id = $2
+              and r.id = rm.room_id and r.created_by <> $2
+              and ($3::bigint is null or r.created_by = $3
+                   or exists (select 1 from room_moderators m
+                               where m.room_id = r.id and m.user_id = $3))"#,
+    )
+    .bind(room_id)
+    .bind(target_user_id)
+    .bind(moderator_id)
+    .execute(&mut *tx)
+    .await?;
+    if res.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+    sqlx::query(
+        r#"insert into mod_actions(room_id, actor_id, target_id, action)
+           values($1, $2, $3, 'kick')"#,
+    )
+    .bind(room_id)
+    .bind(actor_id)
+    .bind(target_user_id)
+    .execute(&mut *tx)
+    .await?;
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::kick(room_id, target_user_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+// Longest /mute; anything longer is a kick.
+pub const MUTE_MAX_MINS: u32 = 24 * 60;
+
+// Refuse the target's sends in this room for `minutes` (replacing any earlier
+// mute) and log it; `minutes` 0 lifts the mute. `moderator_id` None means any
+// room (admin). The creator can't be muted. False without the authority, or
+// when there was no mute to lift.
+pub async fn mute_member(
+    pool: &PgPool,
+    room_id: i64,
+    target_user_id: i64,
+    actor_id: i64,
+    moderator_id: Option<i64>,
+    minutes: u32,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let allowed: bool = sqlx::query_scalar(
+        r#"select exists (select 1 from rooms r
+                           where r.id = $1 and r.created_by <> $2
+                             and ($3::bigint is null or r.created_by = $3
+                                  or exists (select 1 from room_moderators m
+                                              where m.room_id = r.id and m.user_id = $3)))"#,
+    )
+    .bind(room_id)
+    .bind(target_user_id)
+    .bind(moderator_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if !allowed {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+    let changed = if minutes == 0 {
+        sqlx::query(
+            r#"delete from room_mutes
+                where room_id = $1 and user_id = $2 and muted_until > now()"#,
+        )
+        .bind(room_id)
+        .bind(target_user_id)
+        .execute(&mut *tx)
+        .await?
+    } else {
+        sqlx::query(
+            r#"insert into room_mutes(room_id, user_id, muted_until, muted_by)
+               values($1, $2, now() + make_interval(mins => $3), $4)
+               on conflict (room_id, user_id)
+               do update set muted_until = excluded.muted_until, muted_by = excluded.muted_by"#,
+        )
+        .bind(room_id)
+        .bind(target_user_id)
+        .bind(minutes as i32)
+        .bind(actor_id)
+        .execute(&mut *tx)
+        .await?
+    };
+    if changed.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+    let action = if minutes == 0 { "unmute" } else { "mute" };
+    sqlx::query(
+        r#"insert into mod_actions(room_id, actor_id, target_id, action)
+           values($1, $2, $3, $4)"#,
+    )
+    .bind(room_id)
+    .bind(actor_id)
+    .bind(target_user_id)
+    .bind(action)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PinOutcome {
+    Changed,
+    // already pinned (or already not): nothing written, nothing announced
+    Unchanged,
+    // no such live message in the room, or no permission
+    Refused,
+}
+
+// Pin or unpin a live message in `room_id`; `moderator_id` None means any room
+// (admin). Only a message whose pin state actually flips is written and notified.
+pub async fn set_pin(
+    pool: &PgPool,
+    channel: &str,
+    room_id: i64,
+    message_id: i64,
+    pinned: bool,
+    moderator_id: Option<i64>,
+) -> Result<PinOutcome> {
+    let mut tx = pool.begin().await?;
+    let (found, changed): (i64, i64) = sqlx::query_as(
+        r#"with target as (
+               select m.id, m.pinned_at is not null as was_pinned
+               from messages m
+               join rooms r on r.id = m.room_id
+               where m.id = $2 and m.room_id = $1 and m.deleted_at is null
+                 and r.is_deleted = false
+                 and ($4::bigint is null or r.created_by = $4
+                      or exists (select 1 from room_moderators rm
+                                  where rm.room_id = r.id and rm.user_id = $4))
+               for update of m
+           ), flipped as (
+               update messages m
+               set pinned_at = case when $3 then now() end
+               from target t
+               where m.id = t.id and t.was_pinned <> $3
+               returning m.id
+           )
+           select (select count(*) from target), (select count(*) from flipped)"#,
+    )
+    .bind(room_id)
+    .bind(message_id)
+    .bind(pinned)
+    .bind(moderator_id)
+    .fetch_one(&mut *tx)
+    .await?;
+    if changed == 0 {
+        tx.rollback().await?;
+        return Ok(match found {
+            0 => PinOutcome::Refused,
+            _ => PinOutcome::Unchanged,
+        });
+    }
+    notify(
+        &mut tx,
+        channel,
+        realtime::NotifyPayload::pin(room_id, message_id),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(PinOutcome::Changed)
+}
+
+// A room's pinned messages, most recently pinned first.
+pub async fn list_pinned(pool: &PgPool, room_id: i64) -> Result<Vec<MessageView>> {
+    let sql = format!(
+        r#"{}
+           where m.room_id = $2 and m.pinned_at is not null and m.deleted_at is null
+           order by m.pinned_at desc, m.id desc"#,
+        MESSAGE_VIEW_SELECT
+    );
+    let rows = sqlx::query_as::<_, MessageView>(&sql)
+        .bind(DELETED_HANDLE)
+        .bind(room_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows)
+}
+
+// Last focused room, restored on the next login.
+pub async fn set_last_room(pool: &PgPool, user_id: i64, room_id: i64) -> Result<()> {
+    sqlx::query(r#"update users set last_room_id = $2 where id = $1"#)
+        .bind(user_id)
+        .bind(room_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+// None once the room is deleted or the user has left it.
+pub async fn last_room(pool: &PgPool, user_id: i64) -> Result<Option<Room>> {
+    let r = sqlx::query_as::<_, Room>(
+        r#"select r.id, r.name, r.created_by, r.is_deleted, r.created_at, r.deleted_at, r.color,
+                  r.quiet_start, r.quiet_end, r.quiet_offset_mins, r.topic, r.rate_per_min,
+                  r.access
+           from users u
+           join rooms r on r.id = u.last_room_id
+           join room_members rm on rm.room_id = r.id and rm.user_id = u.id
+           where u.id = $1 and r.is_deleted = false"#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(r)
+}
+
+// Ignores
+
+// True when newly added.
+pub async fn add_ignore(pool: &PgPool, user_id: i64, ignored_user_id: i64) -> Result<bool> {
+    let res = sqlx::query(
+        r#"insert into ignores(user_id, ignored_user_id) values($1,$2)
+           on conflict do nothing"#,
+    )
+    .bind(user_id)
+    .bind(ignored_user_id)
+    .execute(pool)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn remove_ignore(pool: &PgPool, user_id: i64, ignored_user_id: i64) -> Result<bool> {
+    let res = sqlx::query(r#"delete from ignores where user_id = $1 and ignored_user_id = $2"#)
+        .bind(user_id)
+        .bind(ignored_user_id)
+        .execute(pool)
+        .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+pub async fn list_ignores(pool: &PgPool, user_id: i64) -> Result<Vec<i64>> {
+    let ids = sqlx::query_scalar(r#"select ignored_user_id from ignores where user_id = $1"#)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(ids)
+}
+
+pub async fn get_tagline(pool: &PgPool, user_id: i64) -> Result<Option<String>> {
+    let tag: Option<Option<String>> =
+        sqlx::query_scalar(r#"select tagline from user_prefs where user_id = $1"#)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(tag.flatten())
+}
+
+pub async fn set_tagline(pool: &PgPool, user_id: i64, tagline: Option<&str>) -> Result<()> {
+    sqlx::query(
+        r#"insert into user_prefs(user_id, tagline)
+           values($1,$2)
+           on conflict(user_id)
+           do update set tagline = excluded.tagline, updated_at = now()"#,
+    )
+    .bind(user_id)
+    .bind(tagline)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_setting(pool: &PgPool, key: &str) -> Result<Option<String>> {
+    let v: Option<String> = sqlx::query_scalar(r#"select value from settings where key = $1"#)
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(v)
+}
+
+// None removes the setting.
+pub async fn set_setting(pool: &PgPool, key: &str, value: Option<&str>) -> Result<()> {
+    match value {
+        Some(v) => {
+            sqlx::query(
+                r#"insert into settings(key, value)
+                   values($1,$2)
+                   on conflict(key)
+                   do update set value = excluded.value, updated_at = now()"#,
+            )
+            .bind(key)
+            .bind(v)
+            .execute(pool)
+            .await?;
+        }
+        None => {
+            sqlx::query(r#"delete from settings where key = $1"#)
+                .bind(key)
+                .execute(pool)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nick::valid_nick;
+
+    #[test]
+    fn random_handle_is_always_valid() {
+        for _ in 0..1000 {
+            let h = random_handle();
+            assert!(valid_nick(&h), "{h}");
+            assert_eq!(h.len(), 16);
+        }
+    }
+}
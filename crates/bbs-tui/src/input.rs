@@ -6,51 +6,201 @@ pub enum Command {
     Quit,
     Me(String),
     Nick(String),
-    Join(String),
+    // room, password (None when not given)
+    Join(String, Option<String>),
     Leave(Option<String>),
     Rooms,
     Who(Option<String>),
     RoomDel(String),
+    RoomColor(Option<String>),
+    RoomRate(Option<String>),
+    Undo,
+    Filter(String),
+    Ignore(String),
+    Unignore(String),
+    // uses, ttl text (`12h`); either order, both optional
+    Invite(Option<u32>, Option<String>),
+    // same, for a code bound to the current room
+    RoomInvite(Option<u32>, Option<String>),
+    Access(Option<String>),
     InviteNew(Option<String>),
     InviteDel(String),
     Invites,
+    Sig(String),
+    Status(String),
+    // None: back from away
+    Away(Option<String>),
+    ServerInfo,
+    Stats,
+    MyMessages,
+    Whois(String),
+    WhoAmI,
+    Find(String),
+    SetMotd(String),
+    Shout(String),
+    Feedback(String),
+    More,
+    Audit(Option<String>),
+    Quiet(Option<String>),
+    Purge(String),
+    // recipient handle, text
+    Msg(String, String),
+    // None when the argument isn't a message id
+    Del(Option<i64>),
+    Edit(String),
+    Topic(Option<String>),
+    List,
+    Clear,
+    Time,
+    // None when the argument isn't a message id
+    Goto(Option<i64>),
+    // parent id (None if not an id), text
+    Reply(Option<i64>, String),
+    // message id (None if not an id), emoji
+    React(Option<i64>, String),
+    // None when the argument isn't a message id
+    Pin(Option<i64>),
+    Unpin(Option<i64>),
+    Pins,
+    // handle
+    Mod(String),
+    Unmod(String),
+    Kick(String),
+    // handle, minutes (0 when missing or not a number)
+    Mute(String, u32),
+    Unmute(String),
 }
 
+// Whitespace rules, for every command:
+// - the command word ends at the first whitespace of any kind (space or tab);
+// - the argument is trimmed at both ends, so an all-whitespace argument is the
+//   same as no argument (`/me   ` gets the usage hint, `/leave  ` means current room);
+// - internal spacing is kept as typed. Free-text args (`/me`, `/sig`, `/setmotd`)
+//   rely on that; single-token args (nicks, rooms, codes) are left with inner
+//   spaces intact so their validators reject them instead of silently joining words.
+//
+// A leading `//` is an escape (IRC-style): not a command, sent as text with one
+// slash removed; see `message_text`.
 pub fn parse_command(s: &str) -> Option<Command> {
     let s = s.trim();
-    if !s.starts_with('/') {
+    if s.starts_with("//") {
         return None;
     }
-    let rest = &s[1..];
-    let mut parts = rest.splitn(2, ' ');
-    let cmd = parts.next().unwrap_or("");
-    let arg = parts.next().unwrap_or("").trim().to_string();
+    let rest = s.strip_prefix('/')?;
+    let (cmd, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let arg = arg.trim().to_string();
+    let opt = |arg: String| if arg.is_empty() { None } else { Some(arg) };
     match cmd {
         "help" | "h" | "?" => Some(Command::Help),
         "quit" | "q" | "exit" => Some(Command::Quit),
         "me" => Some(Command::Me(arg)),
         "nick" | "name" => Some(Command::Nick(arg)),
-        "join" => Some(Command::Join(arg)),
-        "leave" => Some(Command::Leave(if arg.is_empty() {
-            None
-        } else {
-            Some(arg)
-        })),
+        "join" => {
+            let (room, password) = arg.split_once(char::is_whitespace).unwrap_or((&arg, ""));
+            Some(Command::Join(
+                room.to_string(),
+                opt(password.trim().to_string()),
+            ))
+        }
+        "leave" | "part" => Some(Command::Leave(opt(arg))),
         "rooms" => Some(Command::Rooms),
-        "who" => Some(Command::Who(if arg.is_empty() { None } else { Some(arg) })),
+        "list" => Some(Command::List),
+        "clear" => Some(Command::Clear),
+        "time" => Some(Command::Time),
+        "who" => Some(Command::Who(opt(arg))),
         // Canonical: room-del; keep legacy aliases
         "room-del" | "roomdel" | "rdel" => Some(Command::RoomDel(arg)),
-        "invite-new" | "invnew" => Some(Command::InviteNew(if arg.is_empty() {
-            None
-        } else {
-            Some(arg)
-        })),
+        "undo" => Some(Command::Undo),
+        "filter" => Some(Command::Filter(arg)),
+        "ignore" => Some(Command::Ignore(arg)),
+        "unignore" => Some(Command::Unignore(arg)),
+        "mod" => Some(Command::Mod(arg)),
+        "unmod" => Some(Command::Unmod(arg)),
+        "kick" => Some(Command::Kick(arg)),
+        "mute" => {
+            let (nick, mins) = arg.split_once(char::is_whitespace).unwrap_or((&arg, ""));
+            Some(Command::Mute(
+                nick.to_string(),
+                mins.trim().parse().unwrap_or(0),
+            ))
+        }
+        "unmute" => Some(Command::Unmute(arg)),
+        "roomcolor" => Some(Command::RoomColor(opt(arg))),
+        "roomrate" => Some(Command::RoomRate(opt(arg))),
+        "invite" | "roominvite" => {
+            let (mut uses, mut ttl) = (None, None);
+            for t in arg.split_whitespace() {
+                match t.parse() {
+                    Ok(n) => uses = Some(n),
+                    Err(_) => ttl = Some(t.to_string()),
+                }
+            }
+            match cmd {
+                "invite" => Some(Command::Invite(uses, ttl)),
+                _ => Some(Command::RoomInvite(uses, ttl)),
+            }
+        }
+        "access" => Some(Command::Access(opt(arg))),
+        "invite-new" | "invnew" => Some(Command::InviteNew(opt(arg))),
         "invite-del" | "invdel" => Some(Command::InviteDel(arg)),
         "invites" | "invs" => Some(Command::Invites),
+        "sig" => Some(Command::Sig(arg)),
+        "status" => Some(Command::Status(arg)),
+        "away" => Some(Command::Away(opt(arg))),
+        "serverinfo" => Some(Command::ServerInfo),
+        "stats" => Some(Command::Stats),
+        "mine" => Some(Command::MyMessages),
+        "whois" => Some(Command::Whois(arg)),
+        "whoami" => Some(Command::WhoAmI),
+        "find" => Some(Command::Find(arg)),
+        "setmotd" => Some(Command::SetMotd(arg)),
+        "shout" => Some(Command::Shout(arg)),
+        "feedback" => Some(Command::Feedback(arg)),
+        "more" => Some(Command::More),
+        "audit" => Some(Command::Audit(opt(arg))),
+        "quiet" => Some(Command::Quiet(opt(arg))),
+        "topic" => Some(Command::Topic(opt(arg))),
+        "purge" => Some(Command::Purge(arg)),
+        "edit" => Some(Command::Edit(arg)),
+        // `#12` too, the way ids are shown in bodies
+        "del" => Some(Command::Del(
+            arg.strip_prefix('#').unwrap_or(&arg).parse().ok(),
+        )),
+        "goto" | "pin" | "unpin" => {
+            let id = arg.strip_prefix('#').unwrap_or(&arg).parse().ok();
+            match cmd {
+                "goto" => Some(Command::Goto(id)),
+                "pin" => Some(Command::Pin(id)),
+                _ => Some(Command::Unpin(id)),
+            }
+        }
+        "pins" => Some(Command::Pins),
+        "reply" | "react" => {
+            let (id, text) = arg.split_once(char::is_whitespace).unwrap_or((&arg, ""));
+            let id = id.strip_prefix('#').unwrap_or(id).parse().ok();
+            let text = text.trim().to_string();
+            match cmd {
+                "reply" => Some(Command::Reply(id, text)),
+                _ => Some(Command::React(id, text)),
+            }
+        }
+        "msg" => {
+            let (to, text) = arg.split_once(char::is_whitespace).unwrap_or((&arg, ""));
+            Some(Command::Msg(to.to_string(), text.trim().to_string()))
+        }
         _ => Some(Command::Help),
     }
 }
 
+// Text to send for a non-command line: `//foo` becomes `/foo`.
+pub fn message_text(s: &str) -> &str {
+    if s.starts_with("//") {
+        &s[1..]
+    } else {
+        s
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,7 +213,7 @@ mod tests {
         );
         assert_eq!(
             parse_command("/join lobby"),
-            Some(Command::Join("lobby".into()))
+            Some(Command::Join("lobby".into(), None))
         );
         assert_eq!(
             parse_command("/me waves"),
@@ -80,5 +230,209 @@ mod tests {
             parse_command("/leave lobby"),
             Some(Command::Leave(Some("lobby".into())))
         );
+        assert_eq!(
+            parse_command("/part lobby"),
+            Some(Command::Leave(Some("lobby".into())))
+        );
+        assert_eq!(parse_command("/sig off"), Some(Command::Sig("off".into())));
+        assert_eq!(
+            parse_command("/status they/them"),
+            Some(Command::Status("they/them".into()))
+        );
+        assert_eq!(parse_command("/roomcolor"), Some(Command::RoomColor(None)));
+        assert_eq!(parse_command("/away  "), Some(Command::Away(None)));
+        assert_eq!(
+            parse_command("/away bbl, lunch"),
+            Some(Command::Away(Some("bbl, lunch".into())))
+        );
+        assert_eq!(
+            parse_command("/roomrate 30"),
+            Some(Command::RoomRate(Some("30".into())))
+        );
+        assert_eq!(parse_command("/whoami"), Some(Command::WhoAmI));
+        assert_eq!(
+            parse_command("/ignore bob"),
+            Some(Command::Ignore("bob".into()))
+        );
+        assert_eq!(
+            parse_command("/unignore bob "),
+            Some(Command::Unignore("bob".into()))
+        );
+        assert_eq!(parse_command("/topic"), Some(Command::Topic(None)));
+        assert_eq!(
+            parse_command("/topic all about  rust"),
+            Some(Command::Topic(Some("all about  rust".into())))
+        );
+        assert_eq!(parse_command("/undo"), Some(Command::Undo));
+        assert_eq!(
+            parse_command("/edit  fixed  typo "),
+            Some(Command::Edit("fixed  typo".into()))
+        );
+        assert_eq!(
+            parse_command("/quiet 22:00 07:00"),
+            Some(Command::Quiet(Some("22:00 07:00".into())))
+        );
+        assert_eq!(
+            parse_command("/purge user bob"),
+            Some(Command::Purge("user bob".into()))
+        );
+        assert_eq!(
+            parse_command("/feedback done 3"),
+            Some(Command::Feedback("done 3".into()))
+        );
+        assert_eq!(
+            parse_command("/find alice"),
+            Some(Command::Find("alice".into()))
+        );
+    }
+
+    #[test]
+    fn msg_splits_handle_from_text() {
+        assert_eq!(
+            parse_command("/msg bob  hi  there "),
+            Some(Command::Msg("bob".into(), "hi  there".into()))
+        );
+        assert_eq!(
+            parse_command("/msg bob"),
+            Some(Command::Msg("bob".into(), "".into()))
+        );
+        assert_eq!(
+            parse_command("/msg"),
+            Some(Command::Msg("".into(), "".into()))
+        );
+    }
+
+    #[test]
+    fn reply_splits_id_from_text() {
+        assert_eq!(
+            parse_command("/reply #12  agreed,  mostly "),
+            Some(Command::Reply(Some(12), "agreed,  mostly".into()))
+        );
+        assert_eq!(
+            parse_command("/reply 12"),
+            Some(Command::Reply(Some(12), "".into()))
+        );
+        assert_eq!(
+            parse_command("/reply x hi"),
+            Some(Command::Reply(None, "hi".into()))
+        );
+        assert_eq!(
+            parse_command("/react #3 👍"),
+            Some(Command::React(Some(3), "👍".into()))
+        );
+    }
+
+    #[test]
+    fn message_ids_take_plain_or_hash() {
+        assert_eq!(parse_command("/del 12"), Some(Command::Del(Some(12))));
+        assert_eq!(parse_command("/del #12"), Some(Command::Del(Some(12))));
+        assert_eq!(parse_command("/del x"), Some(Command::Del(None)));
+        assert_eq!(parse_command("/del"), Some(Command::Del(None)));
+        assert_eq!(parse_command("/goto #7"), Some(Command::Goto(Some(7))));
+        assert_eq!(parse_command("/goto"), Some(Command::Goto(None)));
+        assert_eq!(parse_command("/pin #7"), Some(Command::Pin(Some(7))));
+        assert_eq!(parse_command("/unpin 7"), Some(Command::Unpin(Some(7))));
+        assert_eq!(
+            parse_command("/mute bob 15"),
+            Some(Command::Mute("bob".into(), 15))
+        );
+        assert_eq!(
+            parse_command("/mute bob soon"),
+            Some(Command::Mute("bob".into(), 0))
+        );
+    }
+
+    #[test]
+    fn invite_takes_optional_uses() {
+        assert_eq!(parse_command("/invite"), Some(Command::Invite(None, None)));
+        assert_eq!(
+            parse_command("/invite 5"),
+            Some(Command::Invite(Some(5), None))
+        );
+        assert_eq!(
+            parse_command("/invite 12h 5"),
+            Some(Command::Invite(Some(5), Some("12h".into())))
+        );
+        assert_eq!(
+            parse_command("/invite x"),
+            Some(Command::Invite(None, Some("x".into())))
+        );
+        assert_eq!(
+            parse_command("/roominvite 3 7d"),
+            Some(Command::RoomInvite(Some(3), Some("7d".into())))
+        );
+    }
+
+    #[test]
+    fn whitespace_only_args_are_absent() {
+        assert_eq!(parse_command("/me   "), Some(Command::Me("".into())));
+        assert_eq!(parse_command("/nick \t "), Some(Command::Nick("".into())));
+        assert_eq!(parse_command("/leave   "), Some(Command::Leave(None)));
+        assert_eq!(parse_command("/who \t"), Some(Command::Who(None)));
+        assert_eq!(
+            parse_command("/invite-new  "),
+            Some(Command::InviteNew(None))
+        );
+        assert_eq!(parse_command("/sig  "), Some(Command::Sig("".into())));
+    }
+
+    #[test]
+    fn token_args_are_trimmed() {
+        assert_eq!(
+            parse_command("  /nick  alice  "),
+            Some(Command::Nick("alice".into()))
+        );
+        assert_eq!(
+            parse_command("/join\tlobby"),
+            Some(Command::Join("lobby".into(), None))
+        );
+        assert_eq!(
+            parse_command("/leave  lobby "),
+            Some(Command::Leave(Some("lobby".into())))
+        );
+        assert_eq!(
+            parse_command("/whois\t bob\t"),
+            Some(Command::Whois("bob".into()))
+        );
+        // the word after the room is its password
+        assert_eq!(
+            parse_command("/join a  b"),
+            Some(Command::Join("a".into(), Some("b".into())))
+        );
+    }
+
+    #[test]
+    fn text_args_keep_internal_spacing() {
+        assert_eq!(
+            parse_command("/me  waves   hello "),
+            Some(Command::Me("waves   hello".into()))
+        );
+        assert_eq!(
+            parse_command("/sig  a  b "),
+            Some(Command::Sig("a  b".into()))
+        );
+        assert_eq!(
+            parse_command("/setmotd hi\\n  there"),
+            Some(Command::SetMotd("hi\\n  there".into()))
+        );
+    }
+
+    #[test]
+    fn bare_words_and_slash() {
+        assert_eq!(parse_command("hello /nick x"), None);
+        assert_eq!(parse_command("/"), Some(Command::Help));
+        assert_eq!(parse_command("/quit  "), Some(Command::Quit));
+        assert_eq!(parse_command("/rooms extra"), Some(Command::Rooms));
+        assert_eq!(parse_command("/list"), Some(Command::List));
+        assert_eq!(parse_command("/clear"), Some(Command::Clear));
+        assert_eq!(parse_command("/time"), Some(Command::Time));
+    }
+
+    #[test]
+    fn double_slash_escapes_to_text() {
+        assert_eq!(parse_command("//join lobby"), None);
+        assert_eq!(message_text("//join lobby"), "/join lobby");
+        assert_eq!(message_text("/// x"), "// x");
+        assert_eq!(message_text("plain"), "plain");
     }
 }
@@ -1,14 +1,13 @@
 use crate::life::{Life, LifeWidget};
+use crate::term::{self, MinSize, TermGuard};
+use crate::theme::{self, ColorLevel};
+use crate::util;
 use anyhow::{anyhow, Result};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
-use crossterm::execute;
-use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
-};
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout::Alignment;
 use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::style::{Color, Modifier, Style};
+use ratatui::style::{Color, Modifier};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, Borders, Paragraph};
 use ratatui::Terminal;
@@ -16,29 +15,70 @@ use sqlx::PgPool;
 use std::io;
 use std::time::{Duration, Instant};
 
-pub async fn prompt(pool: &PgPool) -> Result<()> {
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+// Banner plus the input box and a row of padding above and below.
+const MIN_SIZE: MinSize = MinSize {
+    width: 22,
+    height: 12,
+};
+
+// Invite-screen frame rate bounds (BBS_INVITE_FPS).
+pub const FPS_DEFAULT: u32 = 12;
+pub const FPS_MAX: u32 = 60;
+
+pub fn frame_interval(fps: u32) -> Duration {
+    Duration::from_millis(1000 / u64::from(fps.clamp(1, FPS_MAX)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LifeKey {
+    TogglePause,
+    Step,
+    Reseed,
+    Clear,
+}
+
+// Background controls only apply with an empty input box. Space can't start a
+// code; `.`, `r` and `c` can, so those also wait until the animation is paused.
+fn life_key(ch: char, input: &str, paused: bool) -> Option<LifeKey> {
+    if !input.is_empty() {
+        return None;
+    }
+    match ch {
+        ' ' => Some(LifeKey::TogglePause),
+        '.' if paused => Some(LifeKey::Step),
+        'r' if paused => Some(LifeKey::Reseed),
+        'c' if paused => Some(LifeKey::Clear),
+        _ => None,
+    }
+}
+
+// `frame` is the Life step interval; None keeps the screen static (no background,
+// no banner color cycling), which is kinder to slow links.
+pub async fn prompt(pool: &PgPool, color_level: ColorLevel, frame: Option<Duration>) -> Result<()> {
+    let _term = TermGuard::enter(false)?;
+    let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     let mut input = String::new();
     let mut last_tick = Instant::now();
     let mut last_step = Instant::now();
     let mut phase = 0u8;
+    let mut paused = false;
     // Initialize Life background sized to current terminal
     let mut last_size = terminal.size()?;
-    let mut life = Life::new(last_size.width as usize, last_size.height as usize);
+    let mut life = frame.map(|_| Life::new(last_size.width as usize, last_size.height as usize));
 
     loop {
         terminal.draw(|f| {
             let size = f.size();
-            // Resize life grid if terminal size changed
-            if size != last_size { /* resized */ }
+            if !MIN_SIZE.fits(size) {
+                term::render_too_small(f, MIN_SIZE);
+                return;
+            }
             // Render animated life background first
-            let life_widget = LifeWidget::new(&life);
-            f.render_widget(life_widget, size);
+            if let Some(life) = &life {
+                f.render_widget(LifeWidget::new(life).color_level(color_level), size);
+            }
             // Use 4 chunks: top padding, banner, input area, bottom padding.
             // This centers the input area vertically while keeping the banner
             // and padding consistent.
@@ -57,29 +97,16 @@ pub async fn prompt(pool: &PgPool) -> Result<()> {
                 1 => Color::Magenta,
                 _ => Color::Blue,
             };
+            let banner_style = theme::fg(color_level, banner_color);
             let banner = Paragraph::new(vec![
                 Line::from(Span::styled(
                     "  ____  ____  _____  ",
-                    Style::default()
-                        .fg(banner_color)
-                        .add_modifier(Modifier::BOLD),
-                )),
-                Line::from(Span::styled(
-                    " | __ )| __ )| ____| ",
-                    Style::default().fg(banner_color),
-                )),
-                Line::from(Span::styled(
-                    r" |  _ \|  _ \|  _|   ",
-                    Style::default().fg(banner_color),
-                )),
-                Line::from(Span::styled(
-                    " | |_) | |_) | |___  ",
-                    Style::default().fg(banner_color),
-                )),
-                Line::from(Span::styled(
-                    " |____/|____/|_____| ",
-                    Style::default().fg(banner_color),
+                    banner_style.add_modifier(Modifier::BOLD),
                 )),
+                Line::from(Span::styled(" | __ )| __ )| ____| ", banner_style)),
+                Line::from(Span::styled(r" |  _ \|  _ \|  _|   ", banner_style)),
+                Line::from(Span::styled(" | |_) | |_) | |___  ", banner_style)),
+                Line::from(Span::styled(" |____/|____/|_____| ", banner_style)),
             ])
             .block(Block::default().borders(Borders::NONE));
             f.render_widget(banner.alignment(Alignment::Center), chunks[1]);
@@ -98,21 +125,42 @@ pub async fn prompt(pool: &PgPool) -> Result<()> {
                 .block(Block::default().borders(Borders::ALL))
                 .alignment(Alignment::Center);
             f.render_widget(body, inner[1]);
+            if paused {
+                let hint = Paragraph::new("paused — space resumes, . step, r reseed, c clear")
+                    .alignment(Alignment::Center);
+                let mut row = chunks[3];
+                row.height = row.height.min(1);
+                f.render_widget(hint, row);
+            }
         })?;
 
-        let timeout = Duration::from_millis(100);
+        // wake up in time for the next frame at high FPS settings
+        let timeout = frame.map_or(Duration::from_millis(100), |d| {
+            d.min(Duration::from_millis(100))
+        });
         if event::poll(timeout)? {
             if let Event::Key(KeyEvent {
                 code, modifiers, ..
             }) = event::read()?
             {
+                let key = match (code, modifiers) {
+                    (KeyCode::Char(ch), KeyModifiers::NONE) => life_key(ch, &input, paused),
+                    _ => None,
+                };
+                if let (Some(key), Some(life)) = (key, life.as_mut()) {
+                    match key {
+                        LifeKey::TogglePause => paused = !paused,
+                        LifeKey::Step => life.step(),
+                        LifeKey::Reseed => life.seed_initial(),
+                        LifeKey::Clear => life.clear(),
+                    }
+                    continue;
+                }
                 match (code, modifiers) {
                     (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
-                        cleanup(&mut terminal)?;
                         return Err(anyhow!("cancelled"));
                     }
                     (KeyCode::Esc, _) => {
-                        cleanup(&mut terminal)?;
                         return Err(anyhow!("cancelled"));
                     }
                     (KeyCode::Backspace, _) => {
@@ -121,9 +169,8 @@ pub async fn prompt(pool: &PgPool) -> Result<()> {
                     (KeyCode::Enter, _) => {
                         let code = input.trim();
                         if !code.is_empty() {
-                            match crate::data::consume_invite(pool, code).await {
+                            match crate::data::consume_invite(pool, code, None).await {
                                 Ok(true) => {
-                                    cleanup(&mut terminal)?;
                                     return Ok(());
                                 }
                                 Ok(false) => {
@@ -137,18 +184,19 @@ pub async fn prompt(pool: &PgPool) -> Result<()> {
                         }
                     }
                     (KeyCode::Char(ch), KeyModifiers::NONE)
-                    | (KeyCode::Char(ch), KeyModifiers::SHIFT) => {
-                        // Invite codes are max 16 characters
-                        if input.len() < 16 {
-                            input.push(ch);
-                        }
+                    | (KeyCode::Char(ch), KeyModifiers::SHIFT)
+                        if input.len() < util::INVITE_CODE_LEN =>
+                    {
+                        input.push(ch);
                     }
                     _ => {}
                 }
             }
         }
-        // Step the life simulation at ~12 FPS
-        if last_step.elapsed() >= Duration::from_millis(80) {
+        let (Some(life), Some(frame)) = (life.as_mut(), frame) else {
+            continue;
+        };
+        if !paused && last_step.elapsed() >= frame {
             // handle terminal resize for life grid
             let sz = terminal.size()?;
             if sz != last_size {
@@ -166,10 +214,26 @@ pub async fn prompt(pool: &PgPool) -> Result<()> {
     }
 }
 
-fn cleanup(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
-    disable_raw_mode()?;
-    let w = terminal.backend_mut();
-    crossterm::execute!(w, LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn life_keys_stay_out_of_the_code() {
+        assert_eq!(life_key(' ', "", false), Some(LifeKey::TogglePause));
+        assert_eq!(life_key(' ', "ab", false), None);
+        // a code may start with these, so they only act while paused
+        assert_eq!(life_key('r', "", false), None);
+        assert_eq!(life_key('r', "", true), Some(LifeKey::Reseed));
+        assert_eq!(life_key('c', "", true), Some(LifeKey::Clear));
+        assert_eq!(life_key('.', "", true), Some(LifeKey::Step));
+        assert_eq!(life_key('x', "", true), None);
+    }
+
+    #[test]
+    fn frame_interval_clamps_fps() {
+        assert_eq!(frame_interval(10), Duration::from_millis(100));
+        assert_eq!(frame_interval(0), Duration::from_millis(1000));
+        assert_eq!(frame_interval(1000), frame_interval(FPS_MAX));
+    }
 }
@@ -1,41 +1,1176 @@
-#[cfg(test)]
-mod it_db {
-    use crate::data;
-    use rand::Rng;
-    use sqlx::postgres::PgPoolOptions;
-
-    #[tokio::test]
-    async fn leave_room_drops_membership() -> anyhow::Result<()> {
-        // Skip if DATABASE_URL not set
-        let database_url = match std::env::var("DATABASE_URL") {
-            Ok(v) => v,
-            Err(_) => return Ok(()),
+// DB-backed tests; each one skips when DATABASE_URL isn't set
+use crate::rooms::RoomAccess;
+use crate::{archive, data, realtime};
+use rand::Rng;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+
+// A migrated pool, or None to skip when DATABASE_URL isn't set.
+async fn test_pool() -> Option<PgPool> {
+    let database_url = std::env::var("DATABASE_URL").ok()?;
+    let pool = PgPoolOptions::new()
+        .max_connections(5)
+        .connect(&database_url)
+        .await
+        .expect("connect to DATABASE_URL");
+    sqlx::migrate!().run(&pool).await.expect("migrations apply");
+    Some(pool)
+}
+
+// Random per-test suffix for fingerprints and room names.
+fn test_tag() -> String {
+    format!("{:08x}", rand::thread_rng().gen::<u32>())
+}
+
+// The test user `test-fp-<tag><suffix>`; suffixes tell a test's users apart.
+async fn fixture_user(pool: &PgPool, tag: &str, suffix: &str) -> anyhow::Result<data::User> {
+    let fp = format!("test-fp-{}{}", tag, suffix);
+    data::upsert_user_by_fp(pool, &fp, "ed25519", None).await
+}
+
+#[tokio::test]
+async fn leave_room_drops_membership() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    // Random user and room
+    let tag = test_tag();
+    let user = fixture_user(&pool, &tag, "").await?;
+    let room_name = format!("it-{}", tag);
+    let (room, created) = data::ensure_room_exists(&pool, &room_name, user.id).await?;
+    assert!(created);
+
+    // Join
+    assert!(data::join_room(&pool, realtime::DEFAULT_CHANNEL, room.id, user.id).await?);
+    // re-join only bumps last_joined_at; no second join event
+    assert!(!data::join_room(&pool, realtime::DEFAULT_CHANNEL, room.id, user.id).await?);
+    let times = data::list_joined_rooms_with_times(&pool, user.id).await?;
+    let t = times.iter().find(|r| r.id == room.id).expect("joined");
+    assert!(t.first_joined_at < t.last_joined_at);
+    let joined = data::list_joined_rooms(&pool, user.id).await?;
+    assert!(joined.iter().any(|r| r.id == room.id));
+    let who = data::list_recent_members(&pool, room.id, 10).await?;
+    assert_eq!(who[0].first_joined_at, t.first_joined_at);
+    assert!(who[0].away_reason.is_none());
+    data::set_away(&pool, user.id, Some("bbl")).await?;
+    let who = data::list_recent_members(&pool, room.id, 10).await?;
+    assert_eq!(who[0].away_reason.as_deref(), Some("bbl"));
+
+    // Leave
+    let dropped = data::leave_room(&pool, realtime::DEFAULT_CHANNEL, room.id, user.id).await?;
+    assert!(dropped);
+    let joined2 = data::list_joined_rooms(&pool, user.id).await?;
+    assert!(!joined2.iter().any(|r| r.id == room.id));
+
+    // Idempotent leave
+    let dropped2 = data::leave_room(&pool, realtime::DEFAULT_CHANNEL, room.id, user.id).await?;
+    assert!(!dropped2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn message_view_by_id_hides_deleted() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let user = fixture_user(&pool, &tag, "").await?;
+    let room_name = format!("it-{}", tag);
+    let (room, _) = data::ensure_room_exists(&pool, &room_name, user.id).await?;
+
+    let out = data::insert_message(
+        &pool,
+        realtime::DEFAULT_CHANNEL,
+        room.id,
+        user.id,
+        "soon gone",
+    )
+    .await?;
+    let msg = out.message.expect("under the rate limit");
+    assert!(data::message_view_by_id(&pool, msg.id).await?.is_some());
+
+    // deleted between the NOTIFY and the UI's fetch
+    sqlx::query("update messages set deleted_at = now() where id = $1")
+        .bind(msg.id)
+        .execute(&pool)
+        .await?;
+    assert!(data::message_view_by_id(&pool, msg.id).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn system_owned_rooms_resist_creator_delete() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let system = data::ensure_system_user(&pool).await?;
+    assert_eq!(data::ensure_system_user(&pool).await?.id, system.id);
+    assert_eq!(system.fingerprint_sha256, data::SYSTEM_FP);
+
+    let room_name = format!("it-{}", test_tag());
+    let (room, _) = data::ensure_room_exists(&pool, &room_name, system.id).await?;
+    assert_eq!(
+        data::soft_delete_room_by_moderator(&pool, &room_name, system.id).await?,
+        None
+    );
+    // admins still can
+    assert_eq!(
+        data::soft_delete_room_any(&pool, &room_name).await?,
+        Some(room.id)
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn messages_survive_author_deletion() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let owner = fixture_user(&pool, &tag, "").await?;
+    let room_name = format!("it-{}", tag);
+    let (room, _) = data::ensure_room_exists(&pool, &room_name, owner.id).await?;
+
+    let author = fixture_user(&pool, &tag, "-b").await?;
+    let out =
+        data::insert_message(&pool, realtime::DEFAULT_CHANNEL, room.id, author.id, "hi").await?;
+    let msg = out.message.expect("under the rate limit");
+
+    sqlx::query("delete from users where id = $1")
+        .bind(author.id)
+        .execute(&pool)
+        .await?;
+    let view = data::message_view_by_id(&pool, msg.id)
+        .await?
+        .expect("kept");
+    assert_eq!(view.user_id, None);
+    assert_eq!(view.user_handle, data::DELETED_HANDLE);
+    let recent = data::recent_messages_view(&pool, room.id, 10).await?;
+    assert!(recent
+        .iter()
+        .any(|m| m.id == msg.id && m.user_handle == data::DELETED_HANDLE));
+    Ok(())
+}
+
+#[tokio::test]
+async fn keyset_pages_are_contiguous() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let user = fixture_user(&pool, &tag, "").await?;
+    let room_name = format!("it-{}", tag);
+    let (room, _) = data::ensure_room_exists(&pool, &room_name, user.id).await?;
+
+    // bypass the rate limit; pairs share a timestamp so id has to break the tie
+    let mut ids = Vec::new();
+    for i in 0..8 {
+        let id: i64 = sqlx::query_scalar(
+            r#"insert into messages(room_id, user_id, body, len, created_at)
+               values($1, $2, $3, 1, now() - make_interval(mins => $4))
+               returning id"#,
+        )
+        .bind(room.id)
+        .bind(user.id)
+        .bind(format!("m{}", i))
+        .bind(10 - i / 2)
+        .fetch_one(&pool)
+        .await?;
+        ids.push(id);
+    }
+
+    let mut page = data::recent_messages_view(&pool, room.id, 3).await?;
+    let mut seen: Vec<i64> = page.iter().map(|m| m.id).collect();
+    while let Some(oldest) = page.first() {
+        page = data::messages_before(&pool, room.id, oldest.cursor(), 3).await?;
+        assert!(page.len() <= 3);
+        let mut older: Vec<i64> = page.iter().map(|m| m.id).collect();
+        older.extend(seen);
+        seen = older;
+    }
+    assert_eq!(seen, ids);
+    Ok(())
+}
+
+#[tokio::test]
+async fn session_limit_counts_live_sessions() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let fp = format!("test-fp-{}", test_tag());
+    let stale = std::time::Duration::from_secs(90);
+    let first = data::open_session(&pool, &fp, 1, stale)
+        .await?
+        .expect("slot free");
+    assert_eq!(data::open_session(&pool, &fp, 1, stale).await?, None);
+    // unlimited ignores the count
+    let extra = data::open_session(&pool, &fp, 0, stale)
+        .await?
+        .expect("no limit");
+    data::close_session(&pool, extra).await?;
+
+    // a session that stopped heartbeating no longer counts
+    sqlx::query("update sessions set heartbeat_at = now() - interval '1 hour' where id = $1")
+        .bind(first)
+        .execute(&pool)
+        .await?;
+    let second = data::open_session(&pool, &fp, 1, stale)
+        .await?
+        .expect("stale swept");
+    data::heartbeat_session(&pool, second).await?;
+    data::close_session(&pool, second).await?;
+    let third = data::open_session(&pool, &fp, 1, stale)
+        .await?
+        .expect("closed frees it");
+    data::close_session(&pool, third).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn quiet_hours_reject_inserts_in_window() -> anyhow::Result<()> {
+    use crate::rooms::QuietHours;
+    use chrono::{Duration, NaiveTime, Timelike, Utc};
+
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let owner = fixture_user(&pool, &tag, "").await?;
+    let other = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), owner.id).await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+
+    // a window that is open right now at UTC+01:00 but would be closed at UTC
+    let local = Utc::now() + Duration::hours(1);
+    let time = |d: Duration| -> NaiveTime {
+        let t = (local + d).time();
+        NaiveTime::from_hms_opt(t.hour(), t.minute(), 0).unwrap()
+    };
+    let quiet = QuietHours {
+        start: time(-Duration::minutes(30)),
+        end: time(Duration::minutes(30)),
+        offset_mins: 60,
+    };
+    assert!(!data::set_room_quiet(&pool, room.id, Some(quiet), Some(other.id)).await?);
+    assert!(data::set_room_quiet(&pool, room.id, Some(quiet), Some(owner.id)).await?);
+
+    let out = data::insert_message(&pool, ch, room.id, owner.id, "hi").await?;
+    assert!(out.message.is_none());
+    assert_eq!(out.quiet_until, Some(quiet.end));
+
+    assert!(data::set_room_quiet(&pool, room.id, None, None).await?);
+    let out = data::insert_message(&pool, ch, room.id, owner.id, "hi").await?;
+    assert!(out.message.is_some());
+    assert_eq!(out.quiet_until, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn purge_removes_newest_and_logs() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let owner = fixture_user(&pool, &tag, "").await?;
+    let other = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), owner.id).await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    let mut sent = vec![];
+    for (user, body) in [(&owner, "one"), (&other, "two"), (&owner, "three")] {
+        let out = data::insert_message(&pool, ch, room.id, user.id, body).await?;
+        sent.push(out.message.expect("under rate limit").id);
+    }
+
+    let none = data::purge_messages(&pool, ch, room.id, other.id, data::PurgeCriteria::Last(1));
+    assert_eq!(none.await?, None);
+    let ids = data::purge_messages(&pool, ch, room.id, owner.id, data::PurgeCriteria::Last(1))
+        .await?
+        .expect("creator may purge");
+    assert_eq!(ids, vec![sent[2]]);
+    let ids = data::purge_messages(
+        &pool,
+        ch,
+        room.id,
+        owner.id,
+        data::PurgeCriteria::User(other.id),
+    )
+    .await?
+    .expect("creator may purge");
+    assert_eq!(ids, vec![sent[1]]);
+
+    let left = data::recent_messages_view(&pool, room.id, 10).await?;
+    assert_eq!(left.iter().map(|m| m.id).collect::<Vec<_>>(), vec![sent[0]]);
+    let logged: i64 = sqlx::query_scalar(
+        "select coalesce(sum(affected), 0)::bigint from moderation_log where room_id = $1",
+    )
+    .bind(room.id)
+    .fetch_one(&pool)
+    .await?;
+    assert_eq!(logged, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn edit_last_message_marks_newest_own() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let author = fixture_user(&pool, &tag, "").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), author.id).await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    assert!(data::edit_last_message(&pool, ch, room.id, author.id, "x")
+        .await?
+        .is_none());
+    let mut sent = vec![];
+    for body in ["one", "twoo"] {
+        let out = data::insert_message(&pool, ch, room.id, author.id, body).await?;
+        sent.push(out.message.expect("under rate limit").id);
+    }
+
+    let m = data::edit_last_message(&pool, ch, room.id, author.id, "two")
+        .await?
+        .expect("has a message");
+    assert_eq!(m.id, sent[1]);
+    assert!(m.edited_at.is_some());
+    let views = data::recent_messages_view(&pool, room.id, 10).await?;
+    let shown: Vec<(&str, bool)> = views.iter().map(|v| (v.body.as_str(), v.edited)).collect();
+    assert_eq!(shown, [("one", false), ("two", true)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn list_rooms_counts_members() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    let (busy, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let (empty, _) = data::ensure_room_exists(&pool, &format!("it-{}-e", tag), a.id).await?;
+    for u in [&a, &b] {
+        data::join_room(&pool, ch, busy.id, u.id).await?;
+    }
+
+    let rooms = data::list_rooms(&pool, a.id).await?;
+    let count = |id: i64| rooms.iter().find(|r| r.id == id).map(|r| r.members);
+    assert_eq!(count(busy.id), Some(2));
+    assert_eq!(count(empty.id), Some(0));
+    // the sidebar query only covers rooms the user has joined
+    let mine = data::room_member_counts(&pool, b.id).await?;
+    assert_eq!(mine, vec![(busy.id, 2)]);
+    data::soft_delete_room_by_moderator(&pool, &empty.name, a.id).await?;
+    let rooms = data::list_rooms(&pool, a.id).await?;
+    assert!(!rooms.iter().any(|r| r.id == empty.id));
+    Ok(())
+}
+
+#[tokio::test]
+async fn activity_buckets_cover_every_requested_room() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let user = fixture_user(&pool, &tag, "").await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    let (busy, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), user.id).await?;
+    let (quiet, _) = data::ensure_room_exists(&pool, &format!("it-{}-q", tag), user.id).await?;
+    for body in ["a", "b"] {
+        data::insert_message(&pool, ch, busy.id, user.id, body).await?;
+    }
+
+    let window = std::time::Duration::from_secs(30 * 60);
+    let got = data::room_activity_buckets(&pool, &[busy.id, quiet.id], 4, window).await?;
+    // just-sent messages land in the newest bucket
+    assert_eq!(
+        got,
+        vec![(busy.id, vec![0, 0, 0, 2]), (quiet.id, vec![0; 4])]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn soft_delete_message_checks_author() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let author = fixture_user(&pool, &tag, "").await?;
+    let other = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), author.id).await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    let out = data::insert_message(&pool, ch, room.id, author.id, "oops").await?;
+    let id = out.message.expect("under rate limit").id;
+
+    assert!(!data::soft_delete_message(&pool, ch, id, other.id).await?);
+    assert!(data::soft_delete_message(&pool, ch, id, author.id).await?);
+    // already gone
+    assert!(!data::soft_delete_message(&pool, ch, id, author.id).await?);
+    assert!(data::message_view_by_id(&pool, id).await?.is_none());
+
+    let window = std::time::Duration::from_secs(60);
+    assert!(!data::restore_message(&pool, ch, id, other.id, window).await?);
+    assert!(data::restore_message(&pool, ch, id, author.id, window).await?);
+    assert!(data::message_view_by_id(&pool, id).await?.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn unread_summary_covers_messages_after_mark() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let me = fixture_user(&pool, &tag, "").await?;
+    let other = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), me.id).await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    data::join_room(&pool, ch, room.id, me.id).await?;
+
+    // no mark yet: nothing to summarize
+    data::insert_message(&pool, ch, room.id, other.id, "before").await?;
+    assert!(data::unread_summary(&pool, room.id, me.id).await?.is_none());
+
+    let seen = data::recent_messages_view(&pool, room.id, 1).await?[0].id;
+    data::mark_read(&pool, room.id, me.id, seen).await?;
+    data::insert_message(&pool, ch, room.id, other.id, "one").await?;
+    data::insert_message(&pool, ch, room.id, me.id, "mine").await?;
+    data::insert_message(&pool, ch, room.id, other.id, "two").await?;
+    let s = data::unread_summary(&pool, room.id, me.id)
+        .await?
+        .expect("unread");
+    assert_eq!(s.after_id, seen);
+    assert_eq!(s.count, 2);
+    assert_eq!(s.senders, vec![other.handle.clone()]);
+
+    // marks only move forward
+    data::mark_read(&pool, room.id, me.id, i64::MAX).await?;
+    data::mark_read(&pool, room.id, me.id, seen).await?;
+    assert!(data::unread_summary(&pool, room.id, me.id).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn dms_resolve_handles_both_ways() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let from = fixture_user(&pool, &tag, "").await?;
+    let to = fixture_user(&pool, &tag, "-b").await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+
+    assert!(data::insert_dm(&pool, ch, from.id, "no-such-user", "hi")
+        .await?
+        .is_none());
+    let dm = data::insert_dm(&pool, ch, from.id, &to.handle, "hi")
+        .await?
+        .expect("recipient exists");
+    assert_eq!(
+        (dm.to_user_id, dm.from_handle.as_str()),
+        (to.id, from.handle.as_str())
+    );
+    let fetched = data::dm_by_id(&pool, dm.id).await?.expect("stored");
+    assert_eq!(fetched.body, "hi");
+    assert_eq!(fetched.to_handle, to.handle);
+    Ok(())
+}
+
+#[tokio::test]
+async fn invite_uses_count_down() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let code = data::create_invite(&pool, a.id, 2, None, None).await?;
+    assert!(data::consume_invite(&pool, &code, None).await?);
+    assert!(data::consume_invite(&pool, &code, None).await?);
+    // used up: the row is gone
+    assert!(!data::consume_invite(&pool, &code, None).await?);
+    assert!(!data::delete_invite(&pool, &code).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_invite_is_rejected() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let live = data::create_invite(&pool, a.id, 1, Some(chrono::Duration::hours(1)), None).await?;
+    let stale = data::create_invite(&pool, a.id, 1, Some(chrono::Duration::hours(1)), None).await?;
+    sqlx::query("update invites set expires_at = now() - interval '1 minute' where code=$1")
+        .bind(&stale)
+        .execute(&pool)
+        .await?;
+    assert!(!data::consume_invite(&pool, &stale, None).await?);
+    assert!(data::consume_invite(&pool, &live, None).await?);
+    // rejected codes stay listed until deleted
+    assert!(data::delete_invite(&pool, &stale).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn room_rate_counts_per_room() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let ch = realtime::DEFAULT_CHANNEL;
+    let (slow, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let (other, _) = data::ensure_room_exists(&pool, &format!("it-{}-o", tag), a.id).await?;
+    assert!(data::set_room_rate(&pool, slow.id, Some(1), Some(a.id)).await?);
+
+    let first = data::insert_message(&pool, ch, slow.id, a.id, "one").await?;
+    assert!(first.message.is_some());
+    assert_eq!(first.remaining, 0);
+    let second = data::insert_message(&pool, ch, slow.id, a.id, "two").await?;
+    assert!(second.message.is_none());
+    // the other room keeps the default limit and its own count
+    let elsewhere = data::insert_message(&pool, ch, other.id, a.id, "hi").await?;
+    assert!(elsewhere.message.is_some());
+    assert!(elsewhere.remaining > 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn ignores_round_trip() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    assert!(data::add_ignore(&pool, a.id, b.id).await?);
+    assert!(!data::add_ignore(&pool, a.id, b.id).await?);
+    assert_eq!(data::list_ignores(&pool, a.id).await?, vec![b.id]);
+    assert!(data::list_ignores(&pool, b.id).await?.is_empty());
+    assert!(data::remove_ignore(&pool, a.id, b.id).await?);
+    assert!(!data::remove_ignore(&pool, a.id, b.id).await?);
+    assert!(data::add_ignore(&pool, a.id, a.id).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn last_room_needs_membership_and_a_live_room() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    assert!(data::last_room(&pool, a.id).await?.is_none());
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    data::join_room(&pool, ch, room.id, a.id).await?;
+    data::set_last_room(&pool, a.id, room.id).await?;
+    assert_eq!(
+        data::last_room(&pool, a.id).await?.map(|r| r.id),
+        Some(room.id)
+    );
+    data::leave_room(&pool, ch, room.id, a.id).await?;
+    assert!(data::last_room(&pool, a.id).await?.is_none());
+    data::join_room(&pool, ch, room.id, a.id).await?;
+    data::soft_delete_room_any(&pool, &room.name).await?;
+    assert!(data::last_room(&pool, a.id).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn messages_around_centers_on_target() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let (other, _) = data::ensure_room_exists(&pool, &format!("it2-{}", tag), a.id).await?;
+    let mut ids = vec![];
+    for i in 0..6 {
+        let ins = data::insert_message(&pool, ch, room.id, a.id, &format!("m{}", i)).await?;
+        ids.push(ins.message.unwrap().id);
+    }
+    let got: Vec<i64> = data::messages_around(&pool, room.id, ids[1], 2)
+        .await?
+        .iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(got, ids[0..4]);
+    let got = data::messages_around(&pool, room.id, ids[5], 1).await?;
+    assert_eq!(got.iter().map(|m| m.id).collect::<Vec<_>>(), ids[4..6]);
+    assert!(data::messages_around(&pool, other.id, ids[1], 2)
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn replies_carry_a_quote_until_the_parent_goes() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let parent = data::insert_message(&pool, ch, room.id, a.id, "question?")
+        .await?
+        .message
+        .unwrap();
+    let reply = data::insert_reply(&pool, ch, room.id, a.id, "answer", parent.id)
+        .await?
+        .message
+        .unwrap();
+    let v = data::message_view_by_id(&pool, reply.id).await?.unwrap();
+    assert_eq!(v.reply_to, Some(parent.id));
+    assert_eq!(v.quote_handle.as_deref(), Some(a.handle.as_str()));
+    assert_eq!(v.quote_body.as_deref(), Some("question?"));
+    assert!(data::soft_delete_message(&pool, ch, parent.id, a.id).await?);
+    let v = data::message_view_by_id(&pool, reply.id).await?.unwrap();
+    assert_eq!(v.reply_to, Some(parent.id));
+    assert!(v.quote_body.is_none() && v.quote_handle.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn reactions_toggle_per_user() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let m = data::insert_message(&pool, ch, room.id, a.id, "hello")
+        .await?
+        .message
+        .unwrap();
+    assert_eq!(
+        data::toggle_reaction(&pool, ch, m.id, a.id, "👍").await?,
+        Some(true)
+    );
+    assert_eq!(
+        data::toggle_reaction(&pool, ch, m.id, b.id, "👍").await?,
+        Some(true)
+    );
+    assert_eq!(
+        data::toggle_reaction(&pool, ch, m.id, b.id, "🎉").await?,
+        Some(true)
+    );
+    let counts: Vec<(String, i64)> = data::reaction_counts(&pool, &[m.id])
+        .await?
+        .into_iter()
+        .map(|c| (c.emoji, c.n))
+        .collect();
+    assert_eq!(counts, vec![("👍".to_string(), 2), ("🎉".to_string(), 1)]);
+    assert_eq!(
+        data::toggle_reaction(&pool, ch, m.id, a.id, "👍").await?,
+        Some(false)
+    );
+    assert_eq!(data::reaction_counts(&pool, &[m.id]).await?[0].n, 1);
+    assert!(data::soft_delete_message(&pool, ch, m.id, a.id).await?);
+    assert_eq!(
+        data::toggle_reaction(&pool, ch, m.id, a.id, "👍").await?,
+        None
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn pins_are_creator_only_and_newest_first() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let mut ids = vec![];
+    for body in ["first", "second"] {
+        let m = data::insert_message(&pool, ch, room.id, b.id, body).await?;
+        ids.push(m.message.unwrap().id);
+    }
+    let pin = |id, on, by| data::set_pin(&pool, ch, room.id, id, on, by);
+    assert_eq!(
+        pin(ids[0], true, Some(b.id)).await?,
+        data::PinOutcome::Refused
+    );
+    assert_eq!(
+        pin(ids[0], true, Some(a.id)).await?,
+        data::PinOutcome::Changed
+    );
+    assert_eq!(pin(ids[1], true, None).await?, data::PinOutcome::Changed);
+    // a second pin keeps the original pin time, so the order below holds
+    assert_eq!(
+        pin(ids[0], true, Some(a.id)).await?,
+        data::PinOutcome::Unchanged
+    );
+    let pinned: Vec<i64> = data::list_pinned(&pool, room.id)
+        .await?
+        .iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(pinned, vec![ids[1], ids[0]]);
+    assert_eq!(
+        pin(ids[1], false, Some(a.id)).await?,
+        data::PinOutcome::Changed
+    );
+    assert_eq!(
+        pin(ids[1], false, Some(a.id)).await?,
+        data::PinOutcome::Unchanged
+    );
+    assert!(data::soft_delete_message(&pool, ch, ids[0], b.id).await?);
+    assert!(data::list_pinned(&pool, room.id).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn moderators_share_room_deletion() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let name = format!("it-{}", tag);
+    let (room, _) = data::ensure_room_exists(&pool, &name, a.id).await?;
+    assert!(data::is_moderator(&pool, room.id, a.id).await?);
+    assert!(!data::is_moderator(&pool, room.id, b.id).await?);
+    assert_eq!(
+        data::soft_delete_room_by_moderator(&pool, &name, b.id).await?,
+        None
+    );
+    // only the creator hands it out
+    assert!(!data::grant_moderator(&pool, room.id, b.id, b.id, Some(b.id)).await?);
+    assert!(data::grant_moderator(&pool, room.id, b.id, a.id, Some(a.id)).await?);
+    assert!(!data::grant_moderator(&pool, room.id, b.id, a.id, Some(a.id)).await?);
+    assert!(data::is_moderator(&pool, room.id, b.id).await?);
+    assert_eq!(
+        data::soft_delete_room_by_moderator(&pool, &name, b.id).await?,
+        Some(room.id)
+    );
+    let window = std::time::Duration::from_secs(60);
+    let ch = realtime::DEFAULT_CHANNEL;
+    assert!(data::restore_room(&pool, ch, room.id, Some(b.id), window).await?);
+    assert!(data::revoke_moderator(&pool, room.id, b.id, Some(a.id)).await?);
+    assert!(!data::is_moderator(&pool, room.id, b.id).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn kicks_need_authority_and_are_logged() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let c = fixture_user(&pool, &tag, "-c").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    for u in [&a, &b, &c] {
+        data::join_room(&pool, ch, room.id, u.id).await?;
+    }
+    assert!(!data::kick_member(&pool, ch, room.id, c.id, b.id, Some(b.id)).await?);
+    data::grant_moderator(&pool, room.id, b.id, a.id, Some(a.id)).await?;
+    assert!(!data::kick_member(&pool, ch, room.id, a.id, b.id, Some(b.id)).await?);
+    assert!(data::kick_member(&pool, ch, room.id, c.id, b.id, Some(b.id)).await?);
+    assert!(!data::kick_member(&pool, ch, room.id, c.id, b.id, Some(b.id)).await?);
+    let logged: Vec<(Option<i64>, Option<i64>, String)> =
+        sqlx::query_as("select actor_id, target_id, action from mod_actions where room_id = $1")
+            .bind(room.id)
+            .fetch_all(&pool)
+            .await?;
+    assert_eq!(logged, vec![(Some(b.id), Some(c.id), "kick".to_string())]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn mutes_refuse_sends_until_they_expire() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    assert!(!data::mute_member(&pool, room.id, a.id, b.id, Some(b.id), 5).await?);
+    assert!(data::mute_member(&pool, room.id, b.id, a.id, Some(a.id), 5).await?);
+    let out = data::insert_message(&pool, ch, room.id, b.id, "hi").await?;
+    assert!(out.message.is_none());
+    let left = out.muted_until.unwrap() - chrono::Utc::now();
+    assert!(left > chrono::Duration::minutes(4) && left <= chrono::Duration::minutes(5));
+
+    // an expired mute no longer counts, with nothing cleaning it up
+    sqlx::query(
+        "update room_mutes set muted_until = now() - interval '1 second' where room_id = $1",
+    )
+    .bind(room.id)
+    .execute(&pool)
+    .await?;
+    let out = data::insert_message(&pool, ch, room.id, b.id, "hi").await?;
+    assert!(out.message.is_some() && out.muted_until.is_none());
+    assert!(!data::mute_member(&pool, room.id, b.id, a.id, Some(a.id), 0).await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn protected_rooms_check_the_password_on_first_join() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let name = format!("it-{}", tag);
+    let joined = data::join_protected_room(&pool, ch, &name, a.id, Some("hunter2")).await?;
+    let data::JoinOutcome::Joined(room, true) = joined else {
+        panic!("creator should create the room: {:?}", joined);
+    };
+    let stored: Option<String> =
+        sqlx::query_scalar("select password_hash from rooms where id = $1")
+            .bind(room.id)
+            .fetch_one(&pool)
+            .await?;
+    assert!(stored.is_some_and(|h| h.starts_with("$argon2")));
+
+    for wrong in [None, Some("nope")] {
+        assert!(matches!(
+            data::join_protected_room(&pool, ch, &name, b.id, wrong).await?,
+            data::JoinOutcome::WrongPassword
+        ));
+    }
+    let joined = data::list_joined_rooms(&pool, b.id).await?;
+    assert!(!joined.iter().any(|r| r.id == room.id));
+    assert!(matches!(
+        data::join_protected_room(&pool, ch, &name, b.id, Some("hunter2")).await?,
+        data::JoinOutcome::Joined(..)
+    ));
+    // a member gets back in without it
+    assert!(matches!(
+        data::join_protected_room(&pool, ch, &name, b.id, None).await?,
+        data::JoinOutcome::Joined(..)
+    ));
+
+    // open rooms ignore the argument entirely
+    let open = format!("it-{}-open", tag);
+    data::ensure_room_exists(&pool, &open, a.id).await?;
+    assert!(matches!(
+        data::join_protected_room(&pool, ch, &open, b.id, None).await?,
+        data::JoinOutcome::Joined(..)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn guarded_rooms_are_listed_only_for_members() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let (open, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let locked =
+        match data::join_protected_room(&pool, ch, &format!("it-{}-p", tag), a.id, Some("pw"))
+            .await?
+        {
+            data::JoinOutcome::Joined(room, true) => room,
+            other => panic!("{:?}", other),
         };
+    let (invite, _) = data::ensure_room_exists(&pool, &format!("it-{}-i", tag), a.id).await?;
+    data::set_room_access(&pool, invite.id, RoomAccess::Invite, Some(a.id)).await?;
+    for room in [&open, &invite] {
+        data::join_room(&pool, ch, room.id, a.id).await?;
+    }
 
-        let pool = PgPoolOptions::new().max_connections(5).connect(&database_url).await?;
-        sqlx::migrate!().run(&pool).await?;
+    let ids = |rooms: Vec<i64>| [open.id, locked.id, invite.id].map(|id| rooms.contains(&id));
+    let listed = |viewer: i64| {
+        let pool = pool.clone();
+        async move {
+            let rooms = data::list_rooms(&pool, viewer).await?;
+            anyhow::Ok(rooms.into_iter().map(|r| r.id).collect::<Vec<_>>())
+        }
+    };
+    let found = |viewer: i64| {
+        let pool = pool.clone();
+        async move {
+            let rooms = data::user_rooms(&pool, a.id, viewer).await?;
+            anyhow::Ok(rooms.into_iter().map(|r| r.id).collect::<Vec<_>>())
+        }
+    };
+    // members see all three; an outsider sees only the open room
+    assert_eq!(ids(listed(a.id).await?), [true, true, true]);
+    assert_eq!(ids(found(a.id).await?), [true, true, true]);
+    assert_eq!(ids(listed(b.id).await?), [true, false, false]);
+    assert_eq!(ids(found(b.id).await?), [true, false, false]);
 
-        // Random user and room
-        let fp = format!("test-fp-{:08x}", rand::thread_rng().gen::<u32>());
-        let user = data::upsert_user_by_fp(&pool, &fp, "ed25519").await?;
-        let room_name = format!("it-{:08x}", rand::thread_rng().gen::<u32>());
-        let room = data::ensure_room_exists(&pool, &room_name, user.id).await?;
+    // joining with the password makes the room visible to b
+    data::join_protected_room(&pool, ch, &locked.name, b.id, Some("pw")).await?;
+    assert_eq!(ids(listed(b.id).await?), [true, true, false]);
+    assert_eq!(ids(found(b.id).await?), [true, true, false]);
+    Ok(())
+}
 
-        // Join
-        data::join_room(&pool, room.id, user.id).await?;
-        let joined = data::list_joined_rooms(&pool, user.id).await?;
-        assert!(joined.iter().any(|r| r.id == room.id));
+#[tokio::test]
+async fn invite_rooms_take_only_their_own_codes() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
 
-        // Leave
-        let dropped = data::leave_room(&pool, room.id, user.id).await?;
-        assert!(dropped);
-        let joined2 = data::list_joined_rooms(&pool, user.id).await?;
-        assert!(!joined2.iter().any(|r| r.id == room.id));
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let name = format!("it-{}", tag);
+    let (room, _) = data::ensure_room_exists(&pool, &name, a.id).await?;
+    let (other, _) = data::ensure_room_exists(&pool, &format!("it-{}-o", tag), a.id).await?;
+    assert_eq!(room.access, RoomAccess::Public);
+    assert!(!data::set_room_access(&pool, room.id, RoomAccess::Invite, Some(b.id)).await?);
+    assert!(data::set_room_access(&pool, room.id, RoomAccess::Invite, Some(a.id)).await?);
 
-        // Idempotent leave
-        let dropped2 = data::leave_room(&pool, room.id, user.id).await?;
-        assert!(!dropped2);
-        Ok(())
+    // refused: no code, a login code, another room's code
+    let login = data::create_invite(&pool, a.id, 1, None, None).await?;
+    let elsewhere = data::create_invite(&pool, a.id, 1, None, Some(other.id)).await?;
+    for code in [None, Some(login.as_str()), Some(elsewhere.as_str())] {
+        assert!(matches!(
+            data::join_protected_room(&pool, ch, &name, b.id, code).await?,
+            data::JoinOutcome::WrongPassword
+        ));
     }
+    let joined = data::list_joined_rooms(&pool, b.id).await?;
+    assert!(!joined.iter().any(|r| r.id == room.id));
+    // ...and a room code doesn't open the login gate
+    let code = data::create_invite(&pool, a.id, 1, None, Some(room.id)).await?;
+    assert!(!data::consume_invite(&pool, &code, None).await?);
+    // ...nor become the password of a room that doesn't exist yet
+    let missing = format!("it-{}-m", tag);
+    assert!(matches!(
+        data::join_protected_room(&pool, ch, &missing, b.id, Some(&code)).await?,
+        data::JoinOutcome::WrongPassword
+    ));
+    assert!(data::room_by_name(&pool, &missing).await?.is_none());
+
+    // allowed: the room's own code, spent by the join
+    assert!(matches!(
+        data::join_protected_room(&pool, ch, &name, b.id, Some(&code)).await?,
+        data::JoinOutcome::Joined(..)
+    ));
+    let joined = data::list_joined_rooms(&pool, b.id).await?;
+    assert!(joined.iter().any(|r| r.id == room.id));
+    let left: Option<i32> = sqlx::query_scalar("select uses_left from invites where code = $1")
+        .bind(&code)
+        .fetch_optional(&pool)
+        .await?;
+    assert!(left.is_none());
+    // members come back without one; the unused codes were never spent
+    assert!(matches!(
+        data::join_protected_room(&pool, ch, &name, b.id, None).await?,
+        data::JoinOutcome::Joined(..)
+    ));
+    assert!(data::consume_invite(&pool, &login, None).await?);
+    Ok(())
 }
 
+#[tokio::test]
+async fn export_streams_live_messages_oldest_first() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let ch = realtime::DEFAULT_CHANNEL;
+    let a = fixture_user(&pool, &tag, "").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let mut ids = vec![];
+    for body in ["old", "gone", "new"] {
+        let out = data::insert_message(&pool, ch, room.id, a.id, body).await?;
+        ids.push(out.message.expect("sent").id);
+    }
+    assert!(data::soft_delete_message(&pool, ch, ids[1], a.id).await?);
+    sqlx::query("update messages set created_at = now() - interval '2 days' where id = $1")
+        .bind(ids[0])
+        .execute(&pool)
+        .await?;
+
+    let mut out = Vec::new();
+    assert_eq!(
+        archive::export_room(&pool, room.id, None, &mut out).await?,
+        2
+    );
+    let lines: Vec<archive::ArchivedMessage> = String::from_utf8(out)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+    assert_eq!(
+        lines.iter().map(|m| m.body.as_str()).collect::<Vec<_>>(),
+        ["old", "new"]
+    );
+    assert_eq!(lines[1].id, ids[2]);
+    assert_eq!(lines[1].user_handle, a.handle);
+
+    let since = chrono::Utc::now() - chrono::Duration::days(1);
+    let mut out = Vec::new();
+    archive::export_room(&pool, room.id, Some(since), &mut out).await?;
+    assert_eq!(String::from_utf8(out)?.lines().count(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn import_keeps_timestamps_and_is_all_or_nothing() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+    let ghost = format!("imp-{}", tag);
+    let dump = format!(
+        concat!(
+            r#"{{"id":1,"user_handle":"{}","body":"first","created_at":"2020-01-02T03:04:05Z"}}"#,
+            "\n\n",
+            r#"{{"id":2,"user_handle":"{}","body":"second","created_at":"2020-01-02T03:05:00Z"}}"#,
+            "\n",
+            r#"{{"id":3,"user_handle":"{}","body":"orphan","created_at":"2020-01-02T03:06:00Z"}}"#,
+            "\n"
+        ),
+        a.handle,
+        ghost,
+        data::DELETED_HANDLE
+    );
+
+    // a bad last line rolls back the lines before it
+    let broken = format!("{}not json\n", dump);
+    assert!(archive::import_room(&pool, room.id, broken.as_bytes())
+        .await
+        .is_err());
+    assert!(data::recent_messages_view(&pool, room.id, 10)
+        .await?
+        .is_empty());
+    assert!(data::get_user_by_handle(&pool, &ghost).await?.is_none());
+
+    assert_eq!(
+        archive::import_room(&pool, room.id, dump.as_bytes()).await?,
+        3
+    );
+    let msgs = data::recent_messages_view(&pool, room.id, 10).await?;
+    let got: Vec<(&str, &str, String)> = msgs
+        .iter()
+        .map(|m| {
+            (
+                m.user_handle.as_str(),
+                m.body.as_str(),
+                m.created_at.to_rfc3339(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        got,
+        vec![
+            (
+                a.handle.as_str(),
+                "first",
+                "2020-01-02T03:04:05+00:00".into()
+            ),
+            (ghost.as_str(), "second", "2020-01-02T03:05:00+00:00".into()),
+            (
+                data::DELETED_HANDLE,
+                "orphan",
+                "2020-01-02T03:06:00+00:00".into()
+            ),
+        ]
+    );
+    assert_eq!(msgs[0].user_id, Some(a.id));
+    assert!(msgs[2].user_id.is_none());
+    let placeholder = data::get_user_by_handle(&pool, &ghost)
+        .await?
+        .expect("created");
+    assert!(placeholder
+        .fingerprint_sha256
+        .starts_with(data::IMPORT_FP_PREFIX));
+    Ok(())
+}
+
+async fn next_event(
+    listener: &mut sqlx::postgres::PgListener,
+) -> anyhow::Result<Option<realtime::Event>> {
+    let n = tokio::time::timeout(std::time::Duration::from_secs(5), listener.recv())
+        .await
+        .expect("notified in time")?;
+    Ok(realtime::parse_payload(n.payload()))
+}
+
+#[tokio::test]
+async fn inserts_notify_listeners_without_a_trigger() -> anyhow::Result<()> {
+    let Some(pool) = test_pool().await else {
+        return Ok(());
+    };
+
+    let tag = test_tag();
+    let a = fixture_user(&pool, &tag, "").await?;
+    let b = fixture_user(&pool, &tag, "-b").await?;
+    let (room, _) = data::ensure_room_exists(&pool, &format!("it-{}", tag), a.id).await?;
+
+    // a private channel, so other tests' traffic can't be mistaken for ours
+    let ch = format!("it_{}", tag);
+    assert!(realtime::valid_channel(&ch));
+    let mut listener = sqlx::postgres::PgListener::connect_with(&pool).await?;
+    listener.listen(&ch).await?;
+
+    let m = data::insert_message(&pool, &ch, room.id, a.id, "ping")
+        .await?
+        .message
+        .expect("sent");
+    assert!(matches!(
+        next_event(&mut listener).await?,
+        Some(realtime::Event::Message { id, room_id }) if id == m.id && room_id == room.id
+    ));
+
+    let dm = data::insert_dm(&pool, &ch, a.id, &b.handle, "psst")
+        .await?
+        .expect("recipient exists");
+    assert!(matches!(
+        next_event(&mut listener).await?,
+        Some(realtime::Event::Dm { id, to_user }) if id == dm.id && to_user == b.id
+    ));
+    Ok(())
+}
@@ -1,8 +1,10 @@
 use ratatui::buffer::Buffer;
 use ratatui::layout::Rect;
-use ratatui::style::{Color, Style};
+use ratatui::style::Color;
 use ratatui::widgets::Widget;
 
+use crate::theme::{self, ColorLevel};
+
 // Simple LCG RNG to avoid external deps
 #[derive(Clone)]
 struct Lcg(u64);
@@ -26,6 +28,12 @@ impl Lcg {
     }
 }
 
+// ~12 FPS for the chat screensaver; the invite screen uses BBS_INVITE_FPS.
+pub const STEP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(80);
+
+// Names accepted by `Life::seed_pattern`.
+const PATTERNS: &[&str] = &["glider", "lwss", "blinker", "toad", "beacon"];
+
 pub struct Life {
     pub width: usize,
     pub height: usize,
@@ -117,7 +125,7 @@ impl Life {
     pub fn maybe_spawn(&mut self) {
         // More frequent spawns to keep things active
         // Every ~30 ticks (~2.4s at 12 FPS), ~66% chance to spawn something
-        if self.tick % 30 == 0 && self.rng.chance(2, 3) {
+        if self.tick.is_multiple_of(30) && self.rng.chance(2, 3) {
             let choice = self.rng.gen_range(0, 6);
             match choice {
                 0 | 1 => self.spawn_glider_inward(),
@@ -276,6 +284,27 @@ impl Life {
         self.seed_beacon(x, y);
     }
 
+    // Gallery API for demos and tests; nothing in the app places patterns by name yet.
+    #[allow(dead_code)]
+    pub fn pattern_names() -> &'static [&'static str] {
+        PATTERNS
+    }
+
+    // Place a named pattern with its top-left at (x, y); `dir` picks the rotation
+    // (ignored by the beacon). False for an unknown name. Cells off the grid are dropped.
+    #[allow(dead_code)]
+    pub fn seed_pattern(&mut self, name: &str, x: usize, y: usize, dir: u8) -> bool {
+        match name {
+            "glider" => self.seed_glider(x, y, dir),
+            "lwss" => self.seed_lwss(x, y, dir),
+            "blinker" => self.seed_blinker(x, y, dir),
+            "toad" => self.seed_toad(x, y, dir),
+            "beacon" => self.seed_beacon(x, y),
+            _ => return false,
+        }
+        true
+    }
+
     pub fn seed_glider(&mut self, x: usize, y: usize, dir: u8) {
         // Base glider (moves down-right):
         // . # .
@@ -311,7 +340,7 @@ impl Life {
 
     pub fn seed_blinker(&mut self, x: usize, y: usize, dir: u8) {
         // 3 in a line, horizontal if dir==0 else vertical
-        if dir % 2 == 0 {
+        if dir.is_multiple_of(2) {
             for dx in 0..3 {
                 self.set(x + dx, y, true);
             }
@@ -360,7 +389,7 @@ impl Life {
         // . ###
         // ## .
         let pts = [(1, 0), (2, 0), (3, 0), (0, 1), (1, 1), (2, 1)];
-        if dir % 2 == 0 {
+        if dir.is_multiple_of(2) {
             for (dx, dy) in pts {
                 self.set(x + dx, y + dy, true);
             }
@@ -386,6 +415,7 @@ impl Life {
 pub struct LifeWidget<'a> {
     pub life: &'a Life,
     pub color: Color,
+    pub level: ColorLevel,
 }
 
 impl<'a> LifeWidget<'a> {
@@ -393,15 +423,21 @@ impl<'a> LifeWidget<'a> {
         Self {
             life,
             color: Color::DarkGray,
+            level: ColorLevel::TrueColor,
         }
     }
+
+    pub fn color_level(mut self, level: ColorLevel) -> Self {
+        self.level = level;
+        self
+    }
 }
 
 impl<'a> Widget for LifeWidget<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let w = self.life.width.min(area.width as usize);
         let h = self.life.height.min(area.height as usize);
-        let style = Style::default().fg(self.color);
+        let style = theme::fg(self.level, self.color);
         for y in 0..h {
             for x in 0..w {
                 if self.life.get(x, y) {
@@ -415,3 +451,38 @@ impl<'a> Widget for LifeWidget<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live(life: &Life) -> usize {
+        (0..life.height)
+            .flat_map(|y| (0..life.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| life.get(x, y))
+            .count()
+    }
+
+    #[test]
+    fn seeds_every_named_pattern() {
+        let mut life = Life::new(20, 20);
+        for name in Life::pattern_names() {
+            life.clear();
+            assert!(life.seed_pattern(name, 5, 5, 0), "{}", name);
+            assert!(live(&life) > 0, "{}", name);
+        }
+        assert!(!life.seed_pattern("nope", 5, 5, 0));
+    }
+
+    #[test]
+    fn blinker_oscillates() {
+        let mut life = Life::new(10, 10);
+        life.clear();
+        life.seed_pattern("blinker", 3, 4, 0);
+        life.step();
+        assert!(life.get(4, 3) && life.get(4, 4) && life.get(4, 5));
+        assert_eq!(live(&life), 3);
+        life.step();
+        assert!(life.get(3, 4) && life.get(4, 4) && life.get(5, 4));
+    }
+}
@@ -0,0 +1,1468 @@
+// in-memory Store for UI tests; mirrors the SQL semantics closely enough to drive
+// commands, the send path, and realtime events without Postgres
+use anyhow::{anyhow, bail, Result};
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::data::{
+    self, Announcement, DirectMessage, Feedback, InsertOutcome, Invite, JoinOutcome,
+    MemberFingerprint, Message, MessageCursor, MessageView, PinOutcome, PurgeCriteria,
+    ReactionCount, Room, RoomJoined, RoomListing, RoomStats, RoomSummary, ServerInfo,
+    UnreadSummary, User, UserMessage, WhoSummary,
+};
+use crate::realtime::Event;
+use crate::rooms::{self, QuietHours, RoomAccess};
+use crate::store::Store;
+
+struct Member {
+    room_id: i64,
+    user_id: i64,
+    first_joined_at: DateTime<Utc>,
+    last_joined_at: DateTime<Utc>,
+    last_read_message_id: Option<i64>,
+}
+
+struct Stored {
+    id: i64,
+    user_id: i64,
+    body: String,
+    created_at: DateTime<Utc>,
+}
+
+struct Mem {
+    next_id: i64,
+    rate_per_min: i64,
+    // users whose fingerprint matches BBS_ADMIN_FP in the real store
+    admins: Vec<i64>,
+    users: Vec<User>,
+    rooms: Vec<Room>,
+    members: Vec<Member>,
+    messages: Vec<Message>,
+    announcements: Vec<Stored>,
+    dms: Vec<DirectMessage>,
+    // (report, handled)
+    feedback: Vec<(Stored, bool)>,
+    invites: Vec<Invite>,
+    signatures: HashMap<i64, String>,
+    taglines: HashMap<i64, String>,
+    // (user, ignored user)
+    ignores: Vec<(i64, i64)>,
+    last_rooms: HashMap<i64, i64>,
+    // reply id -> parent id (messages.reply_to)
+    replies: HashMap<i64, i64>,
+    // (message, user, emoji), oldest first
+    reactions: Vec<(i64, i64, String)>,
+    // message id -> pinned_at
+    pins: HashMap<i64, DateTime<Utc>>,
+    // (room, user) from /mod
+    moderators: Vec<(i64, i64)>,
+    // (room, user) -> muted_until
+    mutes: HashMap<(i64, i64), DateTime<Utc>>,
+    // room -> rooms.password_hash
+    room_passwords: HashMap<i64, String>,
+    settings: HashMap<String, String>,
+    // what Postgres would have NOTIFYed, for feeding back into apply_event
+    events: Vec<Event>,
+}
+
+pub struct MemStore {
+    mem: RefCell<Mem>,
+}
+
+impl MemStore {
+    pub fn new() -> Self {
+        MemStore {
+            mem: RefCell::new(Mem {
+                next_id: 1,
+                rate_per_min: 10,
+                admins: vec![],
+                users: vec![],
+                rooms: vec![],
+                members: vec![],
+                messages: vec![],
+                announcements: vec![],
+                dms: vec![],
+                feedback: vec![],
+                invites: vec![],
+                signatures: HashMap::new(),
+                taglines: HashMap::new(),
+                ignores: vec![],
+                last_rooms: HashMap::new(),
+                replies: HashMap::new(),
+                reactions: vec![],
+                pins: HashMap::new(),
+                moderators: vec![],
+                mutes: HashMap::new(),
+                room_passwords: HashMap::new(),
+                settings: HashMap::new(),
+                events: vec![],
+            }),
+        }
+    }
+
+    // Server-side per-minute limit (BBS_RATE_PER_MIN in the real store).
+    pub fn with_rate(self, per_min: i64) -> Self {
+        self.mem.borrow_mut().rate_per_min = per_min;
+        self
+    }
+
+    pub fn add_user(&self, handle: &str) -> User {
+        let mut m = self.mem.borrow_mut();
+        let id = m.id();
+        let user = User {
+            id,
+            fingerprint_sha256: format!("fp-{}", handle),
+            pubkey_type: "ed25519".into(),
+            handle: handle.into(),
+            created_at: Utc::now(),
+            last_seen_at: Utc::now(),
+            key_comment: None,
+            away_reason: None,
+        };
+        m.users.push(user.clone());
+        user
+    }
+
+    pub fn set_last_seen(&self, user_id: i64, at: DateTime<Utc>) {
+        let mut m = self.mem.borrow_mut();
+        if let Some(u) = m.users.iter_mut().find(|u| u.id == user_id) {
+            u.last_seen_at = at;
+        }
+    }
+
+    pub fn set_pubkey_type(&self, user_id: i64, pubkey_type: &str) {
+        let mut m = self.mem.borrow_mut();
+        if let Some(u) = m.users.iter_mut().find(|u| u.id == user_id) {
+            u.pubkey_type = pubkey_type.into();
+        }
+    }
+
+    pub fn set_first_joined(&self, room_id: i64, user_id: i64, at: DateTime<Utc>) {
+        let mut m = self.mem.borrow_mut();
+        let rm = m
+            .members
+            .iter_mut()
+            .find(|rm| rm.room_id == room_id && rm.user_id == user_id);
+        if let Some(rm) = rm {
+            rm.first_joined_at = at;
+        }
+    }
+
+    pub fn make_admin(&self, user_id: i64) {
+        self.mem.borrow_mut().admins.push(user_id);
+    }
+
+    pub fn last_room_id(&self, user_id: i64) -> Option<i64> {
+        self.mem.borrow().last_rooms.get(&user_id).copied()
+    }
+
+    fn insert(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+        reply_to: Option<i64>,
+    ) -> Result<InsertOutcome> {
+        let mut m = self.mem.borrow_mut();
+        let minute_ago = Utc::now() - chrono::Duration::minutes(1);
+        let recent = m
+            .messages
+            .iter()
+            .filter(|msg| {
+                msg.user_id == user_id && msg.room_id == room_id && msg.created_at > minute_ago
+            })
+            .count() as i64;
+        let limit = m
+            .rooms
+            .iter()
+            .find(|r| r.id == room_id)
+            .and_then(|r| r.rate_per_min)
+            .map_or(m.rate_per_min, i64::from);
+        let quiet = m
+            .rooms
+            .iter()
+            .find(|r| r.id == room_id)
+            .and_then(|r| r.quiet_hours())
+            .filter(|q| !m.admins.contains(&user_id) && q.active_at(Utc::now()));
+        let muted = m
+            .mutes
+            .get(&(room_id, user_id))
+            .copied()
+            .filter(|t| *t > Utc::now());
+        if quiet.is_some() || muted.is_some() {
+            return Ok(InsertOutcome {
+                message: None,
+                remaining: (limit - recent).max(0),
+                quiet_until: quiet.map(|q| q.end),
+                muted_until: muted,
+            });
+        }
+        if recent >= limit {
+            return Ok(InsertOutcome {
+                message: None,
+                remaining: 0,
+                quiet_until: None,
+                muted_until: None,
+            });
+        }
+        let msg = Message {
+            id: m.id(),
+            room_id,
+            user_id,
+            body: body.into(),
+            created_at: Utc::now(),
+            deleted_at: None,
+            edited_at: None,
+        };
+        m.messages.push(msg.clone());
+        if let Some(parent) = reply_to {
+            m.replies.insert(msg.id, parent);
+        }
+        m.events.push(Event::Message {
+            id: msg.id,
+            room_id,
+        });
+        Ok(InsertOutcome {
+            message: Some(msg),
+            remaining: (limit - recent - 1).max(0),
+            quiet_until: None,
+            muted_until: None,
+        })
+    }
+
+    pub fn take_events(&self) -> Vec<Event> {
+        std::mem::take(&mut self.mem.borrow_mut().events)
+    }
+
+    pub fn delete_message(&self, id: i64) {
+        let mut m = self.mem.borrow_mut();
+        if let Some(msg) = m.messages.iter_mut().find(|msg| msg.id == id) {
+            msg.deleted_at = Some(Utc::now());
+        }
+    }
+
+    // Drop the user row but keep their messages, like the set-null FK
+    pub fn delete_user(&self, id: i64) {
+        self.mem.borrow_mut().users.retain(|u| u.id != id);
+    }
+}
+
+impl Mem {
+    fn id(&mut self) -> i64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn handle(&self, user_id: i64) -> String {
+        self.users
+            .iter()
+            .find(|u| u.id == user_id)
+            .map(|u| u.handle.clone())
+            .unwrap_or_default()
+    }
+
+    // Creator or granted moderator, like data::is_moderator.
+    fn moderates(&self, room_id: i64, user_id: i64) -> bool {
+        self.rooms
+            .iter()
+            .any(|r| r.id == room_id && r.created_by == user_id)
+            || self.moderators.contains(&(room_id, user_id))
+    }
+
+    fn view(&self, msg: &Message) -> MessageView {
+        let author = self.users.iter().find(|u| u.id == msg.user_id);
+        let reply_to = self.replies.get(&msg.id).copied();
+        let parent = reply_to.and_then(|id| {
+            self.messages
+                .iter()
+                .find(|p| p.id == id && p.deleted_at.is_none())
+        });
+        MessageView {
+            id: msg.id,
+            room_id: msg.room_id,
+            user_id: author.map(|u| u.id),
+            user_handle: author.map_or_else(|| data::DELETED_HANDLE.into(), |u| u.handle.clone()),
+            tagline: self.taglines.get(&msg.user_id).cloned(),
+            body: msg.body.clone(),
+            created_at: msg.created_at,
+            edited: msg.edited_at.is_some(),
+            reply_to,
+            quote_handle: parent.map(|p| {
+                self.users
+                    .iter()
+                    .find(|u| u.id == p.user_id)
+                    .map_or_else(|| data::DELETED_HANDLE.into(), |u| u.handle.clone())
+            }),
+            quote_body: parent.map(|p| p.body.clone()),
+        }
+    }
+
+    // Newest `limit` live messages older than `before`, oldest first.
+    fn page(&self, room_id: i64, before: Option<MessageCursor>, limit: i64) -> Vec<MessageView> {
+        let mut live: Vec<&Message> = self
+            .messages
+            .iter()
+            .filter(|msg| msg.room_id == room_id && msg.deleted_at.is_none())
+            .filter(|msg| before.is_none_or(|c| (msg.created_at, msg.id) < (c.created_at, c.id)))
+            .collect();
+        live.sort_by_key(|msg| (msg.created_at, msg.id));
+        let skip = live.len().saturating_sub(limit as usize);
+        live.into_iter()
+            .skip(skip)
+            .map(|msg| self.view(msg))
+            .collect()
+    }
+
+    // consume_invite without the membership: one use of a live code scoped to
+    // `room_id` (None for login codes); the last use removes it.
+    fn spend_invite(&mut self, code: &str, room_id: Option<i64>) -> bool {
+        let now = Utc::now();
+        let Some(at) = self.invites.iter().position(|i| {
+            i.code == code && i.room_id == room_id && i.expires_at.is_none_or(|t| t >= now)
+        }) else {
+            return false;
+        };
+        if self.invites[at].uses_left > 1 {
+            self.invites[at].uses_left -= 1;
+        } else {
+            self.invites.remove(at);
+        }
+        true
+    }
+
+    fn room_code_is_live(&self, code: &str) -> bool {
+        let now = Utc::now();
+        self.invites
+            .iter()
+            .any(|i| i.code == code && i.room_id.is_some() && i.expires_at.is_none_or(|t| t >= now))
+    }
+
+    // data::VISIBLE_TO_VIEWER: guarded rooms only for their members
+    fn visible_to(&self, room_id: i64, viewer: i64) -> bool {
+        let guarded = self.room_passwords.contains_key(&room_id)
+            || self
+                .rooms
+                .iter()
+                .any(|r| r.id == room_id && r.access == RoomAccess::Invite);
+        !guarded
+            || self
+                .members
+                .iter()
+                .any(|rm| rm.room_id == room_id && rm.user_id == viewer)
+    }
+
+    fn live_room(&self, id: i64) -> Option<&Room> {
+        self.rooms.iter().find(|r| r.id == id && !r.is_deleted)
+    }
+
+    fn joined(&self, user_id: i64) -> Vec<RoomSummary> {
+        let mut out: Vec<RoomSummary> = self
+            .members
+            .iter()
+            .filter(|rm| rm.user_id == user_id)
+            .filter_map(|rm| self.live_room(rm.room_id))
+            .map(|r| RoomSummary {
+                id: r.id,
+                name: r.name.clone(),
+            })
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+}
+
+impl Store for MemStore {
+    async fn get_user_by_handle(&self, handle: &str) -> Result<Option<User>> {
+        let m = self.mem.borrow();
+        Ok(m.users.iter().find(|u| u.handle == handle).cloned())
+    }
+
+    async fn user_handle(&self, user_id: i64) -> Result<Option<String>> {
+        let m = self.mem.borrow();
+        Ok(m.users
+            .iter()
+            .find(|u| u.id == user_id)
+            .map(|u| u.handle.clone()))
+    }
+
+    async fn change_handle(&self, user_id: i64, new_handle: &str) -> Result<User> {
+        let mut m = self.mem.borrow_mut();
+        if m.users
+            .iter()
+            .any(|u| u.handle == new_handle && u.id != user_id)
+        {
+            bail!("handle taken");
+        }
+        let u = m
+            .users
+            .iter_mut()
+            .find(|u| u.id == user_id)
+            .ok_or_else(|| anyhow!("no such user"))?;
+        u.handle = new_handle.into();
+        Ok(u.clone())
+    }
+
+    async fn ensure_room_exists(&self, name: &str, created_by: i64) -> Result<(Room, bool)> {
+        let mut m = self.mem.borrow_mut();
+        if let Some(r) = m.rooms.iter().find(|r| r.name == name) {
+            if r.is_deleted {
+                return Err(anyhow!("room_deleted"));
+            }
+            return Ok((r.clone(), false));
+        }
+        let room = Room {
+            id: m.id(),
+            name: name.into(),
+            created_by,
+            is_deleted: false,
+            created_at: Utc::now(),
+            deleted_at: None,
+            color: None,
+            quiet_start: None,
+            quiet_end: None,
+            quiet_offset_mins: 0,
+            topic: None,
+            rate_per_min: None,
+            access: RoomAccess::Public,
+        };
+        m.rooms.push(room.clone());
+        Ok((room, true))
+    }
+
+    async fn join_room(&self, _channel: &str, room_id: i64, user_id: i64) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let existing = m
+            .members
+            .iter_mut()
+            .find(|rm| rm.room_id == room_id && rm.user_id == user_id);
+        if let Some(rm) = existing {
+            rm.last_joined_at = Utc::now();
+            return Ok(false);
+        }
+        m.members.push(Member {
+            room_id,
+            user_id,
+            first_joined_at: Utc::now(),
+            last_joined_at: Utc::now(),
+            last_read_message_id: None,
+        });
+        m.events.push(Event::Joined { room_id, user_id });
+        Ok(true)
+    }
+
+    async fn join_protected_room(
+        &self,
+        channel: &str,
+        name: &str,
+        user_id: i64,
+        secret: Option<&str>,
+    ) -> Result<JoinOutcome> {
+        let missing = !self.mem.borrow().rooms.iter().any(|r| r.name == name);
+        if missing && secret.is_some_and(|s| self.mem.borrow().room_code_is_live(s)) {
+            return Ok(JoinOutcome::WrongPassword);
+        }
+        let (room, created) = self.ensure_room_exists(name, user_id).await?;
+        if created {
+            if let Some(p) = secret {
+                let hash = rooms::hash_room_password(p)?;
+                self.mem.borrow_mut().room_passwords.insert(room.id, hash);
+            }
+        } else if room.created_by != user_id {
+            let mut m = self.mem.borrow_mut();
+            let member = m
+                .members
+                .iter()
+                .any(|rm| rm.room_id == room.id && rm.user_id == user_id);
+            if !member && room.access == RoomAccess::Invite {
+                if !secret.is_some_and(|code| m.spend_invite(code, Some(room.id))) {
+                    return Ok(JoinOutcome::WrongPassword);
+                }
+            } else if let Some(stored) = m.room_passwords.get(&room.id).filter(|_| !member) {
+                if !secret.is_some_and(|p| rooms::verify_room_password(p, stored)) {
+                    return Ok(JoinOutcome::WrongPassword);
+                }
+            }
+        }
+        self.join_room(channel, room.id, user_id).await?;
+        Ok(JoinOutcome::Joined(room, created))
+    }
+
+    async fn leave_room(&self, _channel: &str, room_id: i64, user_id: i64) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let before = m.members.len();
+        m.members
+            .retain(|rm| !(rm.room_id == room_id && rm.user_id == user_id));
+        let left = m.members.len() < before;
+        if left {
+            m.events.push(Event::Left { room_id, user_id });
+        }
+        Ok(left)
+    }
+
+    async fn list_joined_rooms(&self, user_id: i64) -> Result<Vec<RoomSummary>> {
+        Ok(self.mem.borrow().joined(user_id))
+    }
+
+    async fn list_joined_rooms_with_times(&self, user_id: i64) -> Result<Vec<RoomJoined>> {
+        let m = self.mem.borrow();
+        let mut out: Vec<RoomJoined> = m
+            .members
+            .iter()
+            .filter(|rm| rm.user_id == user_id)
+            .filter_map(|rm| {
+                m.live_room(rm.room_id).map(|r| RoomJoined {
+                    id: r.id,
+                    name: r.name.clone(),
+                    first_joined_at: rm.first_joined_at,
+                    last_joined_at: rm.last_joined_at,
+                })
+            })
+            .collect();
+        out.sort_by_key(|r| std::cmp::Reverse(r.last_joined_at));
+        Ok(out)
+    }
+
+    async fn user_rooms(&self, user_id: i64, viewer: i64) -> Result<Vec<RoomSummary>> {
+        let m = self.mem.borrow();
+        let mut out = m.joined(user_id);
+        out.retain(|r| m.visible_to(r.id, viewer));
+        Ok(out)
+    }
+
+    async fn list_rooms(&self, viewer: i64) -> Result<Vec<RoomListing>> {
+        let m = self.mem.borrow();
+        let mut out: Vec<RoomListing> = m
+            .rooms
+            .iter()
+            .filter(|r| !r.is_deleted && m.visible_to(r.id, viewer))
+            .map(|r| RoomListing {
+                id: r.id,
+                name: r.name.clone(),
+                topic: r.topic.clone(),
+                members: m.members.iter().filter(|rm| rm.room_id == r.id).count() as i64,
+            })
+            .collect();
+        out.sort_by(|a, b| b.members.cmp(&a.members).then_with(|| a.name.cmp(&b.name)));
+        Ok(out)
+    }
+
+    async fn room_member_counts(&self, user_id: i64) -> Result<Vec<(i64, i64)>> {
+        let m = self.mem.borrow();
+        Ok(m.joined(user_id)
+            .into_iter()
+            .map(|r| {
+                let n = m.members.iter().filter(|rm| rm.room_id == r.id).count();
+                (r.id, n as i64)
+            })
+            .collect())
+    }
+
+    async fn list_recent_members(&self, room_id: i64, limit: i64) -> Result<Vec<WhoSummary>> {
+        let m = self.mem.borrow();
+        let mut rows: Vec<&Member> = m
+            .members
+            .iter()
+            .filter(|rm| rm.room_id == room_id)
+            .collect();
+        let seen = |id: i64| {
+            m.users
+                .iter()
+                .find(|u| u.id == id)
+                .map_or(DateTime::<Utc>::MIN_UTC, |u| u.last_seen_at)
+        };
+        rows.sort_by_key(|rm| std::cmp::Reverse((seen(rm.user_id), rm.last_joined_at)));
+        Ok(rows
+            .into_iter()
+            .take(limit as usize)
+            .map(|rm| WhoSummary {
+                id: rm.user_id,
+                handle: m.handle(rm.user_id),
+                first_joined_at: rm.first_joined_at,
+                tagline: m.taglines.get(&rm.user_id).cloned(),
+                last_seen_at: seen(rm.user_id),
+                away_reason: m
+                    .users
+                    .iter()
+                    .find(|u| u.id == rm.user_id)
+                    .and_then(|u| u.away_reason.clone()),
+            })
+            .collect())
+    }
+
+    async fn unread_summary(&self, room_id: i64, user_id: i64) -> Result<Option<UnreadSummary>> {
+        let m = self.mem.borrow();
+        let Some(after_id) = m
+            .members
+            .iter()
+            .find(|rm| rm.room_id == room_id && rm.user_id == user_id)
+            .and_then(|rm| rm.last_read_message_id)
+        else {
+            return Ok(None);
+        };
+        let unread: Vec<&Message> = m
+            .messages
+            .iter()
+            .filter(|msg| {
+                msg.room_id == room_id
+                    && msg.id > after_id
+                    && msg.deleted_at.is_none()
+                    && msg.user_id != user_id
+            })
+            .collect();
+        let Some(since) = unread.iter().map(|msg| msg.created_at).min() else {
+            return Ok(None);
+        };
+        let mut senders: Vec<String> = vec![];
+        for msg in &unread {
+            let handle = m.handle(msg.user_id);
+            if !senders.contains(&handle) {
+                senders.push(handle);
+            }
+        }
+        Ok(Some(UnreadSummary {
+            after_id,
+            count: unread.len() as i64,
+            senders,
+            since,
+        }))
+    }
+
+    async fn mark_read(&self, room_id: i64, user_id: i64, message_id: i64) -> Result<()> {
+        let mut m = self.mem.borrow_mut();
+        if let Some(rm) = m
+            .members
+            .iter_mut()
+            .find(|rm| rm.room_id == room_id && rm.user_id == user_id)
+        {
+            rm.last_read_message_id = rm.last_read_message_id.max(Some(message_id));
+        }
+        Ok(())
+    }
+
+    async fn room_member_fingerprints(&self, room_id: i64) -> Result<Vec<MemberFingerprint>> {
+        let m = self.mem.borrow();
+        let mut rows: Vec<MemberFingerprint> = m
+            .members
+            .iter()
+            .filter(|rm| rm.room_id == room_id)
+            .filter_map(|rm| m.users.iter().find(|u| u.id == rm.user_id))
+            .map(|u| MemberFingerprint {
+                handle: u.handle.clone(),
+                fingerprint_sha256: u.fingerprint_sha256.clone(),
+                pubkey_type: u.pubkey_type.clone(),
+            })
+            .collect();
+        rows.sort_by(|a, b| a.handle.cmp(&b.handle));
+        Ok(rows)
+    }
+
+    async fn soft_delete_room_by_moderator(&self, name: &str, user_id: i64) -> Result<Option<i64>> {
+        let mut m = self.mem.borrow_mut();
+        let system: Vec<i64> = m
+            .users
+            .iter()
+            .filter(|u| u.fingerprint_sha256 == data::SYSTEM_FP)
+            .map(|u| u.id)
+            .collect();
+        let Some(id) = m
+            .rooms
+            .iter()
+            .find(|r| r.name == name && !r.is_deleted && !system.contains(&r.created_by))
+            .map(|r| r.id)
+        else {
+            return Ok(None);
+        };
+        if !m.moderates(id, user_id) {
+            return Ok(None);
+        }
+        let room = m.rooms.iter_mut().find(|r| r.id == id);
+        Ok(room.map(|r| {
+            r.is_deleted = true;
+            r.deleted_at = Some(Utc::now());
+            r.id
+        }))
+    }
+
+    async fn soft_delete_room_any(&self, name: &str) -> Result<Option<i64>> {
+        let mut m = self.mem.borrow_mut();
+        let room = m.rooms.iter_mut().find(|r| r.name == name && !r.is_deleted);
+        Ok(room.map(|r| {
+            r.is_deleted = true;
+            r.deleted_at = Some(Utc::now());
+            r.id
+        }))
+    }
+
+    async fn restore_room(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        moderator_id: Option<i64>,
+        window: Duration,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let cutoff = Utc::now() - chrono::Duration::from_std(window)?;
+        if moderator_id.is_some_and(|u| !m.moderates(room_id, u)) {
+            return Ok(false);
+        }
+        let room = m
+            .rooms
+            .iter_mut()
+            .find(|r| r.id == room_id && r.is_deleted && r.deleted_at.is_some_and(|t| t > cutoff));
+        let Some(room) = room else {
+            return Ok(false);
+        };
+        room.is_deleted = false;
+        room.deleted_at = None;
+        m.events.push(Event::RoomRestored { room_id });
+        Ok(true)
+    }
+
+    async fn set_room_color(
+        &self,
+        room_id: i64,
+        color: Option<&str>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let room = m.rooms.iter_mut().find(|r| {
+            r.id == room_id && !r.is_deleted && creator_id.is_none_or(|c| r.created_by == c)
+        });
+        Ok(room.map(|r| r.color = color.map(str::to_string)).is_some())
+    }
+
+    async fn set_room_topic(
+        &self,
+        room_id: i64,
+        topic: Option<&str>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let room = m.rooms.iter_mut().find(|r| {
+            r.id == room_id && !r.is_deleted && creator_id.is_none_or(|c| r.created_by == c)
+        });
+        Ok(room.map(|r| r.topic = topic.map(str::to_string)).is_some())
+    }
+
+    async fn set_room_access(
+        &self,
+        room_id: i64,
+        access: RoomAccess,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let room = m.rooms.iter_mut().find(|r| {
+            r.id == room_id && !r.is_deleted && creator_id.is_none_or(|c| r.created_by == c)
+        });
+        Ok(room.map(|r| r.access = access).is_some())
+    }
+
+    async fn set_room_rate(
+        &self,
+        room_id: i64,
+        rate_per_min: Option<u32>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let room = m.rooms.iter_mut().find(|r| {
+            r.id == room_id && !r.is_deleted && creator_id.is_none_or(|c| r.created_by == c)
+        });
+        Ok(room
+            .map(|r| r.rate_per_min = rate_per_min.map(|n| n as i32))
+            .is_some())
+    }
+
+    async fn set_room_quiet(
+        &self,
+        room_id: i64,
+        quiet: Option<QuietHours>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let room = m.rooms.iter_mut().find(|r| {
+            r.id == room_id && !r.is_deleted && creator_id.is_none_or(|c| r.created_by == c)
+        });
+        Ok(room
+            .map(|r| {
+                r.quiet_start = quiet.map(|q| q.start);
+                r.quiet_end = quiet.map(|q| q.end);
+                r.quiet_offset_mins = quiet.map_or(0, |q| q.offset_mins);
+            })
+            .is_some())
+    }
+
+    async fn purge_messages(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        actor_id: i64,
+        criteria: PurgeCriteria,
+    ) -> Result<Option<Vec<i64>>> {
+        let mut m = self.mem.borrow_mut();
+        let is_admin = m.admins.contains(&actor_id);
+        let allowed = m
+            .rooms
+            .iter()
+            .any(|r| r.id == room_id && !r.is_deleted && (r.created_by == actor_id || is_admin));
+        if !allowed {
+            return Ok(None);
+        }
+        let (target, limit) = match criteria {
+            PurgeCriteria::Last(n) => (None, n.clamp(0, data::PURGE_MAX)),
+            PurgeCriteria::User(user_id) => (Some(user_id), data::PURGE_MAX),
+        };
+        let mut live: Vec<&mut Message> = m
+            .messages
+            .iter_mut()
+            .filter(|msg| {
+                msg.room_id == room_id
+                    && msg.deleted_at.is_none()
+                    && target.is_none_or(|t| msg.user_id == t)
+            })
+            .collect();
+        live.sort_by_key(|msg| std::cmp::Reverse((msg.created_at, msg.id)));
+        let mut ids = vec![];
+        for msg in live.into_iter().take(limit as usize) {
+            msg.deleted_at = Some(Utc::now());
+            ids.push(msg.id);
+        }
+        for &id in &ids {
+            m.events.push(Event::Deleted { id, room_id });
+        }
+        Ok(Some(ids))
+    }
+
+    async fn edit_last_message(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+    ) -> Result<Option<Message>> {
+        let mut m = self.mem.borrow_mut();
+        let Some(msg) = m
+            .messages
+            .iter_mut()
+            .filter(|msg| msg.room_id == room_id && msg.user_id == user_id)
+            .filter(|msg| msg.deleted_at.is_none())
+            .max_by_key(|msg| (msg.created_at, msg.id))
+        else {
+            return Ok(None);
+        };
+        msg.body = body.into();
+        msg.edited_at = Some(Utc::now());
+        let msg = msg.clone();
+        m.events.push(Event::Edited {
+            id: msg.id,
+            room_id,
+        });
+        Ok(Some(msg))
+    }
+
+    async fn soft_delete_message(&self, _channel: &str, msg_id: i64, user_id: i64) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let Some(msg) = m
+            .messages
+            .iter_mut()
+            .find(|msg| msg.id == msg_id && msg.user_id == user_id && msg.deleted_at.is_none())
+        else {
+            return Ok(false);
+        };
+        msg.deleted_at = Some(Utc::now());
+        let room_id = msg.room_id;
+        m.events.push(Event::Deleted {
+            id: msg_id,
+            room_id,
+        });
+        Ok(true)
+    }
+
+    async fn restore_message(
+        &self,
+        _channel: &str,
+        msg_id: i64,
+        user_id: i64,
+        window: Duration,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let cutoff = Utc::now() - chrono::Duration::from_std(window)?;
+        let Some(msg) = m.messages.iter_mut().find(|msg| {
+            msg.id == msg_id && msg.user_id == user_id && msg.deleted_at.is_some_and(|t| t > cutoff)
+        }) else {
+            return Ok(false);
+        };
+        msg.deleted_at = None;
+        let room_id = msg.room_id;
+        m.events.push(Event::Restored {
+            id: msg_id,
+            room_id,
+        });
+        Ok(true)
+    }
+
+    async fn recent_messages_view(&self, room_id: i64, limit: i64) -> Result<Vec<MessageView>> {
+        Ok(self.mem.borrow().page(room_id, None, limit))
+    }
+
+    async fn messages_before(
+        &self,
+        room_id: i64,
+        before: MessageCursor,
+        limit: i64,
+    ) -> Result<Vec<MessageView>> {
+        Ok(self.mem.borrow().page(room_id, Some(before), limit))
+    }
+
+    async fn messages_around(
+        &self,
+        room_id: i64,
+        center_id: i64,
+        radius: i64,
+    ) -> Result<Vec<MessageView>> {
+        let page = self.mem.borrow().page(room_id, None, i64::MAX);
+        let Some(at) = page.iter().position(|v| v.id == center_id) else {
+            return Ok(vec![]);
+        };
+        let r = radius as usize;
+        let end = (at + r + 1).min(page.len());
+        Ok(page[at.saturating_sub(r)..end].to_vec())
+    }
+
+    async fn message_view_by_id(&self, id: i64) -> Result<Option<MessageView>> {
+        let m = self.mem.borrow();
+        Ok(m.messages
+            .iter()
+            .find(|msg| msg.id == id && msg.deleted_at.is_none())
+            .map(|msg| m.view(msg)))
+    }
+
+    async fn insert_message(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+    ) -> Result<InsertOutcome> {
+        self.insert(room_id, user_id, body, None)
+    }
+
+    async fn insert_reply(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+        reply_to: i64,
+    ) -> Result<InsertOutcome> {
+        self.insert(room_id, user_id, body, Some(reply_to))
+    }
+
+    async fn user_messages(
+        &self,
+        user_id: i64,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<UserMessage>> {
+        let m = self.mem.borrow();
+        let room_name = |id: i64| {
+            m.rooms
+                .iter()
+                .find(|r| r.id == id)
+                .map(|r| r.name.clone())
+                .unwrap_or_default()
+        };
+        Ok(m.messages
+            .iter()
+            .rev()
+            .filter(|msg| msg.user_id == user_id && msg.deleted_at.is_none())
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|msg| UserMessage {
+                id: msg.id,
+                room_name: room_name(msg.room_id),
+                body: msg.body.clone(),
+                created_at: msg.created_at,
+            })
+            .collect())
+    }
+
+    async fn room_activity_buckets(
+        &self,
+        room_ids: &[i64],
+        buckets: i32,
+        _window: Duration,
+    ) -> Result<Vec<(i64, Vec<i64>)>> {
+        Ok(room_ids
+            .iter()
+            .map(|&id| (id, vec![0; buckets.max(0) as usize]))
+            .collect())
+    }
+
+    async fn room_stats(&self, room_id: i64) -> Result<RoomStats> {
+        let m = self.mem.borrow();
+        let lens: Vec<(i64, i32)> = m
+            .messages
+            .iter()
+            .filter(|msg| msg.room_id == room_id && msg.deleted_at.is_none())
+            .map(|msg| (msg.user_id, msg.body.chars().count() as i32))
+            .collect();
+        let mut posters: Vec<i64> = lens.iter().map(|(u, _)| *u).collect();
+        posters.sort();
+        posters.dedup();
+        let total: i64 = lens.iter().map(|(_, l)| *l as i64).sum();
+        Ok(RoomStats {
+            messages: lens.len() as i64,
+            posters: posters.len() as i64,
+            avg_len: (!lens.is_empty()).then(|| total as f64 / lens.len() as f64),
+            max_len: lens.iter().map(|(_, l)| *l).max(),
+        })
+    }
+
+    async fn server_info(&self) -> Result<ServerInfo> {
+        let m = self.mem.borrow();
+        let hour_ago = Utc::now() - chrono::Duration::hours(1);
+        let live = || m.messages.iter().filter(|msg| msg.deleted_at.is_none());
+        Ok(ServerInfo {
+            users: m.users.len() as i64,
+            rooms: m.rooms.iter().filter(|r| !r.is_deleted).count() as i64,
+            messages: live().count() as i64,
+            messages_last_hour: live().filter(|msg| msg.created_at > hour_ago).count() as i64,
+            oldest_message_at: m.messages.iter().map(|msg| msg.created_at).min(),
+        })
+    }
+
+    async fn insert_announcement(&self, _channel: &str, user_id: i64, body: &str) -> Result<i64> {
+        let mut m = self.mem.borrow_mut();
+        let id = m.id();
+        m.announcements.push(Stored {
+            id,
+            user_id,
+            body: body.into(),
+            created_at: Utc::now(),
+        });
+        m.events.push(Event::Announce { id });
+        Ok(id)
+    }
+
+    async fn announcement_by_id(&self, id: i64) -> Result<Option<Announcement>> {
+        let m = self.mem.borrow();
+        Ok(m.announcements
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| Announcement {
+                user_handle: m.handle(a.user_id),
+                body: a.body.clone(),
+                created_at: a.created_at,
+            }))
+    }
+
+    async fn insert_dm(
+        &self,
+        _channel: &str,
+        from_user: i64,
+        to_handle: &str,
+        body: &str,
+    ) -> Result<Option<DirectMessage>> {
+        let mut m = self.mem.borrow_mut();
+        let Some(to) = m.users.iter().find(|u| u.handle == to_handle).cloned() else {
+            return Ok(None);
+        };
+        let dm = DirectMessage {
+            id: m.id(),
+            from_user_id: Some(from_user),
+            from_handle: m.handle(from_user),
+            to_user_id: to.id,
+            to_handle: to.handle,
+            body: body.into(),
+            created_at: Utc::now(),
+        };
+        m.dms.push(dm.clone());
+        m.events.push(Event::Dm {
+            id: dm.id,
+            to_user: dm.to_user_id,
+        });
+        Ok(Some(dm))
+    }
+
+    async fn dm_by_id(&self, id: i64) -> Result<Option<DirectMessage>> {
+        Ok(self.mem.borrow().dms.iter().find(|d| d.id == id).cloned())
+    }
+
+    async fn submit_feedback(&self, user_id: i64, body: &str) -> Result<i64> {
+        let mut m = self.mem.borrow_mut();
+        let id = m.id();
+        m.feedback.push((
+            Stored {
+                id,
+                user_id,
+                body: body.into(),
+                created_at: Utc::now(),
+            },
+            false,
+        ));
+        Ok(id)
+    }
+
+    async fn list_open_feedback(&self, limit: i64) -> Result<Vec<Feedback>> {
+        let m = self.mem.borrow();
+        Ok(m.feedback
+            .iter()
+            .filter(|(_, handled)| !handled)
+            .take(limit as usize)
+            .map(|(f, _)| Feedback {
+                id: f.id,
+                user_handle: m.handle(f.user_id),
+                body: f.body.clone(),
+                created_at: f.created_at,
+            })
+            .collect())
+    }
+
+    async fn mark_feedback_handled(&self, id: i64) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        Ok(
+            match m.feedback.iter_mut().find(|(f, h)| f.id == id && !*h) {
+                Some((_, handled)) => {
+                    *handled = true;
+                    true
+                }
+                None => false,
+            },
+        )
+    }
+
+    async fn insert_invite(
+        &self,
+        code: &str,
+        created_by: i64,
+        uses: u32,
+        ttl: Option<chrono::Duration>,
+        room_id: Option<i64>,
+    ) -> Result<Invite> {
+        let mut m = self.mem.borrow_mut();
+        if m.invites.iter().any(|i| i.code == code) {
+            bail!("invite exists");
+        }
+        let invite = Invite {
+            code: code.into(),
+            created_by: Some(created_by),
+            created_at: Utc::now(),
+            uses_left: uses as i32,
+            expires_at: ttl.map(|d| Utc::now() + d),
+            room_id,
+        };
+        m.invites.push(invite.clone());
+        Ok(invite)
+    }
+
+    async fn create_invite(
+        &self,
+        created_by: i64,
+        max_uses: u32,
+        ttl: Option<chrono::Duration>,
+        room_id: Option<i64>,
+    ) -> Result<String> {
+        let code = crate::util::random_invite_code();
+        self.insert_invite(&code, created_by, max_uses, ttl, room_id)
+            .await?;
+        Ok(code)
+    }
+
+    async fn delete_invite(&self, code: &str) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let before = m.invites.len();
+        m.invites.retain(|i| i.code != code);
+        Ok(m.invites.len() < before)
+    }
+
+    async fn list_invites(&self, limit: i64) -> Result<Vec<Invite>> {
+        let m = self.mem.borrow();
+        Ok(m.invites
+            .iter()
+            .rev()
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_signature(&self, user_id: i64) -> Result<Option<String>> {
+        Ok(self.mem.borrow().signatures.get(&user_id).cloned())
+    }
+
+    async fn set_signature(&self, user_id: i64, signature: Option<&str>) -> Result<()> {
+        let mut m = self.mem.borrow_mut();
+        match signature {
+            Some(s) => m.signatures.insert(user_id, s.into()),
+            None => m.signatures.remove(&user_id),
+        };
+        Ok(())
+    }
+
+    async fn set_away(&self, user_id: i64, reason: Option<&str>) -> Result<()> {
+        let mut m = self.mem.borrow_mut();
+        if let Some(u) = m.users.iter_mut().find(|u| u.id == user_id) {
+            u.away_reason = reason.map(str::to_string);
+        }
+        Ok(())
+    }
+
+    async fn set_last_room(&self, user_id: i64, room_id: i64) -> Result<()> {
+        self.mem.borrow_mut().last_rooms.insert(user_id, room_id);
+        Ok(())
+    }
+
+    async fn toggle_reaction(
+        &self,
+        _channel: &str,
+        message_id: i64,
+        user_id: i64,
+        emoji: &str,
+    ) -> Result<Option<bool>> {
+        let mut m = self.mem.borrow_mut();
+        let Some(room_id) = m
+            .messages
+            .iter()
+            .find(|msg| msg.id == message_id && msg.deleted_at.is_none())
+            .map(|msg| msg.room_id)
+        else {
+            return Ok(None);
+        };
+        let key = (message_id, user_id, emoji.to_string());
+        let before = m.reactions.len();
+        m.reactions.retain(|r| *r != key);
+        let added = m.reactions.len() == before;
+        if added {
+            m.reactions.push(key);
+        }
+        m.events.push(Event::Reacted {
+            id: message_id,
+            room_id,
+        });
+        Ok(Some(added))
+    }
+
+    async fn reaction_counts(&self, msg_ids: &[i64]) -> Result<Vec<ReactionCount>> {
+        let m = self.mem.borrow();
+        let mut out: Vec<ReactionCount> = vec![];
+        for (msg, _, emoji) in m.reactions.iter().filter(|r| msg_ids.contains(&r.0)) {
+            match out
+                .iter_mut()
+                .find(|c| c.message_id == *msg && c.emoji == *emoji)
+            {
+                Some(c) => c.n += 1,
+                None => out.push(ReactionCount {
+                    message_id: *msg,
+                    emoji: emoji.clone(),
+                    n: 1,
+                }),
+            }
+        }
+        // stable: first-use order within a message
+        out.sort_by_key(|c| c.message_id);
+        Ok(out)
+    }
+
+    async fn set_pin(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        message_id: i64,
+        pinned: bool,
+        moderator_id: Option<i64>,
+    ) -> Result<PinOutcome> {
+        let mut m = self.mem.borrow_mut();
+        let allowed = m.rooms.iter().any(|r| r.id == room_id && !r.is_deleted)
+            && moderator_id.is_none_or(|u| m.moderates(room_id, u));
+        let live = m
+            .messages
+            .iter()
+            .any(|msg| msg.id == message_id && msg.room_id == room_id && msg.deleted_at.is_none());
+        if !allowed || !live {
+            return Ok(PinOutcome::Refused);
+        }
+        if m.pins.contains_key(&message_id) == pinned {
+            return Ok(PinOutcome::Unchanged);
+        }
+        if pinned {
+            m.pins.insert(message_id, Utc::now());
+        } else {
+            m.pins.remove(&message_id);
+        }
+        m.events.push(Event::Pinned {
+            id: message_id,
+            room_id,
+        });
+        Ok(PinOutcome::Changed)
+    }
+
+    async fn list_pinned(&self, room_id: i64) -> Result<Vec<MessageView>> {
+        let m = self.mem.borrow();
+        let mut pinned: Vec<(&DateTime<Utc>, &Message)> = m
+            .messages
+            .iter()
+            .filter(|msg| msg.room_id == room_id && msg.deleted_at.is_none())
+            .filter_map(|msg| m.pins.get(&msg.id).map(|at| (at, msg)))
+            .collect();
+        pinned.sort_by_key(|(at, msg)| std::cmp::Reverse((**at, msg.id)));
+        Ok(pinned.into_iter().map(|(_, msg)| m.view(msg)).collect())
+    }
+
+    async fn is_moderator(&self, room_id: i64, user_id: i64) -> Result<bool> {
+        Ok(self.mem.borrow().moderates(room_id, user_id))
+    }
+
+    // No mod_actions log here; it_db covers the audit row.
+    async fn kick_member(
+        &self,
+        _channel: &str,
+        room_id: i64,
+        target_user_id: i64,
+        _actor_id: i64,
+        moderator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let creator = m
+            .rooms
+            .iter()
+            .find(|r| r.id == room_id)
+            .map(|r| r.created_by);
+        if creator.is_none_or(|c| c == target_user_id)
+            || moderator_id.is_some_and(|u| !m.moderates(room_id, u))
+        {
+            return Ok(false);
+        }
+        let before = m.members.len();
+        m.members
+            .retain(|mb| !(mb.room_id == room_id && mb.user_id == target_user_id));
+        if m.members.len() == before {
+            return Ok(false);
+        }
+        m.events.push(Event::Kicked {
+            room_id,
+            user_id: target_user_id,
+        });
+        Ok(true)
+    }
+
+    async fn mute_member(
+        &self,
+        room_id: i64,
+        target_user_id: i64,
+        _actor_id: i64,
+        moderator_id: Option<i64>,
+        minutes: u32,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let creator = m
+            .rooms
+            .iter()
+            .find(|r| r.id == room_id)
+            .map(|r| r.created_by);
+        if creator.is_none_or(|c| c == target_user_id)
+            || moderator_id.is_some_and(|u| !m.moderates(room_id, u))
+        {
+            return Ok(false);
+        }
+        let key = (room_id, target_user_id);
+        if minutes == 0 {
+            let live = m.mutes.get(&key).is_some_and(|t| *t > Utc::now());
+            m.mutes.remove(&key);
+            return Ok(live);
+        }
+        let until = Utc::now() + chrono::Duration::minutes(minutes as i64);
+        m.mutes.insert(key, until);
+        Ok(true)
+    }
+
+    async fn grant_moderator(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        _granted_by: i64,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let owned = m.rooms.iter().any(|r| {
+            r.id == room_id && !r.is_deleted && creator_id.is_none_or(|c| r.created_by == c)
+        });
+        if !owned || m.moderators.contains(&(room_id, user_id)) {
+            return Ok(false);
+        }
+        m.moderators.push((room_id, user_id));
+        Ok(true)
+    }
+
+    async fn revoke_moderator(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let owned = m
+            .rooms
+            .iter()
+            .any(|r| r.id == room_id && creator_id.is_none_or(|c| r.created_by == c));
+        let before = m.moderators.len();
+        if owned {
+            m.moderators.retain(|&(r, u)| (r, u) != (room_id, user_id));
+        }
+        Ok(m.moderators.len() < before)
+    }
+
+    async fn add_ignore(&self, user_id: i64, ignored_user_id: i64) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        if user_id == ignored_user_id {
+            bail!("ignores_not_self");
+        }
+        if m.ignores.contains(&(user_id, ignored_user_id)) {
+            return Ok(false);
+        }
+        m.ignores.push((user_id, ignored_user_id));
+        Ok(true)
+    }
+
+    async fn remove_ignore(&self, user_id: i64, ignored_user_id: i64) -> Result<bool> {
+        let mut m = self.mem.borrow_mut();
+        let before = m.ignores.len();
+        m.ignores.retain(|&p| p != (user_id, ignored_user_id));
+        Ok(m.ignores.len() < before)
+    }
+
+    async fn list_ignores(&self, user_id: i64) -> Result<Vec<i64>> {
+        let m = self.mem.borrow();
+        Ok(m.ignores
+            .iter()
+            .filter(|(u, _)| *u == user_id)
+            .map(|(_, i)| *i)
+            .collect())
+    }
+
+    async fn get_tagline(&self, user_id: i64) -> Result<Option<String>> {
+        Ok(self.mem.borrow().taglines.get(&user_id).cloned())
+    }
+
+    async fn set_tagline(&self, user_id: i64, tagline: Option<&str>) -> Result<()> {
+        let mut m = self.mem.borrow_mut();
+        match tagline {
+            Some(s) => m.taglines.insert(user_id, s.into()),
+            None => m.taglines.remove(&user_id),
+        };
+        Ok(())
+    }
+
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        Ok(self.mem.borrow().settings.get(key).cloned())
+    }
+
+    async fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()> {
+        let mut m = self.mem.borrow_mut();
+        match value {
+            Some(v) => m.settings.insert(key.into(), v.into()),
+            None => m.settings.remove(key),
+        };
+        Ok(())
+    }
+}
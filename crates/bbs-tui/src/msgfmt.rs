@@ -0,0 +1,209 @@
+// message line template (BBS_MSG_FORMAT), parsed once at startup
+
+pub const DEFAULT_TEMPLATE: &str = "[{time}] {handle}: {body}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Lit(String),
+    Time,
+    Date,
+    Handle,
+    Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsgFormat {
+    segments: Vec<Segment>,
+}
+
+impl Default for MsgFormat {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TEMPLATE).expect("default template parses")
+    }
+}
+
+impl MsgFormat {
+    // Placeholders: {time} (HH:MM:SS), {date} (YYYY-MM-DD), {handle}, {body}.
+    // `{{` and `}}` are literal braces. {body} must appear exactly once.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut lit = String::new();
+        let mut bodies = 0;
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    lit.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    lit.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(ch) => name.push(ch),
+                            None => return Err("unclosed '{' in template".into()),
+                        }
+                    }
+                    let seg = match name.as_str() {
+                        "time" => Segment::Time,
+                        "date" => Segment::Date,
+                        "handle" => Segment::Handle,
+                        "body" => {
+                            bodies += 1;
+                            Segment::Body
+                        }
+                        other => return Err(format!("unknown placeholder {{{}}}", other)),
+                    };
+                    if !lit.is_empty() {
+                        segments.push(Segment::Lit(std::mem::take(&mut lit)));
+                    }
+                    segments.push(seg);
+                }
+                '}' => return Err("unmatched '}' in template (use '}}')".into()),
+                c if c.is_control() => return Err("control characters not allowed".into()),
+                c => lit.push(c),
+            }
+        }
+        if !lit.is_empty() {
+            segments.push(Segment::Lit(lit));
+        }
+        if bodies != 1 {
+            return Err("template must contain {body} exactly once".into());
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+}
+
+pub const CODE_FENCE: &str = "```";
+
+// One run of a message body: ordinary text, or the inside of a ``` fenced block.
+// `start` is the byte offset in the body, so link positions stay comparable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BodyPart<'a> {
+    pub start: usize,
+    pub text: &'a str,
+    pub code: bool,
+}
+
+// Split a body around ``` fences. The rest of an opening fence's line is a language
+// tag when it's a single word (or nothing) and isn't shown; an unclosed fence is
+// left as ordinary text.
+pub fn split_code_blocks(body: &str) -> Vec<BodyPart<'_>> {
+    let mut parts = Vec::new();
+    let mut pos = 0;
+    while let Some(rel) = body[pos..].find(CODE_FENCE) {
+        let open = pos + rel;
+        let inner = open + CODE_FENCE.len();
+        let Some(len) = body[inner..].find(CODE_FENCE) else {
+            break;
+        };
+        let close = inner + len;
+        if open > pos {
+            parts.push(BodyPart {
+                start: pos,
+                text: &body[pos..open],
+                code: false,
+            });
+        }
+        let mut start = inner;
+        if let Some(nl) = body[inner..close].find('\n') {
+            let tag = &body[inner..inner + nl];
+            if tag
+                .chars()
+                .all(|c| c.is_alphanumeric() || "+-_#.".contains(c))
+            {
+                start = inner + nl + 1;
+            }
+        }
+        let text = body[start..close]
+            .strip_suffix('\n')
+            .unwrap_or(&body[start..close]);
+        parts.push(BodyPart {
+            start,
+            text,
+            code: true,
+        });
+        pos = close + CODE_FENCE.len();
+        // the fence's own line break isn't a blank line after the block
+        if body[pos..].starts_with('\n') {
+            pos += 1;
+        }
+    }
+    if pos < body.len() {
+        parts.push(BodyPart {
+            start: pos,
+            text: &body[pos..],
+            code: false,
+        });
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_default_and_custom() {
+        assert_eq!(
+            MsgFormat::default().segments(),
+            &[
+                Segment::Lit("[".into()),
+                Segment::Time,
+                Segment::Lit("] ".into()),
+                Segment::Handle,
+                Segment::Lit(": ".into()),
+                Segment::Body,
+            ]
+        );
+        let f = MsgFormat::parse("({time}) <{handle}> {body} {{x}}").unwrap();
+        assert_eq!(f.segments().first(), Some(&Segment::Lit("(".into())));
+        assert_eq!(f.segments().last(), Some(&Segment::Lit(" {x}".into())));
+    }
+
+    #[test]
+    fn splits_fenced_code() {
+        let body = "look:\n```rust\nfn main() {\n    x();\n}\n```\nnice";
+        let parts = split_code_blocks(body);
+        let got: Vec<(&str, bool)> = parts.iter().map(|p| (p.text, p.code)).collect();
+        assert_eq!(
+            got,
+            [
+                ("look:\n", false),
+                ("fn main() {\n    x();\n}", true),
+                ("nice", false)
+            ]
+        );
+        assert_eq!(&body[parts[1].start..parts[1].start + 2], "fn");
+
+        // inline fences, a first line that is code, and an unclosed fence
+        let inline = split_code_blocks("run ```ls -la``` now");
+        assert_eq!(inline[1].text, "ls -la");
+        assert!(inline[1].code);
+        assert_eq!(
+            split_code_blocks("```let x = 1;\nx```")[0].text,
+            "let x = 1;\nx"
+        );
+        let open = split_code_blocks("oops ``` never closed");
+        assert_eq!(open.len(), 1);
+        assert!(!open[0].code);
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!(MsgFormat::parse("{handle}: {nope} {body}").is_err());
+        assert!(MsgFormat::parse("{handle} {body").is_err());
+        assert!(MsgFormat::parse("{handle}").is_err());
+        assert!(MsgFormat::parse("{body} {body}").is_err());
+        assert!(MsgFormat::parse("a } {body}").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+// retention: drop messages older than BBS_RETENTION_DAYS, from --prune or a
+// sweeper task inside each session
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info};
+
+use crate::data;
+
+// Failed sweeps double the wait before the next one, up to this (or the
+// interval itself, if that's longer).
+const BACKOFF_MAX: Duration = Duration::from_secs(24 * 3600);
+
+// Delete messages older than `retention_days`, `batch` rows at a time, until
+// none are left; returns how many went.
+pub async fn prune_expired(pool: &PgPool, retention_days: u32, batch: i64) -> Result<u64> {
+    let cutoff = Utc::now() - ChronoDuration::days(retention_days as i64);
+    let mut total: u64 = 0;
+    loop {
+        match data::prune_old_messages(pool, cutoff, batch).await? {
+            0 => return Ok(total),
+            n => {
+                total += n;
+                // small yield
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+// Wait before the next sweep after `failures` failed ones in a row.
+pub fn sweep_delay(interval: Duration, failures: u32) -> Duration {
+    interval
+        .saturating_mul(1 << failures.min(16))
+        .min(BACKOFF_MAX.max(interval))
+}
+
+// Sweep now and then every `interval_mins` (BBS_PRUNE_INTERVAL_MINS; 0 never
+// starts). It runs on its own task, so a slow or failing sweep can't hold up
+// the UI. A retention of 0 days would delete everything, so it never sweeps.
+pub fn spawn_sweeper(pool: PgPool, retention_days: u32, batch: i64, interval_mins: u32) {
+    if interval_mins == 0 || retention_days == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(u64::from(interval_mins) * 60);
+    tokio::spawn(async move {
+        let mut failures = 0;
+        loop {
+            match prune_expired(&pool, retention_days, batch).await {
+                Ok(n) => {
+                    failures = 0;
+                    if n > 0 {
+                        info!(pruned = n, "retention prune complete");
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    error!(error = %e, failures, "retention prune error");
+                }
+            }
+            tokio::time::sleep(sweep_delay(interval, failures)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failures_back_off_up_to_a_day() {
+        let hour = Duration::from_secs(3600);
+        assert_eq!(sweep_delay(hour, 0), hour);
+        assert_eq!(sweep_delay(hour, 1), 2 * hour);
+        assert_eq!(sweep_delay(hour, 3), 8 * hour);
+        assert_eq!(sweep_delay(hour, 10), BACKOFF_MAX);
+        assert_eq!(sweep_delay(hour, u32::MAX), BACKOFF_MAX);
+        // an interval over the cap is never shortened
+        let week = 7 * BACKOFF_MAX;
+        assert_eq!(sweep_delay(week, 2), week);
+    }
+}
@@ -1,7 +1,8 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 // Simple client-side token bucket to mirror server limit.
-// Tokens refill continuously at `rate_per_min` per minute up to `capacity`.
+// Tokens refill continuously at `rate_per_min` per minute up to `capacity`
+// (the burst).
 #[derive(Debug, Clone)]
 pub struct TokenBucket {
     capacity: f64,
@@ -12,11 +13,16 @@ pub struct TokenBucket {
 
 impl TokenBucket {
     pub fn new(rate_per_min: u32) -> Self {
-        let rate = rate_per_min as f64;
+        Self::with_burst(rate_per_min, rate_per_min)
+    }
+
+    // Up to `burst` sends back to back, then `rate_per_min` sustained.
+    pub fn with_burst(rate_per_min: u32, burst: u32) -> Self {
+        let burst = burst as f64;
         Self {
-            capacity: rate,
-            tokens: rate,
-            rate_per_sec: rate / 60.0,
+            capacity: burst,
+            tokens: burst,
+            rate_per_sec: rate_per_min as f64 / 60.0,
             last: Instant::now(),
         }
     }
@@ -44,6 +50,25 @@ impl TokenBucket {
         self.tokens
     }
 
+    // Adopt the server's count of sends left in its window; it's authoritative.
+    pub fn sync_remaining(&mut self, remaining: f64) {
+        self.refill();
+        self.tokens = remaining.clamp(0.0, self.capacity);
+    }
+
+    // How long until `n` tokens are available at the refill rate; zero if they already are.
+    pub fn time_until(&mut self, n: f64) -> Duration {
+        self.refill();
+        let missing = n - self.tokens;
+        if missing <= 1e-9 {
+            return Duration::ZERO;
+        }
+        if self.rate_per_sec <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64(missing / self.rate_per_sec)
+    }
+
     pub fn capacity(&self) -> f64 {
         self.capacity
     }
@@ -53,7 +78,6 @@ impl TokenBucket {
 mod tests {
     use super::*;
     use std::thread;
-    use std::time::Duration;
 
     #[test]
     fn bucket_basic() {
@@ -65,4 +89,38 @@ mod tests {
         thread::sleep(Duration::from_millis(1200)); // ~0.12 tokens
         assert!(b.try_consume(0.1));
     }
+
+    #[test]
+    fn burst_is_separate_from_rate() {
+        let mut b = TokenBucket::with_burst(600, 2); // 10/s refill
+        assert_eq!(b.capacity(), 2.0);
+        assert!(b.try_consume(1.0));
+        assert!(b.try_consume(1.0));
+        assert!(!b.try_consume(1.0));
+        thread::sleep(Duration::from_millis(150));
+        assert!(b.try_consume(1.0));
+        assert_eq!(TokenBucket::new(7).capacity(), 7.0);
+    }
+
+    #[test]
+    fn time_until_refill() {
+        let mut b = TokenBucket::new(6); // one token per 10s
+        assert_eq!(b.time_until(1.0), Duration::ZERO);
+        b.sync_remaining(0.0);
+        let wait = b.time_until(1.0).as_secs_f64();
+        assert!(wait > 9.9 && wait <= 10.0, "{}", wait);
+        let wait = b.time_until(3.0).as_secs_f64();
+        assert!(wait > 29.9 && wait <= 30.0, "{}", wait);
+    }
+
+    #[test]
+    fn sync_adopts_server_remaining() {
+        let mut b = TokenBucket::new(10);
+        b.sync_remaining(3.0);
+        assert!(b.peek_tokens() < 3.1);
+        b.sync_remaining(50.0);
+        assert!(b.peek_tokens() <= b.capacity());
+        b.sync_remaining(0.0);
+        assert!(!b.try_consume(1.0));
+    }
 }
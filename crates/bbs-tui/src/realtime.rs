@@ -1,66 +1,356 @@
-// LISTEN/NOTIFY loop (to be implemented)
+// LISTEN/NOTIFY loop with jittered reconnect and a polling fallback
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use serde::Deserialize;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgListener, PgPool};
 use tokio::{
     sync::mpsc,
-    time::{sleep, Duration},
+    time::{sleep, Duration, Instant},
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub enum Event {
     Message { id: i64, room_id: i64 },
+    Edited { id: i64, room_id: i64 },
+    Deleted { id: i64, room_id: i64 },
+    // an /undo brought back a deleted message; unlike Message it repeats an old id
+    Restored { id: i64, room_id: i64 },
+    Reacted { id: i64, room_id: i64 },
+    Pinned { id: i64, room_id: i64 },
+    Announce { id: i64 },
+    // a /msg to `to_user`; everyone hears it, only the recipient fetches the body
+    Dm { id: i64, to_user: i64 },
+    // room membership changes (first join / leave, not re-join bumps)
+    Joined { room_id: i64, user_id: i64 },
+    Left { room_id: i64, user_id: i64 },
+    // a moderator removed `user_id` from the room
+    Kicked { room_id: i64, user_id: i64 },
+    // an /undo brought a deleted room back; its members get it in the sidebar again
+    RoomRestored { room_id: i64 },
+    // Listener connectivity; the UI uses it to decide whether sends can reach the DB.
+    Health { online: bool },
+    // Feed mode, sent on changes only: true on LISTEN, false while falling back to polling.
+    ConnState(bool),
+}
+
+pub const DEFAULT_CHANNEL: &str = "room_events";
+const ANNOUNCE_SUFFIX: &str = "_announce";
+
+// Channel names for one BBS instance. Instances sharing a database pick distinct
+// BBS_NOTIFY_CHANNEL values; the announce channel is derived so it can't collide either.
+#[derive(Debug, Clone)]
+pub struct Channels {
+    pub events: String,
+    // instance-wide admin announcements; every client listens regardless of room
+    pub announce: String,
+}
+
+impl Channels {
+    pub fn new(events: &str) -> Self {
+        Self {
+            events: events.to_string(),
+            announce: format!("{}{}", events, ANNOUNCE_SUFFIX),
+        }
+    }
+}
+
+// Plain lowercase identifier, short enough that the derived announce channel
+// still fits Postgres' 63-byte identifier limit.
+pub fn valid_channel(name: &str) -> bool {
+    let mut chars = name.chars();
+    let first_ok = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_');
+    first_ok
+        && name.len() + ANNOUNCE_SUFFIX.len() <= 63
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
 }
 
-#[derive(Debug, Deserialize)]
-struct NotifyPayload {
+// Wire format for the notify channels; writers and the listener both go through this.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NotifyPayload {
     #[serde(rename = "t")]
     t: String,
+    // announcements aren't tied to a room
+    #[serde(default)]
     room_id: i64,
-    id: i64,
+    // message/announcement id; absent on membership events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
+    // set on membership events only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_id: Option<i64>,
+}
+
+impl NotifyPayload {
+    pub fn msg(room_id: i64, id: i64) -> Self {
+        Self {
+            t: "msg".to_string(),
+            room_id,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn edit(room_id: i64, id: i64) -> Self {
+        Self {
+            t: "edit".to_string(),
+            room_id,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn del(room_id: i64, id: i64) -> Self {
+        Self {
+            t: "del".to_string(),
+            room_id,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn restore(room_id: i64, id: i64) -> Self {
+        Self {
+            t: "restore".to_string(),
+            room_id,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn react(room_id: i64, id: i64) -> Self {
+        Self {
+            t: "react".to_string(),
+            room_id,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn pin(room_id: i64, id: i64) -> Self {
+        Self {
+            t: "pin".to_string(),
+            room_id,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn dm(id: i64, to_user: i64) -> Self {
+        Self {
+            t: "dm".to_string(),
+            room_id: 0,
+            id: Some(id),
+            user_id: Some(to_user),
+        }
+    }
+
+    pub fn shout(id: i64) -> Self {
+        Self {
+            t: "shout".to_string(),
+            room_id: 0,
+            id: Some(id),
+            user_id: None,
+        }
+    }
+
+    pub fn join(room_id: i64, user_id: i64) -> Self {
+        Self {
+            t: "join".to_string(),
+            room_id,
+            id: None,
+            user_id: Some(user_id),
+        }
+    }
+
+    pub fn leave(room_id: i64, user_id: i64) -> Self {
+        Self {
+            t: "leave".to_string(),
+            room_id,
+            id: None,
+            user_id: Some(user_id),
+        }
+    }
+
+    pub fn room_restore(room_id: i64) -> Self {
+        Self {
+            t: "unroomdel".to_string(),
+            room_id,
+            id: None,
+            user_id: None,
+        }
+    }
+
+    pub fn kick(room_id: i64, user_id: i64) -> Self {
+        Self {
+            t: "kick".to_string(),
+            room_id,
+            id: None,
+            user_id: Some(user_id),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("payload serializes")
+    }
+
+    fn into_event(self) -> Option<Event> {
+        let room_id = self.room_id;
+        match self.t.as_str() {
+            "unroomdel" => return Some(Event::RoomRestored { room_id }),
+            "join" => {
+                let user_id = self.user_id?;
+                return Some(Event::Joined { room_id, user_id });
+            }
+            "leave" => {
+                let user_id = self.user_id?;
+                return Some(Event::Left { room_id, user_id });
+            }
+            "kick" => {
+                let user_id = self.user_id?;
+                return Some(Event::Kicked { room_id, user_id });
+            }
+            "dm" => {
+                let (id, to_user) = (self.id?, self.user_id?);
+                return Some(Event::Dm { id, to_user });
+            }
+            _ => {}
+        }
+        let id = self.id?;
+        match self.t.as_str() {
+            "msg" => Some(Event::Message { id, room_id }),
+            "edit" => Some(Event::Edited { id, room_id }),
+            "del" => Some(Event::Deleted { id, room_id }),
+            "restore" => Some(Event::Restored { id, room_id }),
+            "react" => Some(Event::Reacted { id, room_id }),
+            "pin" => Some(Event::Pinned { id, room_id }),
+            "shout" => Some(Event::Announce { id }),
+            _ => None,
+        }
+    }
+}
+
+// Decode a NOTIFY payload; unknown or malformed ones are dropped.
+pub(crate) fn parse_payload(json: &str) -> Option<Event> {
+    serde_json::from_str::<NotifyPayload>(json)
+        .ok()
+        .and_then(NotifyPayload::into_event)
+}
+
+// Recently delivered message ids, so a message seen over LISTEN and again by the
+// poll fallback (or vice versa, around a reconnect) reaches the UI once. Bounded:
+// the oldest ids fall out after DELIVERED_CAP, long past any overlap window.
+const DELIVERED_CAP: usize = 1024;
+
+#[derive(Default)]
+pub struct Delivered {
+    ids: std::collections::HashSet<i64>,
+    order: std::collections::VecDeque<i64>,
+}
+
+impl Delivered {
+    // true the first time an id is offered
+    pub fn first(&mut self, id: i64) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > DELIVERED_CAP {
+            if let Some(old) = self.order.pop_front() {
+                self.ids.remove(&old);
+            }
+        }
+        true
+    }
 }
 
-pub async fn spawn_listener(pool: PgPool, tx: mpsc::Sender<Event>) {
+// Forward an event to the UI, dropping repeat `Message`s.
+pub async fn deliver(tx: &mpsc::Sender<Event>, delivered: &mut Delivered, ev: Event) {
+    if let Event::Message { id, .. } = ev {
+        if !delivered.first(id) {
+            return;
+        }
+    }
+    let _ = tx.send(ev).await;
+}
+
+// Reconnect backoff doubles from 1s up to this ceiling.
+const BACKOFF_MAX_SECS: u64 = 30;
+// While the listener is down, poll for new messages at this cadence.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub async fn spawn_listener(pool: PgPool, channels: Channels, tx: mpsc::Sender<Event>) {
     tokio::spawn(async move {
-        let mut backoff_secs = 1u64;
+        let mut ceiling_secs = 1u64;
         let mut last_seen: DateTime<Utc> = Utc::now();
+        // the UI starts out assuming a live feed
+        let mut live = true;
+        let mut delivered = Delivered::default();
         loop {
-            match run_once(&pool, &tx).await {
+            match run_once(&pool, &channels, &tx, &mut live, &mut delivered).await {
                 Ok(_) => {
-                    backoff_secs = 1;
+                    ceiling_secs = 1;
                 }
                 Err(_e) => {
-                    // Fallback polling while we back off
-                    let d = backoff_secs.min(30);
-                    let steps = (d / 2).max(1);
-                    for _ in 0..steps {
-                        if let Err(_pe) = poll_once(&pool, &tx, &mut last_seen).await {
+                    let _ = tx.send(Event::Health { online: false }).await;
+                    set_live(&tx, &mut live, false).await;
+                    // Full jitter so sessions don't all reconnect in lockstep after a
+                    // DB restart; keep polling on a fixed cadence until the retry.
+                    let wait = jittered(ceiling_secs, &mut rand::thread_rng());
+                    let retry_at = Instant::now() + wait;
+                    loop {
+                        if let Err(_pe) =
+                            poll_once(&pool, &tx, &mut last_seen, &mut delivered).await
+                        {
                             // ignore poll errors
                         }
-                        sleep(Duration::from_secs(2)).await;
+                        let now = Instant::now();
+                        if now >= retry_at {
+                            break;
+                        }
+                        sleep((retry_at - now).min(POLL_INTERVAL)).await;
                     }
-                    backoff_secs = (backoff_secs * 2).min(30);
+                    ceiling_secs = (ceiling_secs * 2).min(BACKOFF_MAX_SECS);
                 }
             }
         }
     });
 }
 
-async fn run_once(pool: &PgPool, tx: &mpsc::Sender<Event>) -> Result<()> {
+// Uniform delay in [0, ceiling_secs].
+fn jittered(ceiling_secs: u64, rng: &mut impl Rng) -> Duration {
+    Duration::from_millis(rng.gen_range(0..=ceiling_secs * 1000))
+}
+
+async fn set_live(tx: &mpsc::Sender<Event>, live: &mut bool, now: bool) {
+    if std::mem::replace(live, now) != now {
+        let _ = tx.send(Event::ConnState(now)).await;
+    }
+}
+
+async fn run_once(
+    pool: &PgPool,
+    channels: &Channels,
+    tx: &mpsc::Sender<Event>,
+    live: &mut bool,
+    delivered: &mut Delivered,
+) -> Result<()> {
     let mut listener = PgListener::connect_with(pool).await?;
-    listener.listen("room_events").await?;
+    listener
+        .listen_all([channels.events.as_str(), channels.announce.as_str()])
+        .await?;
+    let _ = tx.send(Event::Health { online: true }).await;
+    set_live(tx, live, true).await;
     loop {
-        let n = listener.recv().await?;
-        if let Ok(p) = serde_json::from_str::<NotifyPayload>(n.payload()) {
-            if p.t == "msg" {
-                let _ = tx
-                    .send(Event::Message {
-                        id: p.id,
-                        room_id: p.room_id,
-                    })
-                    .await;
-            }
+        // try_recv reports a dropped connection as None instead of silently
+        // reconnecting, so the outage reaches the UI.
+        let Some(n) = listener.try_recv().await? else {
+            anyhow::bail!("listener connection lost");
+        };
+        if let Some(ev) = parse_payload(n.payload()) {
+            deliver(tx, delivered, ev).await;
         }
     }
 }
@@ -76,6 +366,7 @@ async fn poll_once(
     pool: &PgPool,
     tx: &mpsc::Sender<Event>,
     last_seen: &mut DateTime<Utc>,
+    delivered: &mut Delivered,
 ) -> Result<()> {
     // Fetch new messages since last_seen and emit as events
     let rows: Vec<MinimalMsg> = sqlx::query_as::<_, MinimalMsg>(
@@ -90,15 +381,179 @@ async fn poll_once(
     .await?;
 
     for r in rows {
-        let _ = tx
-            .send(Event::Message {
-                id: r.id,
-                room_id: r.room_id,
-            })
-            .await;
+        let ev = Event::Message {
+            id: r.id,
+            room_id: r.room_id,
+        };
+        deliver(tx, delivered, ev).await;
         if r.created_at > *last_seen {
             *last_seen = r.created_at;
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn conn_state_only_on_changes() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut live = true;
+        set_live(&tx, &mut live, true).await;
+        set_live(&tx, &mut live, false).await;
+        set_live(&tx, &mut live, false).await;
+        set_live(&tx, &mut live, true).await;
+        drop(tx);
+        let mut got = vec![];
+        while let Some(Event::ConnState(v)) = rx.recv().await {
+            got.push(v);
+        }
+        assert_eq!(got, [false, true]);
+    }
+
+    #[tokio::test]
+    async fn restores_pass_the_repeat_filter() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut d = Delivered::default();
+        let msg = Event::Message { id: 7, room_id: 1 };
+        // heard over LISTEN and again by the poll: one delivery
+        deliver(&tx, &mut d, msg).await;
+        deliver(&tx, &mut d, msg).await;
+        // deleted, then brought back by /undo under the same id
+        deliver(
+            &tx,
+            &mut d,
+            parse_payload(&NotifyPayload::restore(1, 7).to_json()).unwrap(),
+        )
+        .await;
+        drop(tx);
+        let mut got = vec![];
+        while let Some(ev) = rx.recv().await {
+            got.push(ev);
+        }
+        assert!(matches!(
+            got[..],
+            [
+                Event::Message { id: 7, .. },
+                Event::Restored { id: 7, room_id: 1 }
+            ]
+        ));
+    }
+
+    #[test]
+    fn delivered_forgets_oldest_past_cap() {
+        let mut d = Delivered::default();
+        assert!(d.first(1));
+        assert!(!d.first(1));
+        for id in 2..=DELIVERED_CAP as i64 + 1 {
+            assert!(d.first(id));
+        }
+        // 1 was pushed out; the newest are still remembered
+        assert!(d.first(1));
+        assert!(!d.first(DELIVERED_CAP as i64 + 1));
+    }
+
+    #[test]
+    fn parses_payload_kinds() {
+        assert!(matches!(
+            parse_payload(r#"{"t":"msg","room_id":1,"id":2}"#),
+            Some(Event::Message { id: 2, room_id: 1 })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::edit(1, 2).to_json()),
+            Some(Event::Edited { id: 2, .. })
+        ));
+        assert!(matches!(
+            parse_payload(r#"{"t":"del","room_id":1,"id":2}"#),
+            Some(Event::Deleted { id: 2, .. })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::react(1, 2).to_json()),
+            Some(Event::Reacted { id: 2, .. })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::kick(1, 2).to_json()),
+            Some(Event::Kicked {
+                room_id: 1,
+                user_id: 2
+            })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::pin(1, 2).to_json()),
+            Some(Event::Pinned { id: 2, .. })
+        ));
+        assert!(matches!(
+            parse_payload(r#"{"t":"shout","id":7}"#),
+            Some(Event::Announce { id: 7 })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::dm(5, 9).to_json()),
+            Some(Event::Dm { id: 5, to_user: 9 })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::room_restore(4).to_json()),
+            Some(Event::RoomRestored { room_id: 4 })
+        ));
+        assert!(parse_payload(r#"{"t":"dm","id":5}"#).is_none());
+        assert!(parse_payload(r#"{"t":"nope","room_id":1,"id":2}"#).is_none());
+        assert!(parse_payload(r#"{"t":"msg","room_id":1}"#).is_none());
+    }
+
+    #[test]
+    fn membership_payloads_round_trip() {
+        let json = NotifyPayload::join(3, 9).to_json();
+        assert_eq!(json, r#"{"t":"join","room_id":3,"user_id":9}"#);
+        assert!(matches!(
+            parse_payload(&json),
+            Some(Event::Joined {
+                room_id: 3,
+                user_id: 9
+            })
+        ));
+        assert!(matches!(
+            parse_payload(&NotifyPayload::leave(3, 9).to_json()),
+            Some(Event::Left {
+                room_id: 3,
+                user_id: 9
+            })
+        ));
+        assert!(parse_payload(r#"{"t":"join","room_id":3}"#).is_none());
+    }
+
+    #[test]
+    fn validates_channel_names() {
+        assert!(valid_channel(DEFAULT_CHANNEL));
+        assert!(valid_channel("_bbs2"));
+        assert!(!valid_channel(""));
+        assert!(!valid_channel("2bbs"));
+        assert!(!valid_channel("Room_Events"));
+        assert!(!valid_channel("room-events"));
+        assert!(!valid_channel("a;b"));
+        assert!(valid_channel(&"a".repeat(54)));
+        assert!(!valid_channel(&"a".repeat(55)));
+        assert_eq!(Channels::new("bbs2").announce, "bbs2_announce");
+    }
+
+    #[test]
+    fn jitter_stays_under_ceiling() {
+        let mut rng = rand::thread_rng();
+        for ceiling in [1, 8, BACKOFF_MAX_SECS] {
+            for _ in 0..100 {
+                assert!(jittered(ceiling, &mut rng) <= Duration::from_secs(ceiling));
+            }
+        }
+        assert_eq!(jittered(0, &mut rng), Duration::ZERO);
+    }
+
+    #[test]
+    fn msg_payload_round_trips() {
+        let json = NotifyPayload::msg(3, 42).to_json();
+        assert_eq!(json, r#"{"t":"msg","room_id":3,"id":42}"#);
+        assert!(matches!(
+            parse_payload(&json),
+            Some(Event::Message { id: 42, room_id: 3 })
+        ));
+    }
+}
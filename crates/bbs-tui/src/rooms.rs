@@ -1,4 +1,5 @@
 // join/leave/history validators
+use chrono::{DateTime, Duration, NaiveTime, Utc};
 
 pub fn valid_room_name(name: &str) -> bool {
     let s = name.trim();
@@ -9,9 +10,222 @@ pub fn valid_room_name(name: &str) -> bool {
         .all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-'))
 }
 
+// Room passwords are the one word after the name in `/join <room> <password>`,
+// stored only as an argon2 hash.
+pub const ROOM_PASSWORD_MAX: usize = 64;
+
+pub fn hash_room_password(password: &str) -> anyhow::Result<String> {
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2::Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("hash room password: {}", e))?;
+    Ok(hash.to_string())
+}
+
+// An unparseable stored hash never matches.
+pub fn verify_room_password(password: &str, hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+    PasswordHash::new(hash).is_ok_and(|h| {
+        argon2::Argon2::default()
+            .verify_password(password.as_bytes(), &h)
+            .is_ok()
+    })
+}
+
+// Who may join a room for the first time (rooms.access, set with /access).
+// Invite rooms take newcomers only with a /roominvite code bound to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "room_access", rename_all = "lowercase")]
+pub enum RoomAccess {
+    Public,
+    Invite,
+}
+
+impl RoomAccess {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RoomAccess::Public => "public",
+            RoomAccess::Invite => "invite",
+        }
+    }
+}
+
+pub fn parse_room_access(arg: &str) -> Result<RoomAccess, String> {
+    match arg.to_ascii_lowercase().as_str() {
+        "public" => Ok(RoomAccess::Public),
+        "invite" => Ok(RoomAccess::Invite),
+        _ => Err("usage: /access [public|invite]".into()),
+    }
+}
+
+// A room's read-only window. Times are local to a fixed UTC offset (no DST);
+// start > end means the window crosses midnight, start == end means never.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub offset_mins: i32,
+}
+
+impl QuietHours {
+    pub fn active_at(&self, now: DateTime<Utc>) -> bool {
+        let t = (now + Duration::minutes(self.offset_mins as i64)).time();
+        if self.start <= self.end {
+            t >= self.start && t < self.end
+        } else {
+            t >= self.start || t < self.end
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{}-{} {}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            format_offset(self.offset_mins)
+        )
+    }
+}
+
+pub fn format_offset(mins: i32) -> String {
+    if mins == 0 {
+        return "UTC".into();
+    }
+    let sign = if mins < 0 { '-' } else { '+' };
+    format!("UTC{}{:02}:{:02}", sign, mins.abs() / 60, mins.abs() % 60)
+}
+
+// `/quiet <start> <end> [±HH:MM]`, times as HH:MM.
+pub fn parse_quiet(arg: &str) -> Result<QuietHours, String> {
+    const USAGE: &str = "usage: /quiet <HH:MM> <HH:MM> [+HH:MM|-HH:MM] | off";
+    let parts: Vec<&str> = arg.split_whitespace().collect();
+    let time = |s: &str| NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| USAGE.to_string());
+    let (start, end, offset) = match parts.as_slice() {
+        [s, e] => (time(s)?, time(e)?, None),
+        [s, e, o] => (time(s)?, time(e)?, Some(*o)),
+        _ => return Err(USAGE.into()),
+    };
+    let offset_mins = match offset {
+        None => 0,
+        Some(o) => {
+            let (sign, rest) = match o.strip_prefix('+') {
+                Some(r) => (1, r),
+                None => (-1, o.strip_prefix('-').ok_or(USAGE)?),
+            };
+            let (h, m) = rest.split_once(':').ok_or(USAGE)?;
+            let h: i32 = h.parse().map_err(|_| USAGE)?;
+            let m: i32 = m.parse().map_err(|_| USAGE)?;
+            if h > 14 || m > 59 {
+                return Err("utc offset must be within ±14:00".into());
+            }
+            sign * (h * 60 + m)
+        }
+    };
+    if start == end {
+        return Err("quiet window is empty (start equals end)".into());
+    }
+    Ok(QuietHours {
+        start,
+        end,
+        offset_mins,
+    })
+}
+
+// Matches the rooms.topic check; the pane title is one row.
+pub const TOPIC_MAX_LEN: usize = 120;
+
+// Normalized to one line; Err is the status text.
+pub fn parse_topic(arg: &str) -> Result<String, String> {
+    let text = crate::util::normalize_message(arg);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return Err("usage: /topic [text|off]".into());
+    }
+    if text.chars().count() > TOPIC_MAX_LEN {
+        return Err(format!("topic too long (max {} chars)", TOPIC_MAX_LEN));
+    }
+    Ok(text)
+}
+
+// /roomrate bounds; ten a second is already more than anyone types.
+pub const ROOM_RATE_MAX: u32 = 600;
+
+pub fn parse_room_rate(arg: &str) -> Result<u32, String> {
+    match arg.parse::<u32>() {
+        Ok(n) if (1..=ROOM_RATE_MAX).contains(&n) => Ok(n),
+        _ => Err(format!("usage: /roomrate [1-{}|off]", ROOM_RATE_MAX)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn room_passwords_verify_against_their_hash() {
+        let hash = hash_room_password("hunter2").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(verify_room_password("hunter2", &hash));
+        assert!(!verify_room_password("hunter3", &hash));
+        assert!(!verify_room_password("hunter2", "not a hash"));
+        // salted: the same password hashes differently each time
+        assert_ne!(hash, hash_room_password("hunter2").unwrap());
+    }
+
+    #[test]
+    fn room_access_names() {
+        assert_eq!(parse_room_access("invite"), Ok(RoomAccess::Invite));
+        assert_eq!(parse_room_access("Public"), Ok(RoomAccess::Public));
+        assert!(parse_room_access("private").is_err());
+        assert_eq!(RoomAccess::Invite.as_str(), "invite");
+    }
+
+    #[test]
+    fn room_rate_in_range() {
+        assert_eq!(parse_room_rate("30"), Ok(30));
+        assert!(parse_room_rate("0").is_err());
+        assert!(parse_room_rate("601").is_err());
+        assert!(parse_room_rate("fast").is_err());
+    }
+
+    #[test]
+    fn topic_is_one_trimmed_line() {
+        assert_eq!(
+            parse_topic("  rust \t and\nfriends ").unwrap(),
+            "rust and friends"
+        );
+        assert!(parse_topic(" \n ").is_err());
+        assert!(parse_topic(&"x".repeat(TOPIC_MAX_LEN)).is_ok());
+        assert!(parse_topic(&"x".repeat(TOPIC_MAX_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn quiet_window_crosses_midnight() {
+        let q = parse_quiet("22:00 07:00").unwrap();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert!(q.active_at(at("2026-01-01T23:30:00Z")));
+        assert!(q.active_at(at("2026-01-01T06:59:00Z")));
+        assert!(!q.active_at(at("2026-01-01T07:00:00Z")));
+        assert!(!q.active_at(at("2026-01-01T12:00:00Z")));
+
+        let day = parse_quiet("09:00 17:00 +02:00").unwrap();
+        assert_eq!(day.offset_mins, 120);
+        // 08:00Z is 10:00 at +02:00
+        assert!(day.active_at(at("2026-01-01T08:00:00Z")));
+        assert!(!day.active_at(at("2026-01-01T15:30:00Z")));
+        assert_eq!(day.describe(), "09:00-17:00 UTC+02:00");
+    }
+
+    #[test]
+    fn quiet_parse_rejects_bad_input() {
+        assert!(parse_quiet("22:00").is_err());
+        assert!(parse_quiet("25:00 07:00").is_err());
+        assert!(parse_quiet("10:00 10:00").is_err());
+        assert!(parse_quiet("22:00 07:00 +15:00").is_err());
+        assert_eq!(parse_quiet("22:00 07:00 -05:30").unwrap().offset_mins, -330);
+    }
+
     #[test]
     fn room_name_validation() {
         assert!(valid_room_name("lobby"));
@@ -0,0 +1,602 @@
+// data access the UI goes through; Postgres in the app, an in-memory fake in tests
+use anyhow::Result;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::data::{
+    self, Announcement, DirectMessage, Feedback, InsertOutcome, Invite, JoinOutcome,
+    MemberFingerprint, Message, MessageCursor, MessageView, PinOutcome, PurgeCriteria,
+    ReactionCount, Room, RoomJoined, RoomListing, RoomStats, RoomSummary, ServerInfo,
+    UnreadSummary, User, UserMessage, WhoSummary,
+};
+use crate::rooms::{QuietHours, RoomAccess};
+
+// One method per `data` query the UI needs, same names and arguments minus the pool.
+// The UI runs on the main task, so the futures don't need to be Send.
+pub trait Store {
+    async fn get_user_by_handle(&self, handle: &str) -> Result<Option<User>>;
+    async fn user_handle(&self, user_id: i64) -> Result<Option<String>>;
+    async fn change_handle(&self, user_id: i64, new_handle: &str) -> Result<User>;
+    async fn ensure_room_exists(&self, name: &str, created_by: i64) -> Result<(Room, bool)>;
+    async fn join_room(&self, channel: &str, room_id: i64, user_id: i64) -> Result<bool>;
+    async fn join_protected_room(
+        &self,
+        channel: &str,
+        name: &str,
+        user_id: i64,
+        secret: Option<&str>,
+    ) -> Result<JoinOutcome>;
+    async fn leave_room(&self, channel: &str, room_id: i64, user_id: i64) -> Result<bool>;
+    async fn list_joined_rooms(&self, user_id: i64) -> Result<Vec<RoomSummary>>;
+    async fn list_joined_rooms_with_times(&self, user_id: i64) -> Result<Vec<RoomJoined>>;
+    async fn user_rooms(&self, user_id: i64, viewer: i64) -> Result<Vec<RoomSummary>>;
+    async fn list_rooms(&self, viewer: i64) -> Result<Vec<RoomListing>>;
+    async fn room_member_counts(&self, user_id: i64) -> Result<Vec<(i64, i64)>>;
+    async fn list_recent_members(&self, room_id: i64, limit: i64) -> Result<Vec<WhoSummary>>;
+    async fn room_member_fingerprints(&self, room_id: i64) -> Result<Vec<MemberFingerprint>>;
+    async fn unread_summary(&self, room_id: i64, user_id: i64) -> Result<Option<UnreadSummary>>;
+    async fn mark_read(&self, room_id: i64, user_id: i64, message_id: i64) -> Result<()>;
+    async fn soft_delete_room_by_moderator(&self, name: &str, user_id: i64) -> Result<Option<i64>>;
+    async fn soft_delete_room_any(&self, name: &str) -> Result<Option<i64>>;
+    async fn restore_room(
+        &self,
+        channel: &str,
+        room_id: i64,
+        moderator_id: Option<i64>,
+        window: Duration,
+    ) -> Result<bool>;
+    async fn set_room_color(
+        &self,
+        room_id: i64,
+        color: Option<&str>,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn set_room_topic(
+        &self,
+        room_id: i64,
+        topic: Option<&str>,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn set_room_rate(
+        &self,
+        room_id: i64,
+        rate_per_min: Option<u32>,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn set_room_access(
+        &self,
+        room_id: i64,
+        access: RoomAccess,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn set_room_quiet(
+        &self,
+        room_id: i64,
+        quiet: Option<QuietHours>,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn purge_messages(
+        &self,
+        channel: &str,
+        room_id: i64,
+        actor_id: i64,
+        criteria: PurgeCriteria,
+    ) -> Result<Option<Vec<i64>>>;
+    async fn edit_last_message(
+        &self,
+        channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+    ) -> Result<Option<Message>>;
+    async fn soft_delete_message(&self, channel: &str, msg_id: i64, user_id: i64) -> Result<bool>;
+    async fn restore_message(
+        &self,
+        channel: &str,
+        msg_id: i64,
+        user_id: i64,
+        window: Duration,
+    ) -> Result<bool>;
+    async fn recent_messages_view(&self, room_id: i64, limit: i64) -> Result<Vec<MessageView>>;
+    async fn messages_before(
+        &self,
+        room_id: i64,
+        before: MessageCursor,
+        limit: i64,
+    ) -> Result<Vec<MessageView>>;
+    async fn messages_around(
+        &self,
+        room_id: i64,
+        center_id: i64,
+        radius: i64,
+    ) -> Result<Vec<MessageView>>;
+    async fn message_view_by_id(&self, id: i64) -> Result<Option<MessageView>>;
+    async fn insert_message(
+        &self,
+        channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+    ) -> Result<InsertOutcome>;
+    async fn insert_reply(
+        &self,
+        channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+        reply_to: i64,
+    ) -> Result<InsertOutcome>;
+    async fn user_messages(
+        &self,
+        user_id: i64,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<UserMessage>>;
+    async fn room_activity_buckets(
+        &self,
+        room_ids: &[i64],
+        buckets: i32,
+        window: Duration,
+    ) -> Result<Vec<(i64, Vec<i64>)>>;
+    async fn room_stats(&self, room_id: i64) -> Result<RoomStats>;
+    async fn server_info(&self) -> Result<ServerInfo>;
+    async fn insert_announcement(&self, channel: &str, user_id: i64, body: &str) -> Result<i64>;
+    async fn announcement_by_id(&self, id: i64) -> Result<Option<Announcement>>;
+    async fn insert_dm(
+        &self,
+        channel: &str,
+        from_user: i64,
+        to_handle: &str,
+        body: &str,
+    ) -> Result<Option<DirectMessage>>;
+    async fn dm_by_id(&self, id: i64) -> Result<Option<DirectMessage>>;
+    async fn submit_feedback(&self, user_id: i64, body: &str) -> Result<i64>;
+    async fn list_open_feedback(&self, limit: i64) -> Result<Vec<Feedback>>;
+    async fn mark_feedback_handled(&self, id: i64) -> Result<bool>;
+    async fn insert_invite(
+        &self,
+        code: &str,
+        created_by: i64,
+        uses: u32,
+        ttl: Option<chrono::Duration>,
+        room_id: Option<i64>,
+    ) -> Result<Invite>;
+    async fn create_invite(
+        &self,
+        created_by: i64,
+        max_uses: u32,
+        ttl: Option<chrono::Duration>,
+        room_id: Option<i64>,
+    ) -> Result<String>;
+    async fn delete_invite(&self, code: &str) -> Result<bool>;
+    async fn list_invites(&self, limit: i64) -> Result<Vec<Invite>>;
+    async fn get_signature(&self, user_id: i64) -> Result<Option<String>>;
+    async fn set_signature(&self, user_id: i64, signature: Option<&str>) -> Result<()>;
+    async fn set_away(&self, user_id: i64, reason: Option<&str>) -> Result<()>;
+    async fn set_last_room(&self, user_id: i64, room_id: i64) -> Result<()>;
+    async fn toggle_reaction(
+        &self,
+        channel: &str,
+        message_id: i64,
+        user_id: i64,
+        emoji: &str,
+    ) -> Result<Option<bool>>;
+    async fn reaction_counts(&self, msg_ids: &[i64]) -> Result<Vec<ReactionCount>>;
+    async fn set_pin(
+        &self,
+        channel: &str,
+        room_id: i64,
+        message_id: i64,
+        pinned: bool,
+        moderator_id: Option<i64>,
+    ) -> Result<PinOutcome>;
+    async fn list_pinned(&self, room_id: i64) -> Result<Vec<MessageView>>;
+    async fn is_moderator(&self, room_id: i64, user_id: i64) -> Result<bool>;
+    async fn mute_member(
+        &self,
+        room_id: i64,
+        target_user_id: i64,
+        actor_id: i64,
+        moderator_id: Option<i64>,
+        minutes: u32,
+    ) -> Result<bool>;
+    async fn kick_member(
+        &self,
+        channel: &str,
+        room_id: i64,
+        target_user_id: i64,
+        actor_id: i64,
+        moderator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn grant_moderator(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        granted_by: i64,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn revoke_moderator(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        creator_id: Option<i64>,
+    ) -> Result<bool>;
+    async fn add_ignore(&self, user_id: i64, ignored_user_id: i64) -> Result<bool>;
+    async fn remove_ignore(&self, user_id: i64, ignored_user_id: i64) -> Result<bool>;
+    async fn list_ignores(&self, user_id: i64) -> Result<Vec<i64>>;
+    async fn get_tagline(&self, user_id: i64) -> Result<Option<String>>;
+    async fn set_tagline(&self, user_id: i64, tagline: Option<&str>) -> Result<()>;
+    async fn get_setting(&self, key: &str) -> Result<Option<String>>;
+    async fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()>;
+}
+
+impl Store for PgPool {
+    async fn get_user_by_handle(&self, handle: &str) -> Result<Option<User>> {
+        data::get_user_by_handle(self, handle).await
+    }
+    async fn user_handle(&self, user_id: i64) -> Result<Option<String>> {
+        data::user_handle(self, user_id).await
+    }
+    async fn change_handle(&self, user_id: i64, new_handle: &str) -> Result<User> {
+        data::change_handle(self, user_id, new_handle).await
+    }
+    async fn ensure_room_exists(&self, name: &str, created_by: i64) -> Result<(Room, bool)> {
+        data::ensure_room_exists(self, name, created_by).await
+    }
+    async fn join_room(&self, channel: &str, room_id: i64, user_id: i64) -> Result<bool> {
+        data::join_room(self, channel, room_id, user_id).await
+    }
+    async fn join_protected_room(
+        &self,
+        channel: &str,
+        name: &str,
+        user_id: i64,
+        secret: Option<&str>,
+    ) -> Result<JoinOutcome> {
+        data::join_protected_room(self, channel, name, user_id, secret).await
+    }
+    async fn leave_room(&self, channel: &str, room_id: i64, user_id: i64) -> Result<bool> {
+        data::leave_room(self, channel, room_id, user_id).await
+    }
+    async fn list_joined_rooms(&self, user_id: i64) -> Result<Vec<RoomSummary>> {
+        data::list_joined_rooms(self, user_id).await
+    }
+    async fn list_joined_rooms_with_times(&self, user_id: i64) -> Result<Vec<RoomJoined>> {
+        data::list_joined_rooms_with_times(self, user_id).await
+    }
+    async fn user_rooms(&self, user_id: i64, viewer: i64) -> Result<Vec<RoomSummary>> {
+        data::user_rooms(self, user_id, viewer).await
+    }
+    async fn list_rooms(&self, viewer: i64) -> Result<Vec<RoomListing>> {
+        data::list_rooms(self, viewer).await
+    }
+    async fn room_member_counts(&self, user_id: i64) -> Result<Vec<(i64, i64)>> {
+        data::room_member_counts(self, user_id).await
+    }
+    async fn list_recent_members(&self, room_id: i64, limit: i64) -> Result<Vec<WhoSummary>> {
+        data::list_recent_members(self, room_id, limit).await
+    }
+    async fn room_member_fingerprints(&self, room_id: i64) -> Result<Vec<MemberFingerprint>> {
+        data::room_member_fingerprints(self, room_id).await
+    }
+    async fn unread_summary(&self, room_id: i64, user_id: i64) -> Result<Option<UnreadSummary>> {
+        data::unread_summary(self, room_id, user_id).await
+    }
+    async fn mark_read(&self, room_id: i64, user_id: i64, message_id: i64) -> Result<()> {
+        data::mark_read(self, room_id, user_id, message_id).await
+    }
+    async fn soft_delete_room_by_moderator(&self, name: &str, user_id: i64) -> Result<Option<i64>> {
+        data::soft_delete_room_by_moderator(self, name, user_id).await
+    }
+    async fn soft_delete_room_any(&self, name: &str) -> Result<Option<i64>> {
+        data::soft_delete_room_any(self, name).await
+    }
+    async fn restore_room(
+        &self,
+        channel: &str,
+        room_id: i64,
+        moderator_id: Option<i64>,
+        window: Duration,
+    ) -> Result<bool> {
+        data::restore_room(self, channel, room_id, moderator_id, window).await
+    }
+    async fn set_room_color(
+        &self,
+        room_id: i64,
+        color: Option<&str>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::set_room_color(self, room_id, color, creator_id).await
+    }
+    async fn set_room_topic(
+        &self,
+        room_id: i64,
+        topic: Option<&str>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::set_room_topic(self, room_id, topic, creator_id).await
+    }
+    async fn set_room_rate(
+        &self,
+        room_id: i64,
+        rate_per_min: Option<u32>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::set_room_rate(self, room_id, rate_per_min, creator_id).await
+    }
+    async fn set_room_access(
+        &self,
+        room_id: i64,
+        access: RoomAccess,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::set_room_access(self, room_id, access, creator_id).await
+    }
+    async fn set_room_quiet(
+        &self,
+        room_id: i64,
+        quiet: Option<QuietHours>,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::set_room_quiet(self, room_id, quiet, creator_id).await
+    }
+    async fn purge_messages(
+        &self,
+        channel: &str,
+        room_id: i64,
+        actor_id: i64,
+        criteria: PurgeCriteria,
+    ) -> Result<Option<Vec<i64>>> {
+        data::purge_messages(self, channel, room_id, actor_id, criteria).await
+    }
+    async fn edit_last_message(
+        &self,
+        channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+    ) -> Result<Option<Message>> {
+        data::edit_last_message(self, channel, room_id, user_id, body).await
+    }
+    async fn soft_delete_message(&self, channel: &str, msg_id: i64, user_id: i64) -> Result<bool> {
+        data::soft_delete_message(self, channel, msg_id, user_id).await
+    }
+    async fn restore_message(
+        &self,
+        channel: &str,
+        msg_id: i64,
+        user_id: i64,
+        window: Duration,
+    ) -> Result<bool> {
+        data::restore_message(self, channel, msg_id, user_id, window).await
+    }
+    async fn recent_messages_view(&self, room_id: i64, limit: i64) -> Result<Vec<MessageView>> {
+        data::recent_messages_view(self, room_id, limit).await
+    }
+    async fn messages_before(
+        &self,
+        room_id: i64,
+        before: MessageCursor,
+        limit: i64,
+    ) -> Result<Vec<MessageView>> {
+        data::messages_before(self, room_id, before, limit).await
+    }
+    async fn messages_around(
+        &self,
+        room_id: i64,
+        center_id: i64,
+        radius: i64,
+    ) -> Result<Vec<MessageView>> {
+        data::messages_around(self, room_id, center_id, radius).await
+    }
+    async fn message_view_by_id(&self, id: i64) -> Result<Option<MessageView>> {
+        data::message_view_by_id(self, id).await
+    }
+    async fn insert_message(
+        &self,
+        channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+    ) -> Result<InsertOutcome> {
+        data::insert_message(self, channel, room_id, user_id, body).await
+    }
+    async fn insert_reply(
+        &self,
+        channel: &str,
+        room_id: i64,
+        user_id: i64,
+        body: &str,
+        reply_to: i64,
+    ) -> Result<InsertOutcome> {
+        data::insert_reply(self, channel, room_id, user_id, body, reply_to).await
+    }
+    async fn user_messages(
+        &self,
+        user_id: i64,
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<UserMessage>> {
+        data::user_messages(self, user_id, offset, limit).await
+    }
+    async fn room_activity_buckets(
+        &self,
+        room_ids: &[i64],
+        buckets: i32,
+        window: Duration,
+    ) -> Result<Vec<(i64, Vec<i64>)>> {
+        data::room_activity_buckets(self, room_ids, buckets, window).await
+    }
+    async fn room_stats(&self, room_id: i64) -> Result<RoomStats> {
+        data::room_stats(self, room_id).await
+    }
+    async fn server_info(&self) -> Result<ServerInfo> {
+        data::server_info(self).await
+    }
+    async fn insert_announcement(&self, channel: &str, user_id: i64, body: &str) -> Result<i64> {
+        data::insert_announcement(self, channel, user_id, body).await
+    }
+    async fn announcement_by_id(&self, id: i64) -> Result<Option<Announcement>> {
+        data::announcement_by_id(self, id).await
+    }
+    async fn insert_dm(
+        &self,
+        channel: &str,
+        from_user: i64,
+        to_handle: &str,
+        body: &str,
+    ) -> Result<Option<DirectMessage>> {
+        data::insert_dm(self, channel, from_user, to_handle, body).await
+    }
+    async fn dm_by_id(&self, id: i64) -> Result<Option<DirectMessage>> {
+        data::dm_by_id(self, id).await
+    }
+    async fn submit_feedback(&self, user_id: i64, body: &str) -> Result<i64> {
+        data::submit_feedback(self, user_id, body).await
+    }
+    async fn list_open_feedback(&self, limit: i64) -> Result<Vec<Feedback>> {
+        data::list_open_feedback(self, limit).await
+    }
+    async fn mark_feedback_handled(&self, id: i64) -> Result<bool> {
+        data::mark_feedback_handled(self, id).await
+    }
+    async fn insert_invite(
+        &self,
+        code: &str,
+        created_by: i64,
+        uses: u32,
+        ttl: Option<chrono::Duration>,
+        room_id: Option<i64>,
+    ) -> Result<Invite> {
+        data::insert_invite(self, code, created_by, uses, ttl, room_id).await
+    }
+    async fn create_invite(
+        &self,
+        created_by: i64,
+        max_uses: u32,
+        ttl: Option<chrono::Duration>,
+        room_id: Option<i64>,
+    ) -> Result<String> {
+        data::create_invite(self, created_by, max_uses, ttl, room_id).await
+    }
+    async fn delete_invite(&self, code: &str) -> Result<bool> {
+        data::delete_invite(self, code).await
+    }
+    async fn list_invites(&self, limit: i64) -> Result<Vec<Invite>> {
+        data::list_invites(self, limit).await
+    }
+    async fn get_signature(&self, user_id: i64) -> Result<Option<String>> {
+        data::get_signature(self, user_id).await
+    }
+    async fn set_signature(&self, user_id: i64, signature: Option<&str>) -> Result<()> {
+        data::set_signature(self, user_id, signature).await
+    }
+    async fn set_away(&self, user_id: i64, reason: Option<&str>) -> Result<()> {
+        data::set_away(self, user_id, reason).await
+    }
+    async fn set_last_room(&self, user_id: i64, room_id: i64) -> Result<()> {
+        data::set_last_room(self, user_id, room_id).await
+    }
+    async fn toggle_reaction(
+        &self,
+        channel: &str,
+        message_id: i64,
+        user_id: i64,
+        emoji: &str,
+    ) -> Result<Option<bool>> {
+        data::toggle_reaction(self, channel, message_id, user_id, emoji).await
+    }
+    async fn reaction_counts(&self, msg_ids: &[i64]) -> Result<Vec<ReactionCount>> {
+        data::reaction_counts(self, msg_ids).await
+    }
+    async fn set_pin(
+        &self,
+        channel: &str,
+        room_id: i64,
+        message_id: i64,
+        pinned: bool,
+        moderator_id: Option<i64>,
+    ) -> Result<PinOutcome> {
+        data::set_pin(self, channel, room_id, message_id, pinned, moderator_id).await
+    }
+    async fn list_pinned(&self, room_id: i64) -> Result<Vec<MessageView>> {
+        data::list_pinned(self, room_id).await
+    }
+    async fn is_moderator(&self, room_id: i64, user_id: i64) -> Result<bool> {
+        data::is_moderator(self, room_id, user_id).await
+    }
+    async fn mute_member(
+        &self,
+        room_id: i64,
+        target_user_id: i64,
+        actor_id: i64,
+        moderator_id: Option<i64>,
+        minutes: u32,
+    ) -> Result<bool> {
+        data::mute_member(
+            self,
+            room_id,
+            target_user_id,
+            actor_id,
+            moderator_id,
+            minutes,
+        )
+        .await
+    }
+    async fn kick_member(
+        &self,
+        channel: &str,
+        room_id: i64,
+        target_user_id: i64,
+        actor_id: i64,
+        moderator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::kick_member(
+            self,
+            channel,
+            room_id,
+            target_user_id,
+            actor_id,
+            moderator_id,
+        )
+        .await
+    }
+    async fn grant_moderator(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        granted_by: i64,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::grant_moderator(self, room_id, user_id, granted_by, creator_id).await
+    }
+    async fn revoke_moderator(
+        &self,
+        room_id: i64,
+        user_id: i64,
+        creator_id: Option<i64>,
+    ) -> Result<bool> {
+        data::revoke_moderator(self, room_id, user_id, creator_id).await
+    }
+    async fn add_ignore(&self, user_id: i64, ignored_user_id: i64) -> Result<bool> {
+        data::add_ignore(self, user_id, ignored_user_id).await
+    }
+    async fn remove_ignore(&self, user_id: i64, ignored_user_id: i64) -> Result<bool> {
+        data::remove_ignore(self, user_id, ignored_user_id).await
+    }
+    async fn list_ignores(&self, user_id: i64) -> Result<Vec<i64>> {
+        data::list_ignores(self, user_id).await
+    }
+    async fn get_tagline(&self, user_id: i64) -> Result<Option<String>> {
+        data::get_tagline(self, user_id).await
+    }
+    async fn set_tagline(&self, user_id: i64, tagline: Option<&str>) -> Result<()> {
+        data::set_tagline(self, user_id, tagline).await
+    }
+    async fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        data::get_setting(self, key).await
+    }
+    async fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()> {
+        data::set_setting(self, key, value).await
+    }
+}
@@ -0,0 +1,68 @@
+// terminal setup/teardown; the guard restores the terminal on every exit path
+use anyhow::Result;
+use crossterm::event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Alignment, Rect};
+use ratatui::widgets::{Paragraph, Wrap};
+use ratatui::Frame;
+use std::io;
+
+// Raw mode + alternate screen for as long as this lives. Dropping it (normal
+// return, `?`, or a panic unwinding) puts the SSH client's terminal back.
+pub struct TermGuard {
+    bracketed_paste: bool,
+}
+
+impl TermGuard {
+    pub fn enter(bracketed_paste: bool) -> Result<Self> {
+        enable_raw_mode()?;
+        // built before the rest of setup so a failure below still restores raw mode
+        let guard = TermGuard { bracketed_paste };
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, DisableMouseCapture)?;
+        if bracketed_paste {
+            execute!(stdout, EnableBracketedPaste)?;
+        }
+        Ok(guard)
+    }
+}
+
+impl Drop for TermGuard {
+    fn drop(&mut self) {
+        // best effort: nothing useful to do with an error while tearing down
+        let mut stdout = io::stdout();
+        if self.bracketed_paste {
+            let _ = execute!(stdout, DisableBracketedPaste);
+        }
+        let _ = execute!(stdout, LeaveAlternateScreen, crossterm::cursor::Show);
+        let _ = disable_raw_mode();
+    }
+}
+
+// Smallest area a screen's fixed layout fits in. Below it, ratatui hands out
+// zero-height chunks and row arithmetic can underflow, so draw a notice instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+impl MinSize {
+    pub fn fits(&self, area: Rect) -> bool {
+        area.width >= self.width && area.height >= self.height
+    }
+}
+
+pub fn render_too_small(f: &mut Frame, need: MinSize) {
+    let msg = format!(
+        "terminal too small (need \u{2265} {}x{})",
+        need.width, need.height
+    );
+    let p = Paragraph::new(msg)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(p, f.size());
+}
@@ -0,0 +1,219 @@
+// terminal color capability + palette mapping
+use ratatui::style::{Color, Style};
+
+// What the client terminal can render, lowest to highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorLevel {
+    NoColor,
+    Basic16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorLevel {
+    pub fn detect() -> Self {
+        Self::from_env(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref(),
+            std::env::var_os("NO_COLOR").is_some(),
+        )
+    }
+
+    // Pure form of `detect` so the rules can be tested without touching env.
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>, no_color: bool) -> Self {
+        let term = term.unwrap_or("").trim();
+        if no_color || term.is_empty() || term == "dumb" {
+            return ColorLevel::NoColor;
+        }
+        let colorterm = colorterm.unwrap_or("").to_ascii_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorLevel::TrueColor;
+        }
+        if term.contains("256color") {
+            return ColorLevel::Ansi256;
+        }
+        ColorLevel::Basic16
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorLevel::NoColor => "none",
+            ColorLevel::Basic16 => "16",
+            ColorLevel::Ansi256 => "256",
+            ColorLevel::TrueColor => "truecolor",
+        }
+    }
+}
+
+// Map a color down to what the terminal supports; None means "don't color".
+pub fn adapt(level: ColorLevel, color: Color) -> Option<Color> {
+    match level {
+        ColorLevel::NoColor => None,
+        ColorLevel::TrueColor => Some(color),
+        ColorLevel::Ansi256 => match color {
+            Color::Rgb(r, g, b) => Some(Color::Indexed(rgb_to_256(r, g, b))),
+            c => Some(c),
+        },
+        ColorLevel::Basic16 => match color {
+            Color::Rgb(r, g, b) => Some(nearest_16(r, g, b)),
+            Color::Indexed(n) => Some(indexed_to_16(n)),
+            c => Some(c),
+        },
+    }
+}
+
+// Foreground style for `color`, or the plain default style on no-color terminals.
+pub fn fg(level: ColorLevel, color: Color) -> Style {
+    match adapt(level, color) {
+        Some(c) => Style::default().fg(c),
+        None => Style::default(),
+    }
+}
+
+// Background counterpart of `fg`.
+pub fn bg(level: ColorLevel, color: Color) -> Style {
+    match adapt(level, color) {
+        Some(c) => Style::default().bg(c),
+        None => Style::default(),
+    }
+}
+
+// Room accents: the names `/roomcolor` accepts, and the pool derived colors come from.
+pub const ACCENTS: [(&str, Color); 12] = [
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("lightred", Color::LightRed),
+    ("lightgreen", Color::LightGreen),
+    ("lightyellow", Color::LightYellow),
+    ("lightblue", Color::LightBlue),
+    ("lightmagenta", Color::LightMagenta),
+    ("lightcyan", Color::LightCyan),
+];
+
+pub fn accent_by_name(name: &str) -> Option<Color> {
+    ACCENTS.iter().find(|(n, _)| *n == name).map(|(_, c)| *c)
+}
+
+// Stable across sessions and builds (FNV-1a, not std's randomized hasher).
+fn palette_pick(name: &str) -> Color {
+    let h = name.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    ACCENTS[h as usize % ACCENTS.len()].1
+}
+
+// Default accent for a room.
+pub fn room_color(name: &str) -> Color {
+    palette_pick(name)
+}
+
+// Handle color in the message pane; same palette as the room accents.
+pub fn handle_color(handle: &str) -> Color {
+    palette_pick(handle)
+}
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let scale = |v: u8| ((v as u16 * 5 + 127) / 255) as u8;
+    16 + 36 * scale(r) + 6 * scale(g) + scale(b)
+}
+
+// Approximate xterm defaults for the 16 ANSI colors.
+const BASIC16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    let dist = |(pr, pg, pb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, pr) + d(g, pg) + d(b, pb)
+    };
+    BASIC16
+        .iter()
+        .min_by_key(|(_, rgb)| dist(*rgb))
+        .map(|(c, _)| *c)
+        .unwrap_or(Color::Reset)
+}
+
+fn indexed_to_16(n: u8) -> Color {
+    if n < 16 {
+        return BASIC16[n as usize].0;
+    }
+    let (r, g, b) = if n >= 232 {
+        let v = 8 + 10 * (n - 232);
+        (v, v, v)
+    } else {
+        let i = n - 16;
+        let step = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+        (step(i / 36), step((i / 6) % 6), step(i % 6))
+    };
+    nearest_16(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_levels() {
+        use ColorLevel::{Ansi256, Basic16, NoColor, TrueColor};
+        assert_eq!(ColorLevel::from_env(None, Some("dumb"), false), NoColor);
+        assert_eq!(ColorLevel::from_env(None, None, false), NoColor);
+        assert_eq!(ColorLevel::from_env(None, Some("xterm"), true), NoColor);
+        assert_eq!(ColorLevel::from_env(None, Some("xterm"), false), Basic16);
+        assert_eq!(
+            ColorLevel::from_env(None, Some("xterm-256color"), false),
+            Ansi256
+        );
+        assert_eq!(
+            ColorLevel::from_env(Some("truecolor"), Some("xterm-256color"), false),
+            TrueColor
+        );
+    }
+
+    #[test]
+    fn room_colors_are_stable() {
+        assert_eq!(room_color("lobby"), room_color("lobby"));
+        assert!(ACCENTS.iter().any(|(_, c)| *c == room_color("dev")));
+        assert_eq!(handle_color("quiet-otter"), handle_color("quiet-otter"));
+        let picks: std::collections::HashSet<_> = ["alice", "bob", "carol", "dave", "erin"]
+            .map(handle_color)
+            .into();
+        assert!(picks.len() > 1);
+        assert_eq!(accent_by_name("cyan"), Some(Color::Cyan));
+        assert_eq!(accent_by_name("Cyan"), None);
+        assert_eq!(accent_by_name("off"), None);
+    }
+
+    #[test]
+    fn maps_down_palette() {
+        let rgb = Color::Rgb(250, 10, 10);
+        assert_eq!(adapt(ColorLevel::TrueColor, rgb), Some(rgb));
+        assert_eq!(adapt(ColorLevel::Ansi256, rgb), Some(Color::Indexed(196)));
+        assert_eq!(adapt(ColorLevel::Basic16, rgb), Some(Color::LightRed));
+        assert_eq!(
+            adapt(ColorLevel::Basic16, Color::Indexed(244)),
+            Some(Color::DarkGray)
+        );
+        assert_eq!(adapt(ColorLevel::Basic16, Color::Cyan), Some(Color::Cyan));
+        assert_eq!(adapt(ColorLevel::NoColor, Color::Cyan), None);
+    }
+}
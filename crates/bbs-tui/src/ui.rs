@@ -1,51 +1,300 @@
-use anyhow::{anyhow, Result};
-use crossterm::{
-    event::{self, DisableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
-    style::{Modifier, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph},
     Terminal,
 };
 use sqlx::PgPool;
-use std::{io, time::Duration};
+use std::{
+    io,
+    time::{Duration, Instant},
+};
 
-use crate::data::{self, MessageView, Room, User};
-use crate::input::{parse_command, Command};
+use crate::data::{self, JoinOutcome, MessageView, PinOutcome, Room, User};
+use crate::input::{message_text, parse_command, Command};
+use crate::life::{self, Life, LifeWidget};
+use crate::msgfmt::{split_code_blocks, MsgFormat, Segment};
 use crate::nick::valid_nick;
 use crate::rate::TokenBucket;
 use crate::realtime;
-use crate::rooms::valid_room_name;
-use crate::util::normalize_message;
-use std::collections::HashSet;
+use crate::rooms::{self, valid_room_name, RoomAccess, ROOM_PASSWORD_MAX};
+use crate::store::Store;
+use crate::term::{self, MinSize, TermGuard};
+use crate::theme::{self, ColorLevel};
+use crate::util::{
+    age_short, check_body_size, fp_short, mentions, message_refs, normalize_message, parse_motd,
+    parse_ttl, sparkline, valid_reaction, with_signature, AWAY_MAX_LEN, INVITE_MAX_USES,
+    SIGNATURE_MAX_LEN, TAGLINE_MAX_LEN,
+};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use tokio::sync::mpsc;
 
 pub struct UiOpts {
     pub history_load: u32,
     pub msg_max_len: usize,
+    pub msg_max_bytes: usize,
     pub fp_short: String,
     pub rate_per_min: u32,
+    // BBS_RATE_BURST; None means the full per-minute rate
+    pub rate_burst: Option<u32>,
     pub is_admin: bool,
+    pub retention_days: u32,
+    pub color_level: ColorLevel,
+    pub idle_timeout_secs: u64,
+    pub screensaver_secs: u64,
+    pub sparkline: bool,
+    // BBS_SHOW_TAGLINE: append taglines to handles in message lines
+    pub show_tagline: bool,
+    // BBS_CATCH_UP: keep last-read marks and show what was missed on entering a room
+    pub catch_up: bool,
+    pub msg_format: MsgFormat,
+    // BBS_TIME_FORMAT=relative: `{time}` renders as an age (5m, 2h, 1d); /time flips it
+    pub relative_time: bool,
+    pub default_room: String,
+    // BBS_AUDIT_DIR: where `/audit save` writes; saving is off without it
+    pub audit_dir: Option<std::path::PathBuf>,
+    pub channels: realtime::Channels,
+    // first-login greeting, shown only to this session and never stored
+    pub greeting: Option<String>,
 }
 
-struct App {
-    pool: PgPool,
+struct App<S> {
+    store: S,
     user: User,
     room: Room,
     opts: UiOpts,
     input: String,
-    status: String,
+    // live messages naming us since the room was opened (status line)
+    mentions: usize,
+    // caret as a byte offset into `input`; see `cursor_pos`
+    cursor: usize,
+    // lines submitted this session, oldest first; Up/Down walk it
+    sent_history: Vec<String>,
+    // entry shown in the input while walking; None once it's edited or sent
+    history_pos: Option<usize>,
+    status: Status,
     messages: Vec<MessageView>,
     seen_ids: HashSet<i64>,
+    // /clear: events for ids up to here are for cleared messages; 0 when not cleared
+    clear_floor: i64,
     rooms: Vec<RoomEntry>,
     running: bool,
+    // set when state changed since the last frame; the loop skips idle redraws
+    dirty: bool,
+    // last key press; realtime traffic doesn't count as activity
+    last_input: Instant,
+    // last key press or new message in this room; drives the screensaver
+    last_activity: Instant,
+    screensaver: Option<Life>,
     bucket: TokenBucket,
-    show_help: bool,
+    overlay: Option<Overlay>,
+    link_sel: Option<LinkSel>,
+    signature: Option<String>,
+    tagline: Option<String>,
+    // false while the realtime listener is down; sends go to `outbox` instead
+    online: bool,
+    // realtime feed: LISTEN (true) or the polling fallback
+    feed_live: bool,
+    outbox: VecDeque<Queued>,
+    // latest admin announcement, pinned above the message pane until dismissed
+    banner: Option<data::Announcement>,
+    // most recent soft delete this session, for /undo
+    last_deletion: Option<LastDeletion>,
+    // a large paste (plus whatever was typed before it) waiting for Enter/Esc
+    pending_paste: Option<String>,
+    // /filter: show only this user's messages; a view filter, `messages` keeps everything
+    filter: Option<ViewFilter>,
+    // /ignore: user ids whose room messages are hidden and don't count as unread
+    ignored: HashSet<i64>,
+    greeting: Option<Notice>,
+    // "N new messages from ..." above what arrived since the last visit
+    catch_up: Option<Notice>,
+    // /msg traffic this session, shown in whichever room was open when it happened
+    dms: Vec<Notice>,
+    // rows scrolled up from the newest line; 0 follows new messages
+    scroll_offset: usize,
+    // message pane height at the last draw, for paging and clamping
+    pane_rows: Cell<usize>,
+    // ...and its inner width, the wrap column; 0 before the first draw (no wrapping)
+    pane_cols: Cell<usize>,
+    // /goto target, highlighted until the deadline
+    goto: Option<(i64, Instant)>,
+    // the buffer is a /goto window that stops short of the newest messages;
+    // scrolling back to the bottom (or sending) reloads them
+    detached: bool,
+    // per loaded message: (emoji, count) in first-use order
+    reactions: HashMap<i64, Vec<(String, i64)>>,
+    // this room's pinned messages, most recently pinned first
+    pins: Vec<MessageView>,
+}
+
+// Pinned into the pane after message `after_id`, so later messages flow below it
+// like any other line.
+struct Notice {
+    text: String,
+    room_id: i64,
+    after_id: i64,
+}
+
+struct ViewFilter {
+    user_id: i64,
+    handle: String,
+}
+
+struct LastDeletion {
+    kind: Deleted,
+    at: Instant,
+}
+
+enum Deleted {
+    Room { id: i64, name: String },
+    Message { id: i64 },
+}
+
+const UNDO_WINDOW: Duration = Duration::from_secs(60);
+
+// Seen this recently (an open session heartbeats every 30s) earns a `*` in /who.
+const WHO_ACTIVE_WINDOW: chrono::Duration = chrono::Duration::minutes(5);
+
+// Up/Down recall; only this session's lines, oldest dropped first.
+const SENT_HISTORY_MAX: usize = 100;
+
+// Pastes over either limit need confirmation before they're sent.
+const PASTE_MAX_LINES: usize = 3;
+const PASTE_MAX_CHARS: usize = 300;
+
+// A message typed while offline, sent in order once the listener reconnects.
+struct Queued {
+    room_id: i64,
+    body: String,
+    // /reply parent, kept whole so the sent reply can show its quote
+    reply: Option<MessageView>,
+}
+
+const OUTBOX_MAX: usize = 20;
+
+// Centered modal drawn over the main layout; Esc closes it, PageUp/PageDown scroll.
+struct Overlay {
+    title: String,
+    lines: Vec<Line<'static>>,
+    scroll: u16,
+    // set when more lines can be fetched once the user pages past the end
+    more: Option<OverlayMore>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OverlayMore {
+    Mine { offset: i64 },
+}
+
+const OVERLAY_PAGE: u16 = 10;
+const MINE_PAGE: i64 = 50;
+// matches the feedback table's body check
+const FEEDBACK_MAX_LEN: usize = 1000;
+const FEEDBACK_REVIEW_LIMIT: i64 = 100;
+
+impl Overlay {
+    fn new(title: impl Into<String>, lines: Vec<Line<'static>>) -> Self {
+        Overlay {
+            title: title.into(),
+            lines,
+            scroll: 0,
+            more: None,
+        }
+    }
+}
+
+// A selected `#<id>` reference: which message it's in, where, and its target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LinkSel {
+    msg_id: i64,
+    start: usize,
+    target: i64,
+}
+
+// In-session unread counters stop here; the sidebar shows anything over 99 as 99+.
+const UNREAD_CAP: usize = 999;
+
+const STATUS_HINT: &str = "/help for commands";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Info,
+    Success,
+    Warn,
+    Error,
+}
+
+impl Severity {
+    // Confirmations clear quickly; listings and problems stay up long enough to read.
+    fn ttl(self) -> Duration {
+        match self {
+            Severity::Success => Duration::from_secs(4),
+            Severity::Info | Severity::Warn => Duration::from_secs(12),
+            Severity::Error => Duration::from_secs(15),
+        }
+    }
+
+    // Plain (non-light) ANSI colors so both dark and light backgrounds stay readable.
+    fn style(self, level: ColorLevel) -> Style {
+        match self {
+            Severity::Info => Style::default(),
+            Severity::Success => theme::fg(level, Color::Green),
+            Severity::Warn => theme::fg(level, Color::Yellow),
+            Severity::Error => theme::fg(level, Color::Red).add_modifier(Modifier::BOLD),
+        }
+    }
+}
+
+// Status line text; reverts to the hint once `expires_at` passes.
+struct Status {
+    severity: Severity,
+    text: String,
+    expires_at: Option<Instant>,
+}
+
+impl Status {
+    fn hint() -> Self {
+        Status {
+            severity: Severity::Info,
+            text: STATUS_HINT.into(),
+            expires_at: None,
+        }
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|t| now >= t)
+    }
+}
+
+impl<S> App<S> {
+    // Messages the pane shows under the current /filter, minus /ignore'd authors.
+    fn visible_messages(&self) -> impl Iterator<Item = &MessageView> {
+        self.messages.iter().filter(move |m| self.shows(m))
+    }
+
+    fn shows(&self, m: &MessageView) -> bool {
+        let only = self.filter.as_ref().map(|f| f.user_id);
+        only.is_none_or(|id| m.user_id == Some(id)) && !self.is_ignored(m.user_id)
+    }
+
+    fn is_ignored(&self, user_id: Option<i64>) -> bool {
+        user_id.is_some_and(|id| self.ignored.contains(&id))
+    }
+
+    fn set_status(&mut self, severity: Severity, text: impl Into<String>) {
+        self.status = Status {
+            severity,
+            text: text.into(),
+            expires_at: Some(Instant::now() + severity.ttl()),
+        };
+        self.dirty = true;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -53,179 +302,995 @@ struct RoomEntry {
     id: i64,
     name: String,
     unread: usize,
+    // 0 until the first count comes in; kept current by join/leave events
+    members: usize,
+    // message counts per bucket, oldest first; empty unless BBS_SPARKLINE=1
+    activity: Vec<i64>,
 }
 
-pub async fn run(pool: PgPool, user: User, room: Room, opts: UiOpts) -> Result<()> {
-    // setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, DisableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-    terminal.show_cursor()?;
-
-    // preload messages
-    let bucket = TokenBucket::new(opts.rate_per_min);
-    let mut app = App {
-        messages: data::recent_messages_view(&pool, room.id, opts.history_load as i64).await?,
-        pool,
-        user,
-        room,
-        opts,
-        input: String::new(),
-        status: String::from("/help for commands"),
-        running: true,
-        seen_ids: HashSet::new(),
-        rooms: vec![],
-        bucket,
-        show_help: false,
-    };
-    for m in &app.messages {
-        app.seen_ids.insert(m.id);
+impl RoomEntry {
+    fn new(id: i64, name: String) -> Self {
+        RoomEntry {
+            id,
+            name,
+            unread: 0,
+            members: 0,
+            activity: vec![],
+        }
     }
+}
 
-    // load rooms list (only rooms the user has joined)
-    let list = data::list_joined_rooms(&app.pool, app.user.id).await?;
-    app.rooms = list
-        .into_iter()
-        .map(|r| RoomEntry {
-            id: r.id,
-            name: r.name,
-            unread: 0,
-        })
-        .collect();
-    if !app.rooms.iter().any(|r| r.id == app.room.id) {
-        app.rooms.push(RoomEntry {
-            id: app.room.id,
-            name: app.room.name.clone(),
-            unread: 0,
-        });
+const SPARK_BUCKETS: i32 = 8;
+const SPARK_WINDOW: Duration = Duration::from_secs(30 * 60);
+const SPARK_REFRESH: Duration = Duration::from_secs(60);
+// Relative timestamps only change by the minute.
+const AGE_REFRESH: Duration = Duration::from_secs(30);
+
+impl<S: Store> App<S> {
+    // Initial state for a session: recent history, joined rooms, signature.
+    async fn load(store: S, user: User, room: Room, opts: UiOpts) -> Result<Self> {
+        let bucket = room_bucket(&room, &opts);
+        let signature = store.get_signature(user.id).await?;
+        let ignored = store.list_ignores(user.id).await?.into_iter().collect();
+        let tagline = store.get_tagline(user.id).await?;
+        let mut app = App {
+            messages: store
+                .recent_messages_view(room.id, opts.history_load as i64)
+                .await?,
+            store,
+            user,
+            room,
+            opts,
+            input: String::new(),
+            mentions: 0,
+            cursor: 0,
+            sent_history: Vec::new(),
+            history_pos: None,
+            status: Status::hint(),
+            running: true,
+            dirty: true,
+            last_input: Instant::now(),
+            last_activity: Instant::now(),
+            screensaver: None,
+            seen_ids: HashSet::new(),
+            clear_floor: 0,
+            rooms: vec![],
+            bucket,
+            overlay: None,
+            link_sel: None,
+            signature,
+            tagline,
+            online: true,
+            feed_live: true,
+            outbox: VecDeque::new(),
+            banner: None,
+            last_deletion: None,
+            pending_paste: None,
+            filter: None,
+            ignored,
+            greeting: None,
+            catch_up: None,
+            dms: vec![],
+            scroll_offset: 0,
+            pane_rows: Cell::new(DEFAULT_PANE_ROWS),
+            pane_cols: Cell::new(0),
+            goto: None,
+            detached: false,
+            reactions: HashMap::new(),
+            pins: vec![],
+        };
+        for m in &app.messages {
+            app.seen_ids.insert(m.id);
+        }
+        let ids: Vec<i64> = app.messages.iter().map(|m| m.id).collect();
+        load_reactions(&mut app, &ids).await?;
+        load_pins(&mut app).await?;
+        catch_up(&mut app).await?;
+        if let Some(text) = app.opts.greeting.clone() {
+            app.greeting = Some(Notice {
+                text,
+                room_id: app.room.id,
+                after_id: app.messages.last().map_or(0, |m| m.id),
+            });
+        }
+        show_motd(&mut app).await?;
+
+        // load rooms list (only rooms the user has joined)
+        let list = app.store.list_joined_rooms(app.user.id).await?;
+        app.rooms = list
+            .into_iter()
+            .map(|r| RoomEntry::new(r.id, r.name))
+            .collect();
+        if !app.rooms.iter().any(|r| r.id == app.room.id) {
+            app.rooms
+                .push(RoomEntry::new(app.room.id, app.room.name.clone()));
+        }
+        refresh_member_counts(&mut app).await?;
+        Ok(app)
     }
+}
+
+pub async fn run(pool: PgPool, user: User, room: Room, opts: UiOpts) -> Result<()> {
+    let mut app = App::load(pool.clone(), user, room, opts).await?;
+
+    // setup terminal; restored when `term` drops, however we leave this fn
+    let term = TermGuard::enter(true)?;
+    let backend = CrosstermBackend::new(io::stdout());
+    let mut terminal = Terminal::new(backend)?;
+    terminal.show_cursor()?;
 
     // realtime listener
     let (tx, mut rx) = mpsc::channel::<realtime::Event>(128);
-    realtime::spawn_listener(app.pool.clone(), tx).await;
+    realtime::spawn_listener(pool, app.opts.channels.clone(), tx).await;
 
     // event loop
+    let mut drawn_tokens = None;
+    let idle_timeout =
+        (app.opts.idle_timeout_secs > 0).then(|| Duration::from_secs(app.opts.idle_timeout_secs));
+    let mut idle_exit = false;
+    let screensaver_after =
+        (app.opts.screensaver_secs > 0).then(|| Duration::from_secs(app.opts.screensaver_secs));
+    let mut last_step = Instant::now();
+    let mut last_spark: Option<Instant> = None;
+    let mut last_age_draw = Instant::now();
     while app.running {
-        // refresh rate bucket view
+        if app.opts.relative_time && last_age_draw.elapsed() >= AGE_REFRESH {
+            last_age_draw = Instant::now();
+            app.dirty = true;
+        }
+        // sidebar sparklines poll the DB, so refresh on a slow timer, not per frame
+        if app.opts.sparkline
+            && app.online
+            && last_spark.is_none_or(|t| t.elapsed() >= SPARK_REFRESH)
+        {
+            // sparklines are decoration: a failed poll keeps the old ones, retried
+            // on the next tick of the timer rather than ending the session
+            if let Err(e) = refresh_activity(&mut app).await {
+                app.set_status(
+                    Severity::Warn,
+                    format!("couldn't refresh room activity: {}", e),
+                );
+            }
+            last_spark = Some(Instant::now());
+        }
+        if idle_timeout.is_some_and(|t| app.last_input.elapsed() >= t) {
+            app.set_status(Severity::Warn, "disconnecting due to inactivity");
+            let tokens_left = app.bucket.peek_tokens().floor() as i32;
+            let tokens_cap = app.bucket.capacity().round() as i32;
+            draw(&mut terminal, &app, tokens_left, tokens_cap)?;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            idle_exit = true;
+            break;
+        }
+        tick(&mut app, Instant::now());
+        if app.screensaver.is_none()
+            && screensaver_after.is_some_and(|t| app.last_activity.elapsed() >= t)
+        {
+            let sz = terminal.size()?;
+            app.screensaver = Some(Life::new(sz.width as usize, sz.height as usize));
+            app.dirty = true;
+        }
+        if let Some(life) = app.screensaver.as_mut() {
+            if last_step.elapsed() >= life::STEP_INTERVAL {
+                let sz = terminal.size()?;
+                life.resize(sz.width as usize, sz.height as usize);
+                life.step();
+                life.maybe_spawn();
+                last_step = Instant::now();
+                app.dirty = true;
+            }
+        }
+        // refresh rate bucket view; redraw only when something changed
         let tokens_left = app.bucket.peek_tokens().floor() as i32;
         let tokens_cap = app.bucket.capacity().round() as i32;
-        draw(&mut terminal, &app, tokens_left, tokens_cap)?;
+        if app.dirty || drawn_tokens != Some(tokens_left) {
+            draw(&mut terminal, &app, tokens_left, tokens_cap)?;
+            app.dirty = false;
+            drawn_tokens = Some(tokens_left);
+        }
         // drain realtime events
         while let Ok(ev) = rx.try_recv() {
-            match ev {
-                realtime::Event::Message { id, room_id } => {
-                    if room_id == app.room.id {
-                        if let Some(v) = data::message_view_by_id(&app.pool, id).await? {
-                            if !app.seen_ids.contains(&v.id) {
-                                app.seen_ids.insert(v.id);
-                                app.messages.push(v);
-                            }
-                        }
-                    } else if let Some(re) = app.rooms.iter_mut().find(|r| r.id == room_id) {
-                        re.unread = re.unread.saturating_add(1);
+            apply_event(&mut app, ev).await?;
+        }
+        let poll = if app.screensaver.is_some() {
+            life::STEP_INTERVAL
+        } else {
+            Duration::from_millis(200)
+        };
+        if event::poll(poll)? {
+            match event::read()? {
+                Event::Key(k) => {
+                    app.last_input = Instant::now();
+                    app.last_activity = app.last_input;
+                    // the key that wakes the screensaver isn't passed through
+                    if app.screensaver.take().is_none() {
+                        handle_key(&mut app, k).await?;
+                    }
+                    app.dirty = true;
+                }
+                Event::Paste(text) => {
+                    app.last_input = Instant::now();
+                    app.last_activity = app.last_input;
+                    if app.screensaver.take().is_none() {
+                        handle_paste(&mut app, &text);
                     }
+                    app.dirty = true;
                 }
+                Event::Resize(_, _) => app.dirty = true,
+                _ => {}
             }
         }
-        if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(k) = event::read()? {
-                handle_key(&mut app, k).await?;
+    }
+
+    // best effort: a lost connection shouldn't turn leaving into an error
+    let _ = mark_read(&mut app).await;
+    // restore before printing so the message lands on the normal screen
+    drop(term);
+    if idle_exit {
+        println!("disconnected due to inactivity");
+    }
+    Ok(())
+}
+
+// Timed state that lapses on its own; marks the frame dirty only when something did.
+fn tick<S>(app: &mut App<S>, now: Instant) {
+    if app.goto.is_some_and(|(_, until)| now >= until) {
+        app.goto = None;
+        app.dirty = true;
+    }
+    if app.status.expired(now) {
+        app.status = Status::hint();
+        app.dirty = true;
+    }
+}
+
+fn push_own_message(app: &mut App<impl Store>, msg: data::Message, reply: Option<&MessageView>) {
+    if msg.room_id != app.room.id || app.seen_ids.contains(&msg.id) {
+        return;
+    }
+    let mv = MessageView {
+        id: msg.id,
+        room_id: msg.room_id,
+        user_id: Some(msg.user_id),
+        user_handle: app.user.handle.clone(),
+        body: msg.body,
+        created_at: msg.created_at,
+        tagline: app.tagline.clone(),
+        edited: false,
+        reply_to: reply.map(|p| p.id),
+        quote_handle: reply.map(|p| p.user_handle.clone()),
+        quote_body: reply.map(|p| p.body.clone()),
+    };
+    app.seen_ids.insert(mv.id);
+    app.messages.push(mv);
+    // sending jumps back to the newest messages
+    app.scroll_offset = 0;
+}
+
+fn queue_offline(app: &mut App<impl Store>, body: String, reply: Option<MessageView>) {
+    if app.outbox.len() >= OUTBOX_MAX {
+        app.set_status(Severity::Error, "offline; queue full, message not sent");
+        return;
+    }
+    app.outbox.push_back(Queued {
+        room_id: app.room.id,
+        body,
+        reply,
+    });
+    app.input.clear();
+    app.set_status(
+        Severity::Warn,
+        format!("offline; message queued ({})", app.outbox.len()),
+    );
+}
+
+// Status text for a send the server turned away.
+fn rejected_text(out: &data::InsertOutcome) -> String {
+    if let Some(until) = out.muted_until {
+        // round up, so the last few seconds still read 1m
+        let left = until - chrono::Utc::now() + chrono::Duration::seconds(59);
+        return format!("you are muted for {}", age_short(left));
+    }
+    match out.quiet_until {
+        Some(t) => format!("room is quiet until {}", t.format("%H:%M")),
+        None => "rate limited (server)".to_string(),
+    }
+}
+
+// Send queued messages in order; stop at the first rejection and keep the rest.
+// Each send takes a token like a typed one; `app.bucket` only covers the open room,
+// the server still limits the others.
+async fn flush_outbox(app: &mut App<impl Store>) -> Result<()> {
+    let mut sent = 0;
+    while let Some(q) = app.outbox.front() {
+        let here = q.room_id == app.room.id;
+        if here && !app.bucket.try_consume(1.0) {
+            let wait = app.bucket.time_until(1.0).as_secs_f64().ceil().max(1.0);
+            app.set_status(
+                Severity::Warn,
+                format!(
+                    "back online; rate limited — retry in {}s, {} still queued",
+                    wait as u64,
+                    app.outbox.len()
+                ),
+            );
+            return Ok(());
+        }
+        let ch = &app.opts.channels.events;
+        let res = match &q.reply {
+            Some(p) => {
+                app.store
+                    .insert_reply(ch, q.room_id, app.user.id, &q.body, p.id)
+                    .await
+            }
+            None => {
+                app.store
+                    .insert_message(ch, q.room_id, app.user.id, &q.body)
+                    .await
+            }
+        };
+        match res {
+            Ok(out) => {
+                if here {
+                    app.bucket.sync_remaining(out.remaining as f64);
+                }
+                let Some(msg) = out.message else {
+                    let why = match (out.muted_until, out.quiet_until) {
+                        (Some(_), _) => "you are muted",
+                        (None, Some(_)) => "room is quiet",
+                        (None, None) => "rate limited",
+                    };
+                    app.set_status(
+                        Severity::Warn,
+                        format!("back online; {}, {} still queued", why, app.outbox.len()),
+                    );
+                    return Ok(());
+                };
+                let q = app.outbox.pop_front();
+                reattach(app).await?;
+                push_own_message(app, msg, q.and_then(|q| q.reply).as_ref());
+                sent += 1;
+            }
+            Err(e) if data::is_connection_error(&e) => {
+                app.online = false;
+                return Ok(());
             }
+            Err(e) => return Err(e),
         }
     }
+    if sent > 0 {
+        app.set_status(
+            Severity::Success,
+            format!("back online; sent {} queued", sent),
+        );
+    } else {
+        app.set_status(Severity::Success, "back online");
+    }
+    Ok(())
+}
 
-    // restore terminal
-    disable_raw_mode()?;
-    let w = terminal.backend_mut();
-    crossterm::execute!(w, LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+// On entering a room: summarize what arrived since the last visit, then mark
+// everything loaded as read. Both are off without BBS_CATCH_UP.
+async fn catch_up(app: &mut App<impl Store>) -> Result<()> {
+    if !app.opts.catch_up {
+        return Ok(());
+    }
+    app.catch_up = app
+        .store
+        .unread_summary(app.room.id, app.user.id)
+        .await?
+        .map(|s| Notice {
+            text: catch_up_text(&s),
+            room_id: app.room.id,
+            after_id: s.after_id,
+        });
+    mark_read(app).await
+}
+
+// Called before leaving a room's view, so messages read live count too.
+async fn mark_read(app: &mut App<impl Store>) -> Result<()> {
+    if !app.opts.catch_up {
+        return Ok(());
+    }
+    // an empty room still gets a mark, so its first messages are summarized later
+    let newest = app.messages.last().map_or(0, |m| m.id);
+    app.store.mark_read(app.room.id, app.user.id, newest).await
+}
+
+// Names the first few senders so the line fits beside the sidebar.
+const CATCH_UP_NAMES: usize = 3;
+
+fn catch_up_text(s: &data::UnreadSummary) -> String {
+    let mut from = s.senders[..s.senders.len().min(CATCH_UP_NAMES)].join(", ");
+    if s.senders.len() > CATCH_UP_NAMES {
+        from.push_str(&format!(" +{} more", s.senders.len() - CATCH_UP_NAMES));
+    }
+    let noun = if s.count == 1 { "message" } else { "messages" };
+    format!(
+        "{} new {} from {} since {}",
+        s.count,
+        noun,
+        from,
+        s.since.format("%H:%M")
+    )
+}
+
+// Apply a realtime event to the loaded buffer. Edits/deletes/reactions mutate the
+// message in place since `seen_ids` would otherwise drop them as duplicates.
+async fn apply_event(app: &mut App<impl Store>, ev: realtime::Event) -> Result<()> {
+    use realtime::Event as Ev;
+    let (id, room_id) = match ev {
+        Ev::Health { online } => {
+            let was_online = std::mem::replace(&mut app.online, online);
+            if online && !was_online {
+                flush_outbox(app).await?;
+            } else if !online && was_online {
+                app.set_status(Severity::Warn, "offline; messages will be queued");
+            }
+            return Ok(());
+        }
+        Ev::ConnState(live) => {
+            if std::mem::replace(&mut app.feed_live, live) != live {
+                app.dirty = true;
+            }
+            return Ok(());
+        }
+        Ev::Joined { room_id, user_id }
+        | Ev::Left { room_id, user_id }
+        | Ev::Kicked { room_id, user_id } => {
+            // our own joins are counted when the room opens
+            if user_id == app.user.id {
+                if matches!(ev, Ev::Kicked { .. }) {
+                    removed_from_room(app, room_id).await?;
+                }
+                return Ok(());
+            }
+            if let Some(re) = app.rooms.iter_mut().find(|r| r.id == room_id) {
+                re.members = match ev {
+                    Ev::Joined { .. } => re.members + 1,
+                    _ => re.members.saturating_sub(1),
+                };
+                app.dirty = true;
+            }
+            if room_id != app.room.id {
+                return Ok(());
+            }
+            if let Some(handle) = app.store.user_handle(user_id).await? {
+                let verb = match ev {
+                    Ev::Joined { .. } => "joined",
+                    Ev::Kicked { .. } => "was removed from",
+                    _ => "left",
+                };
+                app.set_status(
+                    Severity::Info,
+                    format!("{} {} {}", handle, verb, app.room.name),
+                );
+                app.dirty = true;
+            }
+            return Ok(());
+        }
+        Ev::RoomRestored { room_id } => {
+            return room_restored(app, room_id).await;
+        }
+        Ev::Announce { id } => {
+            if let Some(a) = app.store.announcement_by_id(id).await? {
+                app.banner = Some(a);
+                app.dirty = true;
+            }
+            return Ok(());
+        }
+        Ev::Dm { id, to_user } => {
+            if to_user != app.user.id {
+                return Ok(());
+            }
+            if let Some(dm) = app.store.dm_by_id(id).await? {
+                let text = format!("[dm] {}: {}", dm.from_handle, sanitize(&dm.body));
+                push_dm(app, text);
+                app.set_status(
+                    Severity::Info,
+                    format!(
+                        "dm from {} (reply: /msg {} ...)",
+                        dm.from_handle, dm.from_handle
+                    ),
+                );
+            }
+            return Ok(());
+        }
+        Ev::Message { id, room_id }
+        | Ev::Edited { id, room_id }
+        | Ev::Deleted { id, room_id }
+        | Ev::Restored { id, room_id }
+        | Ev::Reacted { id, room_id }
+        | Ev::Pinned { id, room_id } => (id, room_id),
+    };
+    if room_id != app.room.id {
+        if !matches!(ev, Ev::Message { .. }) || !app.rooms.iter().any(|r| r.id == room_id) {
+            return Ok(());
+        }
+        // only worth a fetch when someone is ignored
+        if !app.ignored.is_empty() {
+            let author = app
+                .store
+                .message_view_by_id(id)
+                .await?
+                .and_then(|v| v.user_id);
+            if app.is_ignored(author) {
+                return Ok(());
+            }
+        }
+        if let Some(re) = app.rooms.iter_mut().find(|r| r.id == room_id) {
+            re.unread = (re.unread + 1).min(UNREAD_CAP);
+            app.dirty = true;
+        }
+        return Ok(());
+    }
+    // replies in the pane quote this message; keep their previews current
+    if matches!(ev, Ev::Edited { .. } | Ev::Deleted { .. })
+        && app.messages.iter().any(|m| m.reply_to == Some(id))
+    {
+        let parent = match ev {
+            Ev::Edited { .. } => app.store.message_view_by_id(id).await?,
+            _ => None,
+        };
+        for c in app.messages.iter_mut().filter(|m| m.reply_to == Some(id)) {
+            c.quote_handle = parent.as_ref().map(|p| p.user_handle.clone());
+            c.quote_body = parent.as_ref().map(|p| p.body.clone());
+        }
+        app.dirty = true;
+    }
+    // the pin bar shows current text, so edits and deletes of a pin reload it too
+    if matches!(ev, Ev::Pinned { .. })
+        || matches!(ev, Ev::Edited { .. } | Ev::Deleted { .. })
+            && app.pins.iter().any(|p| p.id == id)
+    {
+        load_pins(app).await?;
+    }
+    let pos = app.messages.iter().position(|m| m.id == id);
+    match (ev, pos) {
+        (Ev::Pinned { .. }, _) => {}
+        (Ev::Deleted { .. }, Some(_)) => drop_messages(app, &[id]),
+        (Ev::Deleted { .. }, None) => {}
+        (Ev::Reacted { .. }, Some(_)) => load_reactions(app, &[id]).await?,
+        (Ev::Reacted { .. }, None) => {}
+        (Ev::Edited { .. }, Some(i)) => {
+            if let Some(v) = app.store.message_view_by_id(id).await? {
+                app.messages[i] = v;
+                app.dirty = true;
+            }
+        }
+        // Not loaded yet (or a brand-new message): fetch it, but only append
+        // when it's newer than the buffer so history order stays intact. An
+        // older one inside the loaded window is a restored message (/undo).
+        (_, None) => {
+            let newest = app.messages.last().map(|m| m.id).unwrap_or(0);
+            if app.seen_ids.contains(&id) || id <= app.clear_floor {
+                return Ok(());
+            }
+            if id < newest {
+                let oldest = app.messages.first().map_or(newest, |m| m.id);
+                if id > oldest {
+                    if let Some(v) = app.store.message_view_by_id(id).await? {
+                        insert_in_order(app, v);
+                    }
+                }
+                return Ok(());
+            }
+            // past a /goto window; it loads with the rest on the way back down
+            if app.detached {
+                return Ok(());
+            }
+            if let Some(v) = app.store.message_view_by_id(id).await? {
+                // scrolled up: grow the offset by the new rows (a day separator
+                // included) so the view stays put
+                let shown = app.shows(&v);
+                let pinned = app.scroll_offset > 0 && shown;
+                let before = if pinned { pane_lines(app).len() } else { 0 };
+                if shown && mentions_me(app, &v) {
+                    app.mentions += 1;
+                }
+                app.seen_ids.insert(v.id);
+                app.messages.push(v);
+                if pinned {
+                    app.scroll_offset += pane_lines(app).len() - before;
+                }
+                app.last_activity = Instant::now();
+                app.dirty = true;
+            }
+        }
+        (
+            Ev::Message { .. }
+            | Ev::Restored { .. }
+            | Ev::Health { .. }
+            | Ev::ConnState(_)
+            | Ev::Announce { .. }
+            | Ev::Dm { .. }
+            | Ev::Joined { .. }
+            | Ev::Left { .. }
+            | Ev::Kicked { .. }
+            | Ev::RoomRestored { .. },
+            Some(_),
+        ) => {}
+    }
+    Ok(())
+}
+
+// Memberships survive a soft delete, so a restored room the user belongs to comes
+// back in the sidebar; the other entries keep their unread counts and activity.
+async fn room_restored(app: &mut App<impl Store>, room_id: i64) -> Result<()> {
+    if app.rooms.iter().any(|r| r.id == room_id) {
+        return Ok(());
+    }
+    let joined = app.store.list_joined_rooms(app.user.id).await?;
+    if let Some(r) = joined.into_iter().find(|r| r.id == room_id) {
+        app.rooms.push(RoomEntry::new(r.id, r.name));
+        refresh_member_counts(app).await?;
+        app.dirty = true;
+    }
+    Ok(())
+}
+
+// Kicked: drop the room from the sidebar and, if it was focused, fall back to
+// the default room.
+async fn removed_from_room(app: &mut App<impl Store>, room_id: i64) -> Result<()> {
+    let Some(idx) = app.rooms.iter().position(|r| r.id == room_id) else {
+        return Ok(());
+    };
+    let name = app.rooms.remove(idx).name;
+    if room_id == app.room.id {
+        let (room, _) = app
+            .store
+            .ensure_room_exists(&app.opts.default_room, app.user.id)
+            .await?;
+        open_room(app, room).await?;
+    }
+    app.set_status(Severity::Warn, format!("you were removed from {}", name));
+    Ok(())
+}
+
+// Deleted messages leave the buffer entirely; the scroll offset is clamped so
+// the view doesn't point past the first row.
+fn drop_messages(app: &mut App<impl Store>, ids: &[i64]) {
+    app.messages.retain(|m| !ids.contains(&m.id));
+    for id in ids {
+        app.seen_ids.remove(id);
+    }
+    app.scroll_offset = app.scroll_offset.min(max_scroll(app));
+    app.dirty = true;
+}
+
+// Replace the reaction summaries of `ids` (messages without any drop out).
+async fn load_reactions(app: &mut App<impl Store>, ids: &[i64]) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+    for id in ids {
+        app.reactions.remove(id);
+    }
+    for c in app.store.reaction_counts(ids).await? {
+        app.reactions
+            .entry(c.message_id)
+            .or_default()
+            .push((c.emoji, c.n));
+    }
+    app.dirty = true;
+    Ok(())
+}
+
+async fn load_pins(app: &mut App<impl Store>) -> Result<()> {
+    app.pins = app.store.list_pinned(app.room.id).await?;
+    app.dirty = true;
+    Ok(())
+}
+
+fn insert_in_order(app: &mut App<impl Store>, v: MessageView) {
+    let at = app.messages.partition_point(|m| m.id < v.id);
+    app.seen_ids.insert(v.id);
+    app.messages.insert(at, v);
+    app.dirty = true;
+}
+
+// Replace the buffer with the newest page of the open room, at the bottom of the pane.
+async fn load_newest(app: &mut App<impl Store>) -> Result<()> {
+    app.messages = app
+        .store
+        .recent_messages_view(app.room.id, app.opts.history_load as i64)
+        .await?;
+    app.messages.retain(|m| m.id > app.clear_floor);
+    app.seen_ids = app.messages.iter().map(|m| m.id).collect();
+    app.reactions.clear();
+    let ids: Vec<i64> = app.messages.iter().map(|m| m.id).collect();
+    load_reactions(app, &ids).await?;
+    app.detached = false;
+    app.scroll_offset = 0;
+    app.dirty = true;
+    Ok(())
+}
+
+// Leaving a /goto window for the live end of the room.
+async fn reattach(app: &mut App<impl Store>) -> Result<()> {
+    if app.detached {
+        load_newest(app).await?;
+    }
+    Ok(())
+}
+
+// Make `room` the open one: join it, load fresh history at the bottom of the
+// pane, and reset its per-room counters.
+async fn open_room(app: &mut App<impl Store>, room: Room) -> Result<()> {
+    app.store
+        .join_room(&app.opts.channels.events, room.id, app.user.id)
+        .await?;
+    // each room has its own server-side window
+    app.bucket = room_bucket(&room, &app.opts);
+    app.store.set_last_room(app.user.id, room.id).await?;
+    app.room = room;
+    app.clear_floor = 0;
+    load_newest(app).await?;
+    load_pins(app).await?;
+    app.mentions = 0;
+    match app.rooms.iter_mut().find(|r| r.id == app.room.id) {
+        Some(rm) => rm.unread = 0,
+        None => app
+            .rooms
+            .push(RoomEntry::new(app.room.id, app.room.name.clone())),
+    }
+    refresh_member_counts(app).await
+}
+
+// Whole seconds, rounded up, so "retry in 1s" never means "now".
+fn client_rate_limited(app: &mut App<impl Store>) {
+    let wait = app.bucket.time_until(1.0).as_secs_f64().ceil().max(1.0);
+    app.set_status(
+        Severity::Error,
+        format!("rate limited — retry in {}s", wait as u64),
+    );
+}
+
+// Send limit for `room`: its /roomrate, else BBS_RATE_PER_MIN.
+fn room_rate(room: &Room, opts: &UiOpts) -> u32 {
+    room.rate_per_min.map_or(opts.rate_per_min, |n| n as u32)
+}
+
+// The burst never exceeds the room's per-minute limit; the server would refuse the rest.
+fn room_bucket(room: &Room, opts: &UiOpts) -> TokenBucket {
+    let rate = room_rate(room, opts);
+    match opts.rate_burst {
+        Some(b) => TokenBucket::with_burst(rate, b.min(rate)),
+        None => TokenBucket::new(rate),
+    }
+}
+
+// One query for the whole sidebar; rooms it doesn't cover keep their count.
+async fn refresh_member_counts(app: &mut App<impl Store>) -> Result<()> {
+    for (room_id, n) in app.store.room_member_counts(app.user.id).await? {
+        if let Some(re) = app.rooms.iter_mut().find(|r| r.id == room_id) {
+            re.members = n as usize;
+        }
+    }
+    app.dirty = true;
     Ok(())
 }
 
+// DM lines go after the newest loaded message of the room that is open.
+fn push_dm(app: &mut App<impl Store>, text: String) {
+    if app.scroll_offset > 0 {
+        app.scroll_offset += 1;
+    }
+    app.dms.push(Notice {
+        text: text.replace('\n', " "),
+        room_id: app.room.id,
+        after_id: app.messages.last().map_or(0, |m| m.id),
+    });
+    app.dirty = true;
+}
+
+// Until the first draw measures the pane.
+const DEFAULT_PANE_ROWS: usize = 20;
+
+// Fixed parts of the chat layout; the message pane gets whatever is left.
+const STATUS_ROWS: u16 = 1;
+const INPUT_ROWS: u16 = 3;
+// The input box grows with Shift/Alt+Enter line breaks up to this many rows of text.
+const INPUT_MAX_LINES: u16 = 5;
+const SIDEBAR_COLS: u16 = 24;
+const MIN_MESSAGE_COLS: u16 = 10;
+
+// Border rows plus one per pending input line; the oldest lines scroll off past the cap.
+fn input_rows(input: &str) -> u16 {
+    let lines = input.split('\n').count().min(INPUT_MAX_LINES as usize) as u16;
+    (lines + 2).max(INPUT_ROWS)
+}
+
+fn min_size() -> MinSize {
+    MinSize {
+        width: MIN_MESSAGE_COLS + SIDEBAR_COLS,
+        // at least one message row
+        height: STATUS_ROWS + 1 + INPUT_ROWS,
+    }
+}
+
 fn draw(
     terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    app: &App,
+    app: &App<impl Store>,
     tokens_left: i32,
     tokens_cap: i32,
 ) -> Result<()> {
     terminal.draw(|f| {
         let size = f.size();
+        if !min_size().fits(size) {
+            term::render_too_small(f, min_size());
+            return;
+        }
+        if let Some(life) = &app.screensaver {
+            f.render_widget(
+                LifeWidget::new(life).color_level(app.opts.color_level),
+                size,
+            );
+            return;
+        }
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(1),
+                Constraint::Length(STATUS_ROWS),
                 Constraint::Min(1),
-                Constraint::Length(3),
+                Constraint::Length(input_rows(&app.input)),
             ])
             .split(size);
 
         // status line
         let admin_tag = if app.opts.is_admin { " | admin" } else { "" };
+        let filter_tag = match &app.filter {
+            Some(f) => format!(" | filter:{}", f.handle),
+            None => String::new(),
+        };
+        let quiet_tag = match app.room.quiet_hours() {
+            Some(q) if q.active_at(chrono::Utc::now()) => {
+                format!(" | quiet until {}", q.end.format("%H:%M"))
+            }
+            _ => String::new(),
+        };
+        let mention_tag = match app.mentions {
+            0 => String::new(),
+            1 => " | 1 mention".to_string(),
+            n => format!(" | {} mentions", n),
+        };
+        let away_tag = if app.user.away_reason.is_some() {
+            " | away"
+        } else {
+            ""
+        };
+        // polling means new messages can lag by a couple of seconds
+        let feed_tag = if app.feed_live { "[live]" } else { "[poll]" };
         let title = format!(
-            "{} @ {} | msgs:{} | rate:{}/{} | fp:{}{}",
+            "{} @ {} | msgs:{} | rate:{}/{} | fp:{} | {}{}{}{}{}{}",
             app.user.handle,
             app.room.name,
             app.messages.len(),
             tokens_left,
             tokens_cap,
             app.opts.fp_short,
+            feed_tag,
+            filter_tag,
+            quiet_tag,
+            mention_tag,
+            away_tag,
             admin_tag,
         );
+        let accent = room_accent(&app.room);
         let status = Paragraph::new(Span::styled(
             title,
-            Style::default().add_modifier(Modifier::BOLD),
+            theme::fg(app.opts.color_level, accent).add_modifier(Modifier::BOLD),
         ));
         f.render_widget(status, chunks[0]);
 
         // messages pane split main + sidebar
         let msg_chunks = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Min(10), Constraint::Length(24)])
+            .constraints([
+                Constraint::Min(MIN_MESSAGE_COLS),
+                Constraint::Length(SIDEBAR_COLS),
+            ])
             .split(chunks[1]);
 
-        let lines: Vec<Line> = app
-            .messages
-            .iter()
-            .map(|m| {
-                let ts = m.created_at.format("%H:%M:%S");
-                Line::from(format!("[{}] {}: {}", ts, m.user_handle, sanitize(&m.body)))
-            })
-            .collect();
-        let messages =
-            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("messages"));
-        f.render_widget(messages, msg_chunks[0]);
-
-        // sidebar rooms
-        let side_lines: Vec<Line> = app
-            .rooms
-            .iter()
-            .map(|r| {
-                let cur = if r.id == app.room.id { '>' } else { ' ' };
-                if r.unread > 0 {
-                    Line::from(format!("{} {} ({})", cur, r.name, r.unread))
-                } else {
-                    Line::from(format!("{} {}", cur, r.name))
+        // inner width excludes the borders; the sidebar already took its share
+        app.pane_cols
+            .set(msg_chunks[0].width.saturating_sub(2) as usize);
+        let lines = pane_lines(app);
+        let mut msg_area = msg_chunks[0];
+        if let Some(a) = &app.banner {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(1)])
+                .split(msg_area);
+            let style = theme::fg(app.opts.color_level, Color::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::REVERSED);
+            let text = format!(
+                " [{}] ANNOUNCEMENT from {}: {}  (Esc to dismiss)",
+                a.created_at.format("%H:%M"),
+                a.user_handle,
+                sanitize(&a.body).replace('\n', " ")
+            );
+            f.render_widget(
+                Paragraph::new(Span::styled(text, style)).style(style),
+                rows[0],
+            );
+            msg_area = rows[1];
+        }
+        if let Some(text) = pin_bar(&app.pins) {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Min(1)])
+                .split(msg_area);
+            let style = theme::fg(app.opts.color_level, accent);
+            f.render_widget(Paragraph::new(Span::styled(text, style)), rows[0]);
+            msg_area = rows[1];
+        }
+        // show the bottom of the buffer, `scroll_offset` rows further up
+        let rows = msg_area.height.saturating_sub(2) as usize;
+        app.pane_rows.set(rows);
+        let offset = app.scroll_offset.min(lines.len().saturating_sub(rows));
+        let top = lines.len().saturating_sub(rows + offset);
+        let mut title = match &app.room.topic {
+            Some(t) => format!("messages \u{2014} {}", sanitize(t)),
+            None => "messages".to_string(),
+        };
+        if offset > 0 {
+            title.push_str(&format!(" (\u{2191}{} rows, PgDn for newer)", offset));
+        }
+        let messages = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((top.min(u16::MAX as usize) as u16, 0));
+        f.render_widget(messages, msg_area);
+
+        // sidebar rooms (inner width excludes the borders)
+        let sidebar_width = msg_chunks[1].width.saturating_sub(2) as usize;
+        let side_lines: Vec<Line> = app
+            .rooms
+            .iter()
+            .map(|r| {
+                let current = r.id == app.room.id;
+                let cur = if current { '>' } else { ' ' };
+                let spark = sparkline(&r.activity);
+                let text = sidebar_line(cur, &r.name, r.members, &spark, r.unread, sidebar_width);
+                if !current {
+                    return Line::from(text);
                 }
+                // the marker is one char, so the rest of the row starts at byte 1
+                let marker = theme::fg(app.opts.color_level, accent).add_modifier(Modifier::BOLD);
+                Line::from(vec![
+                    Span::styled(text[..1].to_string(), marker),
+                    Span::raw(text[1..].to_string()),
+                ])
             })
             .collect();
         let sidebar =
             Paragraph::new(side_lines).block(Block::default().borders(Borders::ALL).title("rooms"));
         f.render_widget(sidebar, msg_chunks[1]);
 
-        // input line
-        let input = Paragraph::new(app.input.as_str()).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(app.status.as_str()),
-        );
+        // input line; a pending paste prompt stays up until answered
+        let title = match &app.pending_paste {
+            Some(p) => Span::styled(paste_prompt(p), Severity::Warn.style(app.opts.color_level)),
+            None => Span::styled(
+                app.status.text.as_str(),
+                app.status.severity.style(app.opts.color_level),
+            ),
+        };
+        // keep the caret's line in view once the box stops growing
+        let (row, col) = cursor_cell(app);
+        let hidden = (row + 1).saturating_sub(INPUT_MAX_LINES);
+        let input = Paragraph::new(app.input.as_str())
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((hidden, 0));
         f.render_widget(input, chunks[2]);
+        if app.overlay.is_none() && app.pending_paste.is_none() {
+            let area = chunks[2];
+            let x = area.x + 1 + col.min(area.width.saturating_sub(3));
+            f.set_cursor(x, area.y + 1 + row - hidden);
+        }
 
-        // Help overlay
-        if app.show_help {
-            // Build help content
-            let lines = build_help_lines(app.opts.is_admin);
+        // Overlay (help, info panels)
+        if let Some(ov) = &app.overlay {
+            let lines = ov.lines.clone();
             // Centered modal size
             let modal_w = size.width.min(78);
             let modal_h = (lines.len() as u16 + 4).min(size.height.saturating_sub(2));
@@ -248,139 +1313,454 @@ fn draw(
             let area = outer_h[1];
             // Clear area first so underlying borders/text don't show through
             f.render_widget(Clear, area);
-            let help = Paragraph::new(lines).block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("help (Esc to close)"),
-            );
-            f.render_widget(help, area);
+            let hint = if ov.more.is_some() {
+                "Esc to close, PgDn for more"
+            } else {
+                "Esc to close"
+            };
+            let modal = Paragraph::new(lines)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(theme::fg(app.opts.color_level, Color::Cyan))
+                        .title(format!("{} ({})", ov.title, hint)),
+                )
+                .scroll((ov.scroll, 0));
+            f.render_widget(modal, area);
         }
     })?;
     Ok(())
 }
 
+// Dark amber behind messages that name you.
+const MENTION_BG: Color = Color::Rgb(72, 52, 0);
+
+// Someone else's message naming our current handle.
+fn mentions_me(app: &App<impl Store>, m: &MessageView) -> bool {
+    m.user_id != Some(app.user.id) && mentions(&m.body, &app.user.handle)
+}
+
+// Calm blue behind a /goto target while it's highlighted.
+const GOTO_BG: Color = Color::Rgb(30, 50, 90);
+const GOTO_HIGHLIGHT: Duration = Duration::from_secs(3);
+
+fn pane_lines(app: &App<impl Store>) -> Vec<Line<'static>> {
+    pane_layout(app, None).0
+}
+
+// Everything the message pane can show, oldest first: each visible message's rows,
+// with the greeting and catch-up notices slotted in after the message they follow.
+// Also which of those rows message `find` occupies (None if hidden).
+fn pane_layout(
+    app: &App<impl Store>,
+    find: Option<i64>,
+) -> (Vec<Line<'static>>, Option<std::ops::Range<usize>>) {
+    let level = app.opts.color_level;
+    let mut notices: Vec<(i64, Line)> = Vec::new();
+    if let Some(c) = app.catch_up.as_ref().filter(|c| c.room_id == app.room.id) {
+        let style = theme::fg(level, Color::Yellow).add_modifier(Modifier::DIM);
+        let text = format!("--- {} ---", c.text);
+        notices.push((c.after_id, Line::from(Span::styled(text, style))));
+    }
+    if let Some(g) = app.greeting.as_ref().filter(|g| g.room_id == app.room.id) {
+        let style = theme::fg(level, Color::Cyan).add_modifier(Modifier::ITALIC);
+        let text = format!("*** {}", g.text);
+        notices.push((g.after_id, Line::from(Span::styled(text, style))));
+    }
+    let dm_style = theme::fg(level, Color::Magenta).add_modifier(Modifier::BOLD);
+    for d in app.dms.iter().filter(|d| d.room_id == app.room.id) {
+        let line = Line::from(Span::styled(d.text.clone(), dm_style));
+        notices.push((d.after_id, line));
+    }
+    // stable, so DMs after the same message keep their order
+    notices.sort_by_key(|(after, _)| *after);
+    let mut notices = notices.into_iter().peekable();
+    let cols = app.pane_cols.get();
+    let mut lines = Vec::new();
+    let mut found = None;
+    let mut prev_day = None;
+    for m in app.visible_messages() {
+        while let Some((_, l)) = notices.next_if(|(after, _)| m.id > *after) {
+            lines.extend(wrap_line(l, cols));
+        }
+        let day = m.created_at.date_naive();
+        if prev_day.is_some_and(|d| d != day) {
+            lines.push(day_separator(day, cols));
+        }
+        prev_day = Some(day);
+        let selected = app
+            .link_sel
+            .filter(|sel| sel.msg_id == m.id)
+            .map(|sel| sel.start);
+        let rows = message_lines(m, &app.opts, selected, cols);
+        let start = lines.len();
+        if app.goto.is_some_and(|(id, _)| id == m.id) {
+            let style = theme::bg(level, GOTO_BG).add_modifier(Modifier::BOLD);
+            lines.extend(rows.into_iter().map(|l| l.patch_style(style)));
+        } else if mentions_me(app, m) {
+            let style = theme::bg(level, MENTION_BG).add_modifier(Modifier::BOLD);
+            lines.extend(rows.into_iter().map(|l| l.patch_style(style)));
+        } else {
+            lines.extend(rows);
+        }
+        if let Some(rs) = app.reactions.get(&m.id).filter(|rs| !rs.is_empty()) {
+            lines.extend(wrap_line(reaction_line(rs), cols));
+        }
+        if find == Some(m.id) {
+            found = Some(start..lines.len());
+        }
+    }
+    lines.extend(notices.flat_map(|(_, l)| wrap_line(l, cols)));
+    (lines, found)
+}
+
+// One row above the pane: the latest pin, and how many more /pins lists.
+fn pin_bar(pins: &[MessageView]) -> Option<String> {
+    let p = pins.first()?;
+    let more = match pins.len() {
+        1 => String::new(),
+        n => format!("  (+{} more: /pins)", n - 1),
+    };
+    Some(format!(
+        " \u{1f4cc} #{} {}: {}{}",
+        p.id,
+        p.user_handle,
+        sanitize(&p.body).replace('\n', " "),
+        more
+    ))
+}
+
+// The row under a message with reactions: `  👍2 😄1`.
+fn reaction_line(counts: &[(String, i64)]) -> Line<'static> {
+    let parts: Vec<String> = counts
+        .iter()
+        .map(|(emoji, n)| format!("{}{}", emoji, n))
+        .collect();
+    Line::from(Span::styled(
+        format!("{}{}", " ".repeat(WRAP_INDENT), parts.join(" ")),
+        Style::default().add_modifier(Modifier::DIM),
+    ))
+}
+
+// `── Tue Jan 14 ──`, centered in `cols` (UTC days, like the timestamps).
+fn day_separator(day: chrono::NaiveDate, cols: usize) -> Line<'static> {
+    let label = format!(
+        "\u{2500}\u{2500} {} \u{2500}\u{2500}",
+        day.format("%a %b %-d")
+    );
+    let pad = cols.saturating_sub(label.chars().count()) / 2;
+    Line::from(Span::styled(
+        format!("{}{}", " ".repeat(pad), label),
+        Style::default().add_modifier(Modifier::DIM),
+    ))
+}
+
+fn max_scroll(app: &App<impl Store>) -> usize {
+    pane_lines(app).len().saturating_sub(app.pane_rows.get())
+}
+
+// Move the pane `delta` rows towards older (+) or newer (-) messages, never past
+// the first loaded row; back at the bottom the view follows new messages again.
+fn scroll_by(app: &mut App<impl Store>, delta: isize) {
+    let max = max_scroll(app);
+    let next = (app.scroll_offset.min(max) as isize + delta).clamp(0, max as isize);
+    app.scroll_offset = next as usize;
+}
+
+// Towards newer messages; paging into the bottom of a /goto window carries on
+// into the newest ones.
+async fn scroll_down(app: &mut App<impl Store>, rows: usize) -> Result<()> {
+    scroll_by(app, -(rows as isize));
+    if app.scroll_offset == 0 {
+        reattach(app).await?;
+    }
+    Ok(())
+}
+
+// Explicit /roomcolor wins; otherwise derive from the name so every room has one.
+fn room_accent(room: &Room) -> Color {
+    room.color
+        .as_deref()
+        .and_then(theme::accent_by_name)
+        .unwrap_or_else(|| theme::room_color(&room.name))
+}
+
+// One sidebar row: marker, name (truncated with … to fit), and a capped unread badge.
+fn sidebar_line(
+    cur: char,
+    name: &str,
+    members: usize,
+    spark: &str,
+    unread: usize,
+    width: usize,
+) -> String {
+    let mut badge = match unread {
+        0 => String::new(),
+        1..=99 => format!(" ({} unread)", unread),
+        _ => " (99+ unread)".to_string(),
+    };
+    if !spark.is_empty() {
+        badge = format!(" {}{}", spark, badge);
+    }
+    if members > 0 {
+        badge = format!(" [{}]{}", members, badge);
+    }
+    let avail = width.saturating_sub(2 + badge.chars().count());
+    let name = if name.chars().count() > avail {
+        let mut s: String = name.chars().take(avail.saturating_sub(1)).collect();
+        s.push('…');
+        s
+    } else {
+        name.to_string()
+    };
+    format!("{} {}{}", cur, name, badge)
+}
+
+// /invite and /roominvite arguments: uses default to 1, a ttl must parse.
+fn invite_terms(uses: Option<u32>, ttl: Option<&str>) -> Option<(u32, Option<chrono::Duration>)> {
+    let uses = uses.unwrap_or(1);
+    if uses == 0 || uses > INVITE_MAX_USES {
+        return None;
+    }
+    match ttl {
+        Some(t) => Some((uses, Some(parse_ttl(t)?))),
+        None => Some((uses, None)),
+    }
+}
+
+// `code (3 uses, expires in 12h)`
+fn invite_summary(code: &str, uses: u32, ttl: Option<String>) -> String {
+    let noun = if uses == 1 { "use" } else { "uses" };
+    let expiry = match ttl {
+        Some(t) => format!(", expires in {}", t),
+        None => String::new(),
+    };
+    format!("{} ({} {}{})", code, uses, noun, expiry)
+}
+
 fn build_help_lines(is_admin: bool) -> Vec<Line<'static>> {
     let mut lines: Vec<Line<'static>> = vec![
         Line::from("Commands:"),
         Line::from("  /help               Show this help screen"),
         Line::from("  /quit               Quit"),
         Line::from("  /nick <name>        Change nickname [a-z0-9_-]{2,16}"),
-        Line::from("  /join <room>        Join or create room [a-z0-9_-]{1,24}"),
-        Line::from("  /leave [room]       Leave a room (current if omitted)"),
+        Line::from(
+            "  /join <room> [pw]   Join or create room [a-z0-9_-]{1,24}; pw locks a new one",
+        ),
+        Line::from("  /leave [room]       Leave a room (current if omitted; alias /part)"),
         Line::from("  /rooms              List rooms you’ve joined"),
-        Line::from("  /who                Show recent active users in current room"),
+        Line::from("  /list               Browse every room with member counts"),
+        Line::from("  /who                List members, last seen and member since"),
+        Line::from("  /msg <nick> <text>  Send a private message"),
         Line::from("  /me <action>        Emote as ‘* nick <action>’"),
+        Line::from("  /sig [text|off]     Show, set, or clear your message signature"),
+        Line::from("  /status [text|off]  Show, set, or clear your tagline (e.g. pronouns)"),
+        Line::from(
+            "  /away [reason]      Mark yourself away; /away again (or any message) returns",
+        ),
+        Line::from("  /serverinfo         Show instance-wide counts"),
+        Line::from("  /stats              Show message stats for this room"),
+        Line::from("  /mine               Page through everything you've posted"),
+        Line::from("  /more               Load older messages in this room"),
+        Line::from("  /whois <nick>       Show a user's key fingerprint and details"),
+        Line::from("  /whoami             Show your own handle, key, and room"),
+        Line::from("  /find <nick>        List the rooms a user is in"),
+        Line::from("  /filter <nick|off>  Show only one user's messages"),
+        Line::from("  /clear              Empty the message pane (this session only)"),
+        Line::from("  /time               Toggle clock / relative (5m, 2h) timestamps"),
+        Line::from("  /ignore [nick]      Hide a user's messages (/unignore <nick> to undo)"),
+        Line::from("  /edit <text>        Rewrite your last message in this room"),
+        Line::from("  /del <id>           Delete one of your own messages"),
+        Line::from("  /goto <id>          Jump to a message in this room"),
+        Line::from("  /reply <id> <text>  Reply, quoting a message in this room"),
+        Line::from("  /react <id> <emoji> Add (or remove) a reaction"),
+        Line::from("  /pins               List this room's pinned messages"),
+        Line::from("  /undo               Undo your last delete (within 60s)"),
+        Line::from("  /roomcolor [c|off]  Show or set this room's accent (creator)"),
+        Line::from("  /roomrate [n|off]   Show or set this room's messages/min per user (creator)"),
+        Line::from("  /quiet [a b|off]    Show or set read-only hours, e.g. 22:00 07:00"),
+        Line::from("  /topic [text|off]   Show or set this room's topic (creator)"),
+        Line::from("  /access [mode]      Show or set public / invite-only joining (creator)"),
+        Line::from("  /roominvite [n] [t] Mint a code for this room (moderator)"),
+        Line::from("  /pin /unpin <id>    Pin or unpin a message above the pane (moderator)"),
+        Line::from("  /mod /unmod <nick>  Grant or revoke moderator in this room (creator)"),
+        Line::from("  /kick <nick>        Remove someone from this room (moderator)"),
+        Line::from("  /mute <nick> <min>  Stop someone posting here for a while (moderator)"),
+        Line::from("  /unmute <nick>      Lift a mute early (moderator)"),
+        Line::from("  /purge <n>|user <h> Delete recent messages here (creator)"),
+        Line::from("  /feedback <text>    Report a problem or idea to the admins"),
         Line::from(""),
         Line::from("Aliases:"),
         Line::from("  /h /? (help), /q /exit (quit)"),
+        Line::from(""),
+        Line::from("Keys:"),
+        Line::from("  Tab                 Switch to the next room"),
+        Line::from("  Ctrl+K              Select #id message links (Enter jumps, Esc cancels)"),
+        Line::from("  PgUp/PgDn           Scroll history a page; PgUp at the top loads older"),
+        Line::from("  Ctrl+U/Ctrl+D       Scroll half a page"),
+        Line::from("  //text              Send a message starting with '/' (e.g. //shrug)"),
     ];
     if is_admin {
         lines.extend_from_slice(&[
             Line::from(""),
             Line::from("Admin:"),
             Line::from("  /room-del <name>    Soft-delete a room (any room)"),
+            Line::from(
+                "  /invite [uses] [ttl] Mint a random invite code (default 1 use, no expiry)",
+            ),
             Line::from("  /invite-new [code]  Create invite (random if omitted)"),
             Line::from("  /invite-del <code>  Delete invite"),
             Line::from("  /invites            List recent invites"),
+            Line::from("  /setmotd <text|off> Set message of the day (\\n for line breaks)"),
+            Line::from("  /shout <text>       Announce to every connected user"),
+            Line::from("  /feedback review    List open feedback; /feedback done <id>"),
+            Line::from("  /audit [save]       Full fingerprints of this room's members"),
             Line::from("Aliases: /roomdel /rdel, /invnew, /invdel, /invs"),
         ]);
     }
     lines
 }
 
-async fn handle_key(app: &mut App, k: KeyEvent) -> Result<()> {
+async fn handle_key(app: &mut App<impl Store>, k: KeyEvent) -> Result<()> {
     match (k.code, k.modifiers) {
-        // Close help on Esc
-        (KeyCode::Esc, _) if app.show_help => {
-            app.show_help = false;
+        // Close overlay on Esc
+        (KeyCode::Esc, _) if app.overlay.is_some() => {
+            app.overlay = None;
+        }
+        (KeyCode::PageUp, _) if app.overlay.is_some() => {
+            if let Some(ov) = app.overlay.as_mut() {
+                ov.scroll = ov.scroll.saturating_sub(OVERLAY_PAGE);
+            }
+        }
+        (KeyCode::PageDown, _) if app.overlay.is_some() => {
+            overlay_page_down(app).await?;
         }
         (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
             app.running = false;
         }
+        // a page keeps one row of overlap; Ctrl+U/Ctrl+D move half as far
+        (KeyCode::PageUp, _) => {
+            // already at the oldest loaded row: fetch the page before it first
+            if app.scroll_offset >= max_scroll(app) {
+                load_older(app).await?;
+            }
+            let page = app.pane_rows.get().saturating_sub(1).max(1);
+            scroll_by(app, page as isize);
+        }
+        (KeyCode::PageDown, _) => {
+            let page = app.pane_rows.get().saturating_sub(1).max(1);
+            scroll_down(app, page).await?;
+        }
+        (KeyCode::Char('u'), KeyModifiers::CONTROL) => {
+            scroll_by(app, (app.pane_rows.get() / 2).max(1) as isize);
+        }
+        (KeyCode::Char('d'), KeyModifiers::CONTROL) => {
+            scroll_down(app, (app.pane_rows.get() / 2).max(1)).await?;
+        }
+        (KeyCode::Enter, _) if app.pending_paste.is_some() => {
+            if let Some(text) = app.pending_paste.take() {
+                send_message(app, text.trim(), None).await?;
+            }
+        }
+        (KeyCode::Esc, _) if app.pending_paste.is_some() => {
+            app.pending_paste = None;
+            app.set_status(Severity::Info, "paste cancelled");
+        }
+        // anything else waits for the paste to be answered
+        _ if app.pending_paste.is_some() => {}
+        (KeyCode::Char('k'), KeyModifiers::CONTROL) => {
+            select_prev_link(app);
+        }
+        (KeyCode::Esc, _) if app.link_sel.is_some() => {
+            app.link_sel = None;
+            app.set_status(Severity::Info, "link selection cleared");
+        }
+        (KeyCode::Esc, _) if app.banner.is_some() && app.input.is_empty() => {
+            app.banner = None;
+        }
         (KeyCode::Esc, _) => {
             app.input.clear();
+            app.history_pos = None;
         }
+        (KeyCode::Up, _) => recall_sent(app, -1),
+        (KeyCode::Down, _) => recall_sent(app, 1),
         (KeyCode::Backspace, _) => {
-            app.input.pop();
+            app.history_pos = None;
+            let at = cursor_pos(app);
+            if let Some(c) = app.input[..at].chars().next_back() {
+                app.cursor = at - c.len_utf8();
+                app.input.remove(app.cursor);
+            }
+        }
+        (KeyCode::Delete, _) => {
+            app.history_pos = None;
+            let at = cursor_pos(app);
+            if at < app.input.len() {
+                app.input.remove(at);
+            }
+        }
+        (KeyCode::Left, _) => {
+            let at = cursor_pos(app);
+            app.cursor = at
+                - app.input[..at]
+                    .chars()
+                    .next_back()
+                    .map_or(0, char::len_utf8);
+        }
+        (KeyCode::Right, _) => {
+            let at = cursor_pos(app);
+            app.cursor = at + app.input[at..].chars().next().map_or(0, char::len_utf8);
+        }
+        // Home/End stay on the caret's line of a multi-line message
+        (KeyCode::Home, _) => {
+            let at = cursor_pos(app);
+            app.cursor = app.input[..at].rfind('\n').map_or(0, |i| i + 1);
+        }
+        (KeyCode::End, _) => {
+            let at = cursor_pos(app);
+            app.cursor = app.input[at..]
+                .find('\n')
+                .map_or(app.input.len(), |i| at + i);
+        }
+        // Shift+Enter needs a terminal that reports it; Alt+Enter works nearly everywhere
+        (KeyCode::Enter, m) if m.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) => {
+            app.link_sel = None;
+            insert_at_cursor(app, "\n");
         }
         (KeyCode::Enter, _) => {
-            let s = app.input.trim();
-            if s.is_empty() {
-                app.status = "empty".into();
-                app.input.clear();
+            if let Some(sel) = app.link_sel.take() {
+                goto_message(app, sel.target).await?;
                 return Ok(());
             }
-            if let Some(cmd) = parse_command(s) {
-                handle_command(app, cmd).await?;
+            let s = app.input.trim().to_string();
+            if s.is_empty() {
+                app.set_status(Severity::Warn, "empty");
                 app.input.clear();
                 return Ok(());
             }
-            if s.len() > app.opts.msg_max_len {
-                return Err(anyhow!("message too long"));
-            }
-            // normalize body (nfkc + strip controls)
-            let s = normalize_message(s);
-            // client-side rate bucket
-            if !app.bucket.try_consume(1.0) {
-                app.status = "rate limited (client)".into();
+            remember_sent(app, &s);
+            if let Some(cmd) = parse_command(&s) {
+                handle_command(app, cmd).await?;
                 app.input.clear();
                 return Ok(());
             }
-            // send
-            let res = data::insert_message(&app.pool, app.room.id, app.user.id, &s).await;
-            let msg = match res {
-                Ok(m) => m,
-                Err(e) => {
-                    let msg = e.to_string();
-                    if msg.contains("rate_limited") {
-                        app.status = "rate limited (server)".into();
-                        return Ok(());
-                    } else {
-                        return Err(e);
-                    }
-                }
-            };
-            let mv = MessageView {
-                id: msg.id,
-                room_id: msg.room_id,
-                user_id: msg.user_id,
-                user_handle: app.user.handle.clone(),
-                body: msg.body,
-                created_at: msg.created_at,
-            };
-            app.seen_ids.insert(mv.id);
-            app.messages.push(mv);
-            app.status = "sent".into();
-            app.input.clear();
+            let s = message_text(&s).to_string();
+            send_message(app, &s, None).await?;
         }
         (KeyCode::Char(ch), KeyModifiers::NONE) | (KeyCode::Char(ch), KeyModifiers::SHIFT) => {
-            app.input.push(ch);
-        }
-        (KeyCode::Tab, _) => {
-            if !app.rooms.is_empty() {
-                if let Some(idx) = app.rooms.iter().position(|r| r.id == app.room.id) {
-                    let next = (idx + 1) % app.rooms.len();
-                    let target = app.rooms[next].id;
-                    if let Some(re) = app.rooms.iter().find(|r| r.id == target) {
-                        let room =
-                            data::ensure_room_exists(&app.pool, &re.name, app.user.id).await?;
-                        data::join_room(&app.pool, room.id, app.user.id).await?;
-                        app.room = room;
-                        app.messages = data::recent_messages_view(
-                            &app.pool,
-                            app.room.id,
-                            app.opts.history_load as i64,
-                        )
-                        .await?;
-                        app.seen_ids.clear();
-                        for m in &app.messages {
-                            app.seen_ids.insert(m.id);
-                        }
-                        if let Some(rm) = app.rooms.iter_mut().find(|r| r.id == target) {
-                            rm.unread = 0;
-                        }
-                        app.status = format!("joined {}", app.room.name);
-                    }
+            app.link_sel = None;
+            insert_at_cursor(app, ch.encode_utf8(&mut [0; 4]));
+        }
+        (KeyCode::Tab, _) if !app.rooms.is_empty() => {
+            if let Some(idx) = app.rooms.iter().position(|r| r.id == app.room.id) {
+                let next = (idx + 1) % app.rooms.len();
+                let target = app.rooms[next].id;
+                if let Some(re) = app.rooms.iter().find(|r| r.id == target) {
+                    let (room, _) = app.store.ensure_room_exists(&re.name, app.user.id).await?;
+                    mark_read(app).await?;
+                    open_room(app, room).await?;
+                    catch_up(app).await?;
+                    app.set_status(Severity::Success, format!("joined {}", app.room.name));
                 }
             }
         }
@@ -389,138 +1769,1134 @@ async fn handle_key(app: &mut App, k: KeyEvent) -> Result<()> {
     Ok(())
 }
 
-fn sanitize(s: &str) -> String {
-    s.chars()
-        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
-        .collect()
+// Where the caret is, in bytes. Anything that replaces `input` wholesale (clear,
+// send) leaves `cursor` stale, so it's clamped here: past the end means the end.
+fn cursor_pos(app: &App<impl Store>) -> usize {
+    let mut at = app.cursor.min(app.input.len());
+    while !app.input.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
 }
 
-async fn handle_command(app: &mut App, cmd: Command) -> Result<()> {
-    match cmd {
-        Command::Help => {
-            app.show_help = true;
-            app.status = "help".into();
+fn insert_at_cursor(app: &mut App<impl Store>, s: &str) {
+    app.history_pos = None;
+    let at = cursor_pos(app);
+    app.input.insert_str(at, s);
+    app.cursor = at + s.len();
+}
+
+// Repeating the previous line doesn't add another entry, like most shells.
+fn remember_sent(app: &mut App<impl Store>, line: &str) {
+    app.history_pos = None;
+    if app.sent_history.last().map(String::as_str) == Some(line) {
+        return;
+    }
+    if app.sent_history.len() >= SENT_HISTORY_MAX {
+        app.sent_history.remove(0);
+    }
+    app.sent_history.push(line.to_string());
+}
+
+// Step through sent lines: -1 is older (Up), +1 newer (Down). Down past the
+// newest entry leaves history with an empty input.
+fn recall_sent(app: &mut App<impl Store>, step: isize) {
+    let len = app.sent_history.len();
+    if len == 0 {
+        return;
+    }
+    let next = match (app.history_pos, step < 0) {
+        (None, true) => Some(len - 1),
+        (None, false) => return,
+        (Some(i), true) => Some(i.saturating_sub(1)),
+        (Some(i), false) => Some(i + 1).filter(|&n| n < len),
+    };
+    app.input = next.map_or_else(String::new, |i| app.sent_history[i].clone());
+    app.cursor = app.input.len();
+    app.history_pos = next;
+}
+
+// Caret as (line, display column) within the input text.
+fn cursor_cell(app: &App<impl Store>) -> (u16, u16) {
+    let before = &app.input[..cursor_pos(app)];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let row = before.matches('\n').count();
+    let col = Span::raw(&before[line_start..]).width();
+    (row as u16, col as u16)
+}
+
+// Small pastes join the input line (newlines become spaces; Shift/Alt+Enter
+// is the way to type a break); big ones are held as a unit until the user confirms or cancels.
+fn handle_paste(app: &mut App<impl Store>, text: &str) {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let lines = text.lines().count();
+    if lines <= PASTE_MAX_LINES && text.chars().count() <= PASTE_MAX_CHARS {
+        app.link_sel = None;
+        insert_at_cursor(app, &text.replace('\n', " "));
+        return;
+    }
+    app.pending_paste = Some(format!("{}{}", app.input, text));
+    app.dirty = true;
+}
+
+fn paste_prompt(text: &str) -> String {
+    format!(
+        "paste is {} lines / {} chars — Enter to send, Esc to cancel",
+        text.lines().count(),
+        text.chars().count()
+    )
+}
+
+// Normalize, sign, and send one message body from the input (or a confirmed paste).
+// `reply`: the /reply parent, already checked to be live and in this room.
+async fn send_message(
+    app: &mut App<impl Store>,
+    s: &str,
+    reply: Option<MessageView>,
+) -> Result<()> {
+    // normalize body (nfkc + strip controls)
+    let s = normalize_message(s);
+    // append signature (if any) within the length budget
+    let s = match with_signature(&s, app.signature.as_deref(), app.opts.msg_max_len) {
+        Some(b) => b,
+        None => {
+            app.set_status(Severity::Warn, "message too long with signature");
+            return Ok(());
         }
-        Command::Quit => {
-            app.running = false;
+    };
+    if let Err(e) = check_body_size(&s, app.opts.msg_max_len, app.opts.msg_max_bytes) {
+        app.set_status(Severity::Warn, e);
+        return Ok(());
+    }
+    // known offline: don't wait on a doomed insert
+    if !app.online {
+        queue_offline(app, s, reply);
+        return Ok(());
+    }
+    // client-side rate bucket
+    if !app.bucket.try_consume(1.0) {
+        client_rate_limited(app);
+        app.input.clear();
+        return Ok(());
+    }
+    // send
+    let ch = &app.opts.channels.events;
+    let res = match &reply {
+        Some(p) => {
+            app.store
+                .insert_reply(ch, app.room.id, app.user.id, &s, p.id)
+                .await
         }
-        Command::Me(action) => {
-            if action.trim().is_empty() {
-                app.status = "usage: /me <action>".into();
-                return Ok(());
-            }
-            let body = format!("* {} {}", app.user.handle, normalize_message(action.trim()));
-            let msg = data::insert_message(&app.pool, app.room.id, app.user.id, &body).await?;
-            let mv = MessageView {
-                id: msg.id,
-                room_id: msg.room_id,
-                user_id: msg.user_id,
-                user_handle: app.user.handle.clone(),
-                body: msg.body,
-                created_at: msg.created_at,
-            };
-            app.seen_ids.insert(mv.id);
-            app.messages.push(mv);
-            app.status = "me".into();
+        None => {
+            app.store
+                .insert_message(ch, app.room.id, app.user.id, &s)
+                .await
         }
-        Command::Nick(new) => {
-            let new = new.trim();
-            if !valid_nick(new) {
-                app.status = "invalid nick [a-z0-9_-]{2,16}".into();
-                return Ok(());
+    };
+    let out = match res {
+        Ok(out) => out,
+        Err(e) if data::is_connection_error(&e) => {
+            app.online = false;
+            queue_offline(app, s, reply);
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+    app.bucket.sync_remaining(out.remaining as f64);
+    let Some(msg) = out.message else {
+        app.set_status(Severity::Error, rejected_text(&out));
+        return Ok(());
+    };
+    reattach(app).await?;
+    push_own_message(app, msg, reply.as_ref());
+    app.set_status(Severity::Success, "sent");
+    app.input.clear();
+    clear_away_on_send(app).await
+}
+
+// Talking means you're back; says so in the status line when it changes anything.
+async fn clear_away_on_send(app: &mut App<impl Store>) -> Result<()> {
+    if app.user.away_reason.take().is_some() {
+        app.store.set_away(app.user.id, None).await?;
+        app.set_status(Severity::Success, "sent (no longer away)");
+    }
+    Ok(())
+}
+
+// A message as display lines: the template on the first line, body line breaks
+// starting new ones, and ``` blocks as their own verbatim rows. Text rows wrap at
+// `cols` (0: don't); code rows never do and are cut off at the pane edge instead.
+fn message_lines(
+    m: &MessageView,
+    opts: &UiOpts,
+    selected: Option<usize>,
+    cols: usize,
+) -> Vec<Line<'static>> {
+    let level = opts.color_level;
+    let mut lines = Vec::new();
+    let push = |lines: &mut Vec<Line<'static>>, spans: &mut Vec<Span<'static>>| {
+        lines.extend(wrap_line(Line::from(std::mem::take(spans)), cols));
+    };
+    lines.extend(quote_line(m, cols));
+    let mut spans = Vec::new();
+    for seg in opts.msg_format.segments() {
+        match seg {
+            Segment::Lit(s) => spans.push(Span::raw(s.clone())),
+            Segment::Time if opts.relative_time => {
+                spans.push(Span::raw(age_short(chrono::Utc::now() - m.created_at)))
             }
-            match data::change_handle(&app.pool, app.user.id, new).await {
-                Ok(updated) => {
-                    app.user = updated;
-                    app.status = "nick changed".into();
+            Segment::Time => spans.push(Span::raw(m.created_at.format("%H:%M:%S").to_string())),
+            Segment::Date => spans.push(Span::raw(m.created_at.format("%Y-%m-%d").to_string())),
+            Segment::Handle => {
+                // the deleted-user placeholder isn't anyone, so it stays plain
+                let style = match m.user_id {
+                    Some(_) => theme::fg(level, theme::handle_color(&m.user_handle)),
+                    None => Style::default(),
+                };
+                spans.push(Span::styled(m.user_handle.clone(), style));
+                if let Some(tag) = m.tagline.as_ref().filter(|_| opts.show_tagline) {
+                    spans.push(Span::styled(
+                        format!(" ({})", tag),
+                        Style::default().add_modifier(Modifier::DIM),
+                    ));
                 }
-                Err(e) => {
-                    let is_unique = e
-                        .downcast_ref::<sqlx::Error>()
-                        .and_then(|err| err.as_database_error())
-                        .and_then(|d| d.code())
-                        .map(|c| c == "23505")
-                        .unwrap_or(false);
-                    if is_unique {
-                        app.status = "nick taken".into();
-                    } else {
-                        app.status = format!("nick error: {}", e);
+            }
+            Segment::Body => {
+                let body = sanitize(&m.body);
+                for part in split_code_blocks(&body) {
+                    if part.code {
+                        // the header row stays even when the body opens with a block
+                        if lines.is_empty() || !spans.is_empty() {
+                            push(&mut lines, &mut spans);
+                        }
+                        lines.extend(code_lines(part.text, level));
+                        continue;
+                    }
+                    let mut offset = part.start;
+                    for (i, row) in part.text.split('\n').enumerate() {
+                        if i > 0 {
+                            push(&mut lines, &mut spans);
+                        }
+                        spans.extend(body_spans(row, offset, selected, level));
+                        offset += row.len() + 1;
                     }
                 }
+                if m.edited {
+                    spans.push(Span::styled(
+                        " (edited)",
+                        Style::default().add_modifier(Modifier::DIM),
+                    ));
+                }
             }
         }
-        Command::Join(name) => {
-            let name = name.trim();
-            if !valid_room_name(name) {
-                app.status = "invalid room [a-z0-9_-]{1,24}".into();
-                return Ok(());
-            }
-            let room = match data::ensure_room_exists(&app.pool, name, app.user.id).await {
-                Ok(r) => r,
-                Err(e) => {
-                    if e.to_string().contains("room_deleted") {
-                        app.status = "room is deleted".into();
-                        return Ok(());
-                    }
-                    return Err(e);
-                }
-            };
-            data::join_room(&app.pool, room.id, app.user.id).await?;
-            app.room = room;
-            app.messages =
-                data::recent_messages_view(&app.pool, app.room.id, app.opts.history_load as i64)
-                    .await?;
-            app.seen_ids.clear();
-            for m in &app.messages {
-                app.seen_ids.insert(m.id);
+    }
+    if lines.is_empty() || !spans.is_empty() {
+        push(&mut lines, &mut spans);
+    }
+    lines
+}
+
+// Longest quote preview, in chars, however wide the pane.
+const QUOTE_PREVIEW_MAX: usize = 60;
+
+// A reply's first row: a dim, single-line preview of the message it quotes.
+fn quote_line(m: &MessageView, cols: usize) -> Option<Line<'static>> {
+    m.reply_to?;
+    let text = match (&m.quote_handle, &m.quote_body) {
+        (Some(h), Some(b)) => {
+            let flat: Vec<&str> = b.split_whitespace().collect();
+            format!("{}: {}", h, sanitize(&flat.join(" ")))
+        }
+        _ => "(quoting deleted message)".to_string(),
+    };
+    let max = match cols {
+        0 => QUOTE_PREVIEW_MAX,
+        c => c.min(QUOTE_PREVIEW_MAX),
+    };
+    let mut row = format!("\u{256d} {}", text);
+    if row.chars().count() > max {
+        row = row.chars().take(max.saturating_sub(1)).collect();
+        row.push('…');
+    }
+    Some(Line::from(Span::styled(
+        row,
+        Style::default().add_modifier(Modifier::DIM),
+    )))
+}
+
+// Continuation rows of a wrapped line start this far in, so the `[ts] handle:`
+// prefix stays alone at the left edge.
+const WRAP_INDENT: usize = 2;
+
+// Split `line` into rows at most `cols` wide, breaking after the last space that
+// fits; a word longer than a row (pasted URLs) is cut mid-word. The breaking space
+// is dropped, styles carry over per character. `cols` 0 leaves the line alone.
+fn wrap_line(line: Line<'static>, cols: usize) -> Vec<Line<'static>> {
+    use unicode_width::UnicodeWidthChar;
+    if cols == 0 || line.width() <= cols {
+        return vec![line];
+    }
+    let cells: Vec<(char, Style)> = line
+        .spans
+        .iter()
+        .flat_map(|s| s.content.chars().map(move |c| (c, s.style)))
+        .collect();
+    let indent = if cols > WRAP_INDENT * 2 {
+        WRAP_INDENT
+    } else {
+        0
+    };
+    let mut rows = Vec::new();
+    let mut start = 0;
+    while start < cells.len() {
+        let avail = if rows.is_empty() { cols } else { cols - indent };
+        let (mut end, mut used, mut space) = (start, 0, None);
+        while end < cells.len() {
+            let w = cells[end].0.width().unwrap_or(0);
+            if used + w > avail && end > start {
+                break;
             }
-            if let Some(rm) = app.rooms.iter_mut().find(|r| r.id == app.room.id) {
-                rm.unread = 0;
+            if cells[end].0 == ' ' && end > start {
+                space = Some(end);
             }
-            if !app.rooms.iter().any(|r| r.id == app.room.id) {
-                app.rooms.push(RoomEntry {
-                    id: app.room.id,
-                    name: app.room.name.clone(),
-                    unread: 0,
-                });
+            used += w;
+            end += 1;
+        }
+        let (row_end, next) = match space {
+            _ if end == cells.len() => (end, end),
+            _ if cells[end].0 == ' ' => (end, end + 1),
+            Some(sp) => (sp, sp + 1),
+            None => (end, end),
+        };
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        if !rows.is_empty() && indent > 0 {
+            spans.push(Span::raw(" ".repeat(indent)));
+        }
+        for &(c, style) in &cells[start..row_end] {
+            match spans.last_mut() {
+                Some(s) if s.style == style => s.content.to_mut().push(c),
+                _ => spans.push(Span::styled(c.to_string(), style)),
             }
-            app.status = "joined".into();
         }
-        Command::RoomDel(name) => {
-            let name = name.trim();
-            if !valid_room_name(name) {
-                app.status = "usage: /room-del <name> (a-z0-9_-){1,24}".into();
+        rows.push(Line::from(spans).style(line.style));
+        start = next;
+    }
+    rows
+}
+
+// Rows of a fenced block: verbatim (tabs expanded so columns line up), indented,
+// on a dim background so the block reads apart from chat text.
+fn code_lines(code: &str, level: ColorLevel) -> Vec<Line<'static>> {
+    let style = theme::bg(level, Color::Rgb(48, 48, 48)).add_modifier(Modifier::DIM);
+    code.split('\n')
+        .map(|row| {
+            Line::from(vec![
+                Span::raw("  "),
+                Span::styled(format!(" {} ", row.replace('\t', "    ")), style),
+            ])
+        })
+        .collect()
+}
+
+// `base` is where `body` starts in the whole message, so `selected` (a byte offset
+// into the sanitized body) can be matched per row.
+fn body_spans(
+    body: &str,
+    base: usize,
+    selected: Option<usize>,
+    level: ColorLevel,
+) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for (range, _) in message_refs(body) {
+        if range.start > last {
+            spans.push(Span::raw(body[last..range.start].to_string()));
+        }
+        let mut style = theme::fg(level, Color::Cyan).add_modifier(Modifier::UNDERLINED);
+        if selected == Some(base + range.start) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        spans.push(Span::styled(body[range.clone()].to_string(), style));
+        last = range.end;
+    }
+    if last < body.len() {
+        spans.push(Span::raw(body[last..].to_string()));
+    }
+    spans
+}
+
+// `#<id>` references outside code blocks, found row by row as `message_lines`
+// draws them: (byte offset in the body, target id).
+fn text_refs(body: &str) -> Vec<(usize, i64)> {
+    let mut out = Vec::new();
+    for part in split_code_blocks(body).into_iter().filter(|p| !p.code) {
+        let mut offset = part.start;
+        for row in part.text.split('\n') {
+            out.extend(
+                message_refs(row)
+                    .into_iter()
+                    .map(|(range, id)| (offset + range.start, id)),
+            );
+            offset += row.len() + 1;
+        }
+    }
+    out
+}
+
+// Cycle the link selection from the newest `#<id>` reference towards older ones.
+fn select_prev_link(app: &mut App<impl Store>) {
+    let links: Vec<LinkSel> = app
+        .visible_messages()
+        .flat_map(|m| {
+            text_refs(&sanitize(&m.body))
+                .into_iter()
+                .map(move |(start, target)| LinkSel {
+                    msg_id: m.id,
+                    start,
+                    target,
+                })
+        })
+        .collect();
+    if links.is_empty() {
+        app.link_sel = None;
+        app.set_status(Severity::Warn, "no message links");
+        return;
+    }
+    let idx = match app
+        .link_sel
+        .and_then(|cur| links.iter().position(|l| *l == cur))
+    {
+        Some(i) if i > 0 => i - 1,
+        _ => links.len() - 1,
+    };
+    app.link_sel = Some(links[idx]);
+    app.set_status(
+        Severity::Info,
+        format!("link #{} (Enter to jump, Esc to cancel)", links[idx].target),
+    );
+}
+
+const MOTD_KEY: &str = "motd";
+
+// One query for the whole sidebar, like refresh_member_counts.
+async fn refresh_activity(app: &mut App<impl Store>) -> Result<()> {
+    let ids: Vec<i64> = app.rooms.iter().map(|r| r.id).collect();
+    for (room_id, counts) in app
+        .store
+        .room_activity_buckets(&ids, SPARK_BUCKETS, SPARK_WINDOW)
+        .await?
+    {
+        if let Some(re) = app.rooms.iter_mut().find(|r| r.id == room_id) {
+            re.activity = counts;
+        }
+    }
+    app.dirty = true;
+    Ok(())
+}
+
+// Scroll the overlay a page; past the end of what's loaded, fetch the next batch.
+async fn overlay_page_down(app: &mut App<impl Store>) -> Result<()> {
+    let Some(ov) = app.overlay.as_mut() else {
+        return Ok(());
+    };
+    let len = ov.lines.len() as u16;
+    if ov.scroll + OVERLAY_PAGE < len {
+        ov.scroll += OVERLAY_PAGE;
+        return Ok(());
+    }
+    match ov.more {
+        Some(OverlayMore::Mine { offset }) => {
+            let page = app
+                .store
+                .user_messages(app.user.id, offset, MINE_PAGE)
+                .await?;
+            let Some(ov) = app.overlay.as_mut() else {
                 return Ok(());
-            }
-            let ok = if app.opts.is_admin {
-                data::soft_delete_room_any(&app.pool, name).await?
-            } else {
-                data::soft_delete_room_by_creator(&app.pool, name, app.user.id).await?
             };
-            if ok {
-                app.status = format!("room '{}' deleted", name);
-                // refresh rooms list (joined rooms)
-                let list = data::list_joined_rooms(&app.pool, app.user.id).await?;
-                app.rooms = list
-                    .into_iter()
-                    .map(|r| RoomEntry {
-                        id: r.id,
-                        name: r.name,
-                        unread: 0,
-                    })
-                    .collect();
-            } else if app.opts.is_admin {
-                app.status = "room not found or already deleted".into();
+            let got = page.len() as i64;
+            ov.lines.extend(page.iter().map(mine_line));
+            if got < MINE_PAGE {
+                ov.lines.push(Line::from("  (end)"));
+                ov.more = None;
             } else {
-                app.status = "not room creator or already deleted".into();
+                ov.more = Some(OverlayMore::Mine {
+                    offset: offset + got,
+                });
             }
+            ov.scroll = (ov.scroll + OVERLAY_PAGE).min((ov.lines.len() as u16).saturating_sub(1));
         }
-        Command::Leave(name_opt) => {
-            // Determine room to leave
-            let target_room_name_owned = name_opt.unwrap_or_else(|| app.room.name.clone());
+        None => {}
+    }
+    Ok(())
+}
+
+fn mine_line(m: &data::UserMessage) -> Line<'static> {
+    Line::from(format!(
+        "[{}] {}: {}  #{}",
+        m.created_at.format("%Y-%m-%d %H:%M"),
+        m.room_name,
+        sanitize(&m.body),
+        m.id
+    ))
+}
+
+// Show the message of the day as an overlay; it's never stored as a message, so it
+// stays out of history and unread counts.
+async fn show_motd(app: &mut App<impl Store>) -> Result<()> {
+    let Some(motd) = app.store.get_setting(MOTD_KEY).await? else {
+        return Ok(());
+    };
+    let style = theme::fg(app.opts.color_level, Color::Yellow);
+    let lines = motd
+        .lines()
+        .map(|l| Line::from(Span::styled(sanitize(l), style)))
+        .collect();
+    app.overlay = Some(Overlay::new("message of the day", lines));
+    app.dirty = true;
+    Ok(())
+}
+
+// /mute for `minutes`, or /unmute with 0; moderators and admins only.
+async fn set_mute(app: &mut App<impl Store>, nick: &str, minutes: u32) -> Result<()> {
+    let moderator = if app.opts.is_admin {
+        None
+    } else {
+        Some(app.user.id)
+    };
+    let allowed = match moderator {
+        None => true,
+        Some(u) => app.store.is_moderator(app.room.id, u).await?,
+    };
+    if !allowed {
+        app.set_status(
+            Severity::Warn,
+            "only the room creator or a moderator can mute",
+        );
+        return Ok(());
+    }
+    let Some(u) = app.store.get_user_by_handle(nick).await? else {
+        app.set_status(Severity::Warn, "no such user");
+        return Ok(());
+    };
+    if u.id == app.room.created_by {
+        app.set_status(Severity::Warn, "the room creator can't be muted");
+        return Ok(());
+    }
+    let changed = app
+        .store
+        .mute_member(app.room.id, u.id, app.user.id, moderator, minutes)
+        .await?;
+    match (minutes, changed) {
+        (0, true) => app.set_status(
+            Severity::Success,
+            format!("{} can talk in {} again", u.handle, app.room.name),
+        ),
+        (0, false) => app.set_status(Severity::Info, format!("{} isn't muted", u.handle)),
+        _ => app.set_status(
+            Severity::Success,
+            format!("muted {} in {} for {}m", u.handle, app.room.name, minutes),
+        ),
+    }
+    Ok(())
+}
+
+// /mod and /unmod: only the room creator (or an admin) hands out moderation.
+async fn set_moderator(app: &mut App<impl Store>, nick: &str, grant: bool) -> Result<()> {
+    let (cmd, verb) = if grant {
+        ("mod", "grant")
+    } else {
+        ("unmod", "revoke")
+    };
+    if nick.is_empty() {
+        app.set_status(Severity::Warn, format!("usage: /{} <nick>", cmd));
+        return Ok(());
+    }
+    let creator = if app.opts.is_admin {
+        None
+    } else {
+        Some(app.user.id)
+    };
+    if creator.is_some_and(|c| c != app.room.created_by) {
+        app.set_status(
+            Severity::Warn,
+            format!("only the room creator can {} moderator", verb),
+        );
+        return Ok(());
+    }
+    let Some(u) = app.store.get_user_by_handle(nick).await? else {
+        app.set_status(Severity::Warn, "no such user");
+        return Ok(());
+    };
+    if u.id == app.room.created_by {
+        app.set_status(Severity::Info, format!("{} owns this room", u.handle));
+        return Ok(());
+    }
+    let room = app.room.id;
+    let (changed, done, unchanged) = if grant {
+        (
+            app.store
+                .grant_moderator(room, u.id, app.user.id, creator)
+                .await?,
+            "is now a moderator of",
+            "is already a moderator",
+        )
+    } else {
+        (
+            app.store.revoke_moderator(room, u.id, creator).await?,
+            "is no longer a moderator of",
+            "isn't a moderator",
+        )
+    };
+    match changed {
+        true => app.set_status(
+            Severity::Success,
+            format!("{} {} {}", u.handle, done, app.room.name),
+        ),
+        false => app.set_status(Severity::Info, format!("{} {}", u.handle, unchanged)),
+    }
+    Ok(())
+}
+
+// Message `id` for a command acting on it, from the pane if loaded, else from the
+// DB. None, with the reason in the status line, when it's missing or in another room.
+async fn message_in_room(app: &mut App<impl Store>, id: i64) -> Result<Option<MessageView>> {
+    let loaded = app.messages.iter().find(|m| m.id == id).cloned();
+    let found = match loaded {
+        Some(m) => Some(m),
+        None => app.store.message_view_by_id(id).await?,
+    };
+    let Some(m) = found else {
+        app.set_status(Severity::Warn, format!("message #{} not found", id));
+        return Ok(None);
+    };
+    if m.room_id != app.room.id {
+        app.set_status(Severity::Warn, "message not in this room");
+        return Ok(None);
+    }
+    Ok(Some(m))
+}
+
+// /goto: load a window around message `id` if needed, scroll it to the middle of
+// the pane and highlight it for a moment.
+async fn goto_message(app: &mut App<impl Store>, id: i64) -> Result<()> {
+    if !app.seen_ids.contains(&id) {
+        if message_in_room(app, id).await?.is_none() {
+            return Ok(());
+        }
+        let radius = (app.opts.history_load / 2).max(1) as i64;
+        let window = app.store.messages_around(app.room.id, id, radius).await?;
+        // swapped in whole: merging would leave an unmarked hole between the
+        // window and the newest page. A full radius after the target means
+        // newer messages may follow.
+        let at = window.iter().position(|v| v.id == id).unwrap_or(0);
+        app.detached = (window.len() - at - 1) as i64 >= radius;
+        app.seen_ids = window.iter().map(|v| v.id).collect();
+        app.messages = window;
+        app.reactions.clear();
+        let ids: Vec<i64> = app.messages.iter().map(|v| v.id).collect();
+        load_reactions(app, &ids).await?;
+    }
+    let (lines, rows) = pane_layout(app, Some(id));
+    let Some(rows) = rows else {
+        app.set_status(
+            Severity::Warn,
+            format!("message #{} is hidden by /filter or /ignore", id),
+        );
+        return Ok(());
+    };
+    let view = app.pane_rows.get();
+    let top = (rows.start + rows.len() / 2).saturating_sub(view / 2);
+    let max = lines.len().saturating_sub(view);
+    app.scroll_offset = lines.len().saturating_sub(view + top).min(max);
+    app.goto = Some((id, Instant::now() + GOTO_HIGHLIGHT));
+    app.link_sel = None;
+    app.dirty = true;
+    app.set_status(Severity::Info, format!("jumped to #{}", id));
+    Ok(())
+}
+
+// Prepend the page of history just older than what's loaded.
+async fn load_older(app: &mut App<impl Store>) -> Result<()> {
+    let Some(oldest) = app.messages.first().map(|m| m.cursor()) else {
+        app.set_status(Severity::Info, "no more history");
+        return Ok(());
+    };
+    let older = app
+        .store
+        .messages_before(app.room.id, oldest, app.opts.history_load as i64)
+        .await?;
+    if older.is_empty() {
+        app.set_status(Severity::Info, "no more history");
+        return Ok(());
+    }
+    for m in &older {
+        app.seen_ids.insert(m.id);
+    }
+    let n = older.len();
+    let ids: Vec<i64> = older.iter().map(|m| m.id).collect();
+    app.messages.splice(0..0, older);
+    load_reactions(app, &ids).await?;
+    app.set_status(Severity::Info, format!("loaded {} older messages", n));
+    Ok(())
+}
+
+// One column of a TSV export: unlike `sanitize`, tabs and newlines go too, since
+// they'd split the column or the row.
+fn tsv_field(s: &str) -> String {
+    s.chars().filter(|c| !c.is_control()).collect()
+}
+
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect()
+}
+
+async fn handle_command(app: &mut App<impl Store>, cmd: Command) -> Result<()> {
+    match cmd {
+        Command::Help => {
+            app.overlay = Some(Overlay::new("help", build_help_lines(app.opts.is_admin)));
+            app.set_status(Severity::Info, "help");
+        }
+        Command::Quit => app.running = false,
+        Command::Me(action) => {
+            if action.trim().is_empty() {
+                app.set_status(Severity::Warn, "usage: /me <action>");
+                return Ok(());
+            }
+            let body = format!("* {} {}", app.user.handle, normalize_message(action.trim()));
+            if let Err(e) = check_body_size(&body, app.opts.msg_max_len, app.opts.msg_max_bytes) {
+                app.set_status(Severity::Warn, e);
+                return Ok(());
+            }
+            let out = app
+                .store
+                .insert_message(&app.opts.channels.events, app.room.id, app.user.id, &body)
+                .await?;
+            app.bucket.sync_remaining(out.remaining as f64);
+            let Some(msg) = out.message else {
+                app.set_status(Severity::Error, rejected_text(&out));
+                return Ok(());
+            };
+            reattach(app).await?;
+            push_own_message(app, msg, None);
+            app.set_status(Severity::Info, "me");
+            clear_away_on_send(app).await?;
+        }
+        Command::Nick(new) => {
+            let new = new.trim();
+            if !valid_nick(new) {
+                app.set_status(Severity::Warn, "invalid nick [a-z0-9_-]{2,16}");
+                return Ok(());
+            }
+            match app.store.change_handle(app.user.id, new).await {
+                Ok(updated) => {
+                    app.user = updated;
+                    app.set_status(Severity::Success, "nick changed");
+                }
+                Err(e) => {
+                    let is_unique = e
+                        .downcast_ref::<sqlx::Error>()
+                        .and_then(|err| err.as_database_error())
+                        .and_then(|d| d.code())
+                        .map(|c| c == "23505")
+                        .unwrap_or(false);
+                    if is_unique {
+                        app.set_status(Severity::Warn, "nick taken");
+                    } else {
+                        app.set_status(Severity::Error, format!("nick error: {}", e));
+                    }
+                }
+            }
+        }
+        Command::Join(name, password) => {
+            let name = name.trim();
+            if !valid_room_name(name) {
+                app.set_status(Severity::Warn, "invalid room [a-z0-9_-]{1,24}");
+                return Ok(());
+            }
+            if password
+                .as_deref()
+                .is_some_and(|p| p.len() > ROOM_PASSWORD_MAX || p.contains(char::is_whitespace))
+            {
+                app.set_status(
+                    Severity::Warn,
+                    format!(
+                        "usage: /join <room> [password] (one word, max {})",
+                        ROOM_PASSWORD_MAX
+                    ),
+                );
+                return Ok(());
+            }
+            let joined = app
+                .store
+                .join_protected_room(
+                    &app.opts.channels.events,
+                    name,
+                    app.user.id,
+                    password.as_deref(),
+                )
+                .await;
+            let (room, created) = match joined {
+                Ok(JoinOutcome::Joined(room, created)) => (room, created),
+                // same answer whether or not the room exists
+                Ok(JoinOutcome::WrongPassword) => {
+                    app.set_status(Severity::Warn, "wrong password");
+                    return Ok(());
+                }
+                Err(e) => {
+                    if e.to_string().contains("room_deleted") {
+                        app.set_status(Severity::Warn, "room is deleted");
+                        return Ok(());
+                    }
+                    return Err(e);
+                }
+            };
+            mark_read(app).await?;
+            open_room(app, room).await?;
+            catch_up(app).await?;
+            if created {
+                app.set_status(
+                    Severity::Success,
+                    format!("created room '{}' (it's empty; say hi!)", app.room.name),
+                );
+            } else {
+                app.set_status(Severity::Success, format!("joined '{}'", app.room.name));
+            }
+            if app.room.name == app.opts.default_room {
+                show_motd(app).await?;
+            }
+        }
+        Command::RoomDel(name) => {
+            let name = name.trim();
+            if !valid_room_name(name) {
+                app.set_status(Severity::Warn, "usage: /room-del <name> (a-z0-9_-){1,24}");
+                return Ok(());
+            }
+            let deleted = if app.opts.is_admin {
+                app.store.soft_delete_room_any(name).await?
+            } else {
+                app.store
+                    .soft_delete_room_by_moderator(name, app.user.id)
+                    .await?
+            };
+            if let Some(id) = deleted {
+                app.last_deletion = Some(LastDeletion {
+                    kind: Deleted::Room {
+                        id,
+                        name: name.to_string(),
+                    },
+                    at: Instant::now(),
+                });
+                app.set_status(
+                    Severity::Success,
+                    format!("room '{}' deleted (/undo within 60s)", name),
+                );
+                // only that entry goes; the rest keep their unread counts
+                app.rooms.retain(|r| r.id != id);
+            } else if app.opts.is_admin {
+                app.set_status(Severity::Warn, "room not found or already deleted");
+            } else {
+                app.set_status(Severity::Warn, "not a room moderator or already deleted");
+            }
+        }
+        Command::RoomColor(arg) => {
+            let names: Vec<&str> = theme::ACCENTS.iter().map(|(n, _)| *n).collect();
+            let Some(arg) = arg else {
+                let how = match &app.room.color {
+                    Some(c) => c.clone(),
+                    None => "derived from name".to_string(),
+                };
+                app.set_status(
+                    Severity::Info,
+                    format!("room color: {} (choices: {}, off)", how, names.join(" ")),
+                );
+                return Ok(());
+            };
+            let arg = arg.to_ascii_lowercase();
+            let color = if arg == "off" {
+                None
+            } else if theme::accent_by_name(&arg).is_some() {
+                Some(arg.as_str())
+            } else {
+                app.set_status(
+                    Severity::Warn,
+                    format!("usage: /roomcolor <{}|off>", names.join("|")),
+                );
+                return Ok(());
+            };
+            let creator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            if app
+                .store
+                .set_room_color(app.room.id, color, creator)
+                .await?
+            {
+                app.room.color = color.map(str::to_string);
+                let msg = match color {
+                    Some(c) => format!("room color set to {}", c),
+                    None => "room color reset (derived from name)".to_string(),
+                };
+                app.set_status(Severity::Success, msg);
+            } else {
+                app.set_status(Severity::Warn, "only the room creator can set its color");
+            }
+        }
+        Command::RoomRate(arg) => {
+            let Some(arg) = arg else {
+                let msg = match app.room.rate_per_min {
+                    Some(n) => format!("room rate: {}/min per user", n),
+                    None => format!(
+                        "room rate: {}/min per user (default)",
+                        app.opts.rate_per_min
+                    ),
+                };
+                app.set_status(Severity::Info, msg);
+                return Ok(());
+            };
+            let rate = if arg == "off" {
+                None
+            } else {
+                match rooms::parse_room_rate(&arg) {
+                    Ok(n) => Some(n),
+                    Err(e) => {
+                        app.set_status(Severity::Warn, e);
+                        return Ok(());
+                    }
+                }
+            };
+            let creator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            if app.store.set_room_rate(app.room.id, rate, creator).await? {
+                app.room.rate_per_min = rate.map(|n| n as i32);
+                app.bucket = room_bucket(&app.room, &app.opts);
+                let msg = match rate {
+                    Some(n) => format!("room rate set to {}/min", n),
+                    None => "room rate reset to the default".to_string(),
+                };
+                app.set_status(Severity::Success, msg);
+            } else {
+                app.set_status(Severity::Warn, "only the room creator can set its rate");
+            }
+        }
+        Command::Access(arg) => {
+            let Some(arg) = arg else {
+                app.set_status(
+                    Severity::Info,
+                    format!("room access: {}", app.room.access.as_str()),
+                );
+                return Ok(());
+            };
+            let access = match rooms::parse_room_access(&arg) {
+                Ok(a) => a,
+                Err(e) => {
+                    app.set_status(Severity::Warn, e);
+                    return Ok(());
+                }
+            };
+            if app.room.name == app.opts.default_room {
+                app.set_status(Severity::Warn, "the default room stays public");
+                return Ok(());
+            }
+            let creator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            if app
+                .store
+                .set_room_access(app.room.id, access, creator)
+                .await?
+            {
+                app.room.access = access;
+                let msg = match access {
+                    RoomAccess::Public => "room is public".to_string(),
+                    RoomAccess::Invite => {
+                        "room is invite-only (members stay; /roominvite for codes)".to_string()
+                    }
+                };
+                app.set_status(Severity::Success, msg);
+            } else {
+                app.set_status(Severity::Warn, "only the room creator can set its access");
+            }
+        }
+        Command::Topic(arg) => {
+            let Some(arg) = arg else {
+                let msg = match &app.room.topic {
+                    Some(t) => format!("topic: {}", t),
+                    None => "no topic set (/topic <text>)".to_string(),
+                };
+                app.set_status(Severity::Info, msg);
+                return Ok(());
+            };
+            let topic = if arg.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                match rooms::parse_topic(&arg) {
+                    Ok(t) => Some(t),
+                    Err(e) => {
+                        app.set_status(Severity::Warn, e);
+                        return Ok(());
+                    }
+                }
+            };
+            let creator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            if !app
+                .store
+                .set_room_topic(app.room.id, topic.as_deref(), creator)
+                .await?
+            {
+                app.set_status(Severity::Warn, "only the room creator can set the topic");
+                return Ok(());
+            }
+            let msg = match &topic {
+                Some(t) => format!("topic set: {}", t),
+                None => "topic cleared".to_string(),
+            };
+            app.room.topic = topic;
+            app.set_status(Severity::Success, msg);
+        }
+        Command::Quiet(arg) => {
+            let Some(arg) = arg else {
+                let msg = match app.room.quiet_hours() {
+                    Some(q) if q.active_at(chrono::Utc::now()) => {
+                        format!("quiet hours: {} (now quiet)", q.describe())
+                    }
+                    Some(q) => format!("quiet hours: {}", q.describe()),
+                    None => "quiet hours: off".to_string(),
+                };
+                app.set_status(Severity::Info, msg);
+                return Ok(());
+            };
+            let quiet = if arg.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                match rooms::parse_quiet(&arg) {
+                    Ok(q) => Some(q),
+                    Err(e) => {
+                        app.set_status(Severity::Warn, e);
+                        return Ok(());
+                    }
+                }
+            };
+            let creator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            if app
+                .store
+                .set_room_quiet(app.room.id, quiet, creator)
+                .await?
+            {
+                app.room.quiet_start = quiet.map(|q| q.start);
+                app.room.quiet_end = quiet.map(|q| q.end);
+                app.room.quiet_offset_mins = quiet.map_or(0, |q| q.offset_mins);
+                let msg = match quiet {
+                    Some(q) => format!("quiet hours set: {}", q.describe()),
+                    None => "quiet hours off".to_string(),
+                };
+                app.set_status(Severity::Success, msg);
+            } else {
+                app.set_status(Severity::Warn, "only the room creator can set quiet hours");
+            }
+        }
+        Command::Msg(to, text) => {
+            if to.is_empty() || text.is_empty() {
+                app.set_status(Severity::Warn, "usage: /msg <nick> <text>");
+                return Ok(());
+            }
+            if to == app.user.handle {
+                app.set_status(Severity::Warn, "that's you");
+                return Ok(());
+            }
+            let body = normalize_message(&text);
+            if let Err(e) = check_body_size(&body, app.opts.msg_max_len, app.opts.msg_max_bytes) {
+                app.set_status(Severity::Warn, e);
+                return Ok(());
+            }
+            if !app.bucket.try_consume(1.0) {
+                client_rate_limited(app);
+                return Ok(());
+            }
+            let sent = app
+                .store
+                .insert_dm(&app.opts.channels.events, app.user.id, &to, &body)
+                .await?;
+            match sent {
+                Some(dm) => {
+                    let text =
+                        format!("[dm] you \u{2192} {}: {}", dm.to_handle, sanitize(&dm.body));
+                    push_dm(app, text);
+                    app.set_status(Severity::Success, format!("sent to {}", dm.to_handle));
+                }
+                None => app.set_status(Severity::Warn, "no such user"),
+            }
+        }
+        Command::Purge(arg) => {
+            const USAGE: &str = "usage: /purge <n> | /purge user <handle>";
+            let criteria = if let Some(handle) = arg.strip_prefix("user ") {
+                let handle = handle.trim();
+                match app.store.get_user_by_handle(handle).await? {
+                    Some(u) => data::PurgeCriteria::User(u.id),
+                    None => {
+                        app.set_status(Severity::Warn, format!("no such user '{}'", handle));
+                        return Ok(());
+                    }
+                }
+            } else {
+                match arg.parse::<i64>() {
+                    Ok(n) if n > 0 => data::PurgeCriteria::Last(n),
+                    _ => {
+                        app.set_status(Severity::Warn, USAGE);
+                        return Ok(());
+                    }
+                }
+            };
+            let Some(ids) = app
+                .store
+                .purge_messages(
+                    &app.opts.channels.events,
+                    app.room.id,
+                    app.user.id,
+                    criteria,
+                )
+                .await?
+            else {
+                app.set_status(Severity::Warn, "only the room creator can purge");
+                return Ok(());
+            };
+            // drop them now; the broadcast does the same for everyone else
+            drop_messages(app, &ids);
+            let capped = matches!(criteria, data::PurgeCriteria::Last(n) if n > data::PURGE_MAX)
+                || ids.len() as i64 == data::PURGE_MAX;
+            let note = if capped {
+                format!(" (max {} per /purge)", data::PURGE_MAX)
+            } else {
+                String::new()
+            };
+            app.set_status(
+                Severity::Success,
+                format!("purged {} messages{}", ids.len(), note),
+            );
+            app.dirty = true;
+        }
+        Command::Leave(name_opt) => {
+            // Determine room to leave
+            let target_room_name_owned = name_opt.unwrap_or_else(|| app.room.name.clone());
             let target_name = target_room_name_owned.trim();
             if target_name.is_empty() {
-                app.status = "usage: /leave [room]".into();
+                app.set_status(Severity::Warn, "usage: /leave [room]");
                 return Ok(());
             }
             // Find room entry by name
@@ -531,11 +2907,14 @@ async fn handle_command(app: &mut App, cmd: Command) -> Result<()> {
                 if leaving_is_current {
                     // Need another room to focus
                     if app.rooms.len() <= 1 {
-                        app.status = "cannot leave the last room".into();
+                        app.set_status(Severity::Warn, "cannot leave the last room");
                         return Ok(());
                     }
                     // Drop membership first
-                    let _ = data::leave_room(&app.pool, leaving_id, app.user.id).await?;
+                    let _ = app
+                        .store
+                        .leave_room(&app.opts.channels.events, leaving_id, app.user.id)
+                        .await?;
                     // pick next room different from current
                     let mut candidate = None;
                     for off in 0..app.rooms.len() {
@@ -548,62 +2927,110 @@ async fn handle_command(app: &mut App, cmd: Command) -> Result<()> {
                     if let Some(next_id) = candidate {
                         // load next room by id (name lookup from list)
                         if let Some(re) = app.rooms.iter().find(|r| r.id == next_id) {
-                            let room =
-                                data::ensure_room_exists(&app.pool, &re.name, app.user.id).await?;
-                            data::join_room(&app.pool, room.id, app.user.id).await?;
-                            app.room = room;
-                            app.messages = data::recent_messages_view(
-                                &app.pool,
-                                app.room.id,
-                                app.opts.history_load as i64,
-                            )
-                            .await?;
-                            app.seen_ids.clear();
-                            for m in &app.messages {
-                                app.seen_ids.insert(m.id);
-                            }
+                            let (room, _) =
+                                app.store.ensure_room_exists(&re.name, app.user.id).await?;
+                            open_room(app, room).await?;
                         }
                     }
                     // remove leaving room from sidebar
                     if let Some(idx2) = app.rooms.iter().position(|r| r.id == leaving_id) {
                         app.rooms.remove(idx2);
                     }
-                    app.status = format!("left '{}'", target_name);
+                    app.set_status(Severity::Success, format!("left '{}'", target_name));
+                    return Ok(());
                 } else {
                     // Leaving a non-focused room: drop membership and remove from sidebar
-                    let _ = data::leave_room(&app.pool, leaving_id, app.user.id).await?;
+                    let _ = app
+                        .store
+                        .leave_room(&app.opts.channels.events, leaving_id, app.user.id)
+                        .await?;
                     app.rooms.remove(idx);
-                    app.status = format!("left '{}'", target_name);
+                    app.set_status(Severity::Success, format!("left '{}'", target_name));
                 }
             } else {
-                app.status = "room not in sidebar".into();
+                // Not in the sidebar (e.g. hidden by a stale list): resolve membership in the DB
+                let joined = app.store.list_joined_rooms(app.user.id).await?;
+                if let Some(r) = joined.into_iter().find(|r| r.name == target_name) {
+                    app.store
+                        .leave_room(&app.opts.channels.events, r.id, app.user.id)
+                        .await?;
+                    app.set_status(Severity::Success, format!("left '{}'", target_name));
+                } else {
+                    app.set_status(Severity::Warn, format!("not a member of '{}'", target_name));
+                }
             }
         }
         Command::Rooms => {
             // Show joined rooms with join times; mark current with '>'
-            let list = data::list_joined_rooms_with_times(&app.pool, app.user.id).await?;
+            let list = app.store.list_joined_rooms_with_times(app.user.id).await?;
             if list.is_empty() {
-                app.status = "rooms: (none)".into();
+                app.set_status(Severity::Info, "rooms: (none)");
             } else {
+                let now = chrono::Utc::now();
                 let items: Vec<String> = list
                     .into_iter()
                     .map(|r| {
                         let mark = if r.id == app.room.id { "> " } else { "" };
                         let ts = r.last_joined_at.format("%H:%M");
-                        format!("{}{} [{}]", mark, r.name, ts)
+                        let age = age_short(now - r.first_joined_at);
+                        format!("{}{} [{}, member {}]", mark, r.name, ts, age)
                     })
                     .collect();
-                app.status = format!("rooms: {}", items.join(", "));
+                app.set_status(Severity::Info, format!("rooms: {}", items.join(", ")));
             }
         }
+        Command::List => {
+            let rooms = app.store.list_rooms(app.user.id).await?;
+            let joined: Vec<i64> = app.rooms.iter().map(|r| r.id).collect();
+            // `>` is the open room, `*` the others you're in
+            let lines = rooms
+                .iter()
+                .map(|r| {
+                    let mark = if r.id == app.room.id {
+                        '>'
+                    } else if joined.contains(&r.id) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    let noun = if r.members == 1 { "member" } else { "members" };
+                    let mut text = format!("{} {:<24} {:>4} {}", mark, r.name, r.members, noun);
+                    if let Some(t) = &r.topic {
+                        text.push_str(&format!("  \u{2014} {}", sanitize(t)));
+                    }
+                    Line::from(text)
+                })
+                .collect();
+            let title = format!("rooms ({}) \u{2014} /join <name>", rooms.len());
+            app.overlay = Some(Overlay::new(title, lines));
+        }
         Command::Who(_room) => {
-            let who = data::list_recent_members(&app.pool, app.room.id, 50).await?;
-            let names: Vec<String> = who.into_iter().map(|u| u.handle).collect();
-            app.status = format!("who: {}", names.join(", "));
+            let who = app.store.list_recent_members(app.room.id, 50).await?;
+            let now = chrono::Utc::now();
+            let names: Vec<String> = who
+                .into_iter()
+                .map(|u| {
+                    let since = now - u.last_seen_at;
+                    let mark = if since < WHO_ACTIVE_WINDOW { "*" } else { "" };
+                    let ago = format!("{} ago", age_short(since));
+                    let member = age_short(now - u.first_joined_at);
+                    let entry = match u.tagline {
+                        Some(tag) => {
+                            format!("{}{} ({}, {}, member {})", mark, u.handle, tag, ago, member)
+                        }
+                        None => format!("{}{} ({}, member {})", mark, u.handle, ago, member),
+                    };
+                    match u.away_reason {
+                        Some(r) => format!("{} (away: {})", entry, r),
+                        None => entry,
+                    }
+                })
+                .collect();
+            app.set_status(Severity::Info, format!("who: {}", names.join(", ")));
         }
         Command::InviteNew(code_opt) => {
             if !app.opts.is_admin {
-                app.status = "admin only".into();
+                app.set_status(Severity::Error, "admin only");
                 return Ok(());
             }
             let code = if let Some(c) = code_opt {
@@ -611,59 +3038,2916 @@ async fn handle_command(app: &mut App, cmd: Command) -> Result<()> {
             } else {
                 random_code(12)
             };
-            match data::create_invite(&app.pool, &code, app.user.id).await {
+            match app
+                .store
+                .insert_invite(&code, app.user.id, 1, None, None)
+                .await
+            {
                 Ok(_inv) => {
-                    app.status = format!("invite created: {}", code);
+                    app.set_status(Severity::Info, format!("invite created: {}", code));
                 }
                 Err(e) => {
-                    app.status = format!("invite error: {}", e);
+                    app.set_status(Severity::Error, format!("invite error: {}", e));
                 }
             }
         }
-        Command::InviteDel(code) => {
+        Command::Invite(uses, ttl) => {
             if !app.opts.is_admin {
-                app.status = "admin only".into();
+                app.set_status(Severity::Error, "admin only");
                 return Ok(());
             }
-            if code.trim().is_empty() {
-                app.status = "usage: /invite-del <code>".into();
+            let Some((uses, ttl_parsed)) = invite_terms(uses, ttl.as_deref()) else {
+                app.set_status(
+                    Severity::Warn,
+                    format!(
+                        "usage: /invite [uses] [ttl] (1-{} uses, ttl like 30m/12h/7d)",
+                        INVITE_MAX_USES
+                    ),
+                );
+                return Ok(());
+            };
+            let code = app
+                .store
+                .create_invite(app.user.id, uses, ttl_parsed, None)
+                .await?;
+            app.set_status(
+                Severity::Success,
+                format!("invite code: {}", invite_summary(&code, uses, ttl)),
+            );
+        }
+        Command::RoomInvite(uses, ttl) => {
+            let allowed =
+                app.opts.is_admin || app.store.is_moderator(app.room.id, app.user.id).await?;
+            if !allowed {
+                app.set_status(
+                    Severity::Warn,
+                    "only the room creator or a moderator can invite",
+                );
                 return Ok(());
             }
-            let ok = data::delete_invite(&app.pool, code.trim()).await?;
-            app.status = if ok {
-                "invite deleted".into()
-            } else {
-                "not found".into()
+            let Some((uses, ttl_parsed)) = invite_terms(uses, ttl.as_deref()) else {
+                app.set_status(
+                    Severity::Warn,
+                    format!(
+                        "usage: /roominvite [uses] [ttl] (1-{} uses, ttl like 30m/12h/7d)",
+                        INVITE_MAX_USES
+                    ),
+                );
+                return Ok(());
             };
+            let code = app
+                .store
+                .create_invite(app.user.id, uses, ttl_parsed, Some(app.room.id))
+                .await?;
+            app.set_status(
+                Severity::Success,
+                format!(
+                    "invite code for {}: {} (/join {} <code>)",
+                    app.room.name,
+                    invite_summary(&code, uses, ttl),
+                    app.room.name
+                ),
+            );
         }
-        Command::Invites => {
+        Command::InviteDel(code) => {
             if !app.opts.is_admin {
-                app.status = "admin only".into();
+                app.set_status(Severity::Error, "admin only");
                 return Ok(());
             }
-            let invs = data::list_invites(&app.pool, 20).await?;
-            if invs.is_empty() {
-                app.status = "invites: (none)".into();
+            if code.trim().is_empty() {
+                app.set_status(Severity::Warn, "usage: /invite-del <code>");
+                return Ok(());
+            }
+            let ok = app.store.delete_invite(code.trim()).await?;
+            if ok {
+                app.set_status(Severity::Success, "invite deleted");
             } else {
-                let s = invs
-                    .into_iter()
-                    .map(|i| i.code)
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                app.status = format!("invites: {}", s);
+                app.set_status(Severity::Warn, "not found");
             }
         }
-    }
-    Ok(())
-}
-
-fn random_code(n: usize) -> String {
-    use rand::{distributions::Alphanumeric, Rng};
-    let s: String = rand::thread_rng()
-        .sample_iter(&Alphanumeric)
-        .filter(|c| c.is_ascii_alphanumeric())
-        .map(|c| (c as char).to_ascii_lowercase())
-        .take(n)
-        .collect();
+        Command::Edit(text) => {
+            if text.is_empty() {
+                app.set_status(Severity::Warn, "usage: /edit <text>");
+                return Ok(());
+            }
+            // same budget as a fresh send, signature included
+            let body = normalize_message(&text);
+            let Some(body) = with_signature(&body, app.signature.as_deref(), app.opts.msg_max_len)
+            else {
+                app.set_status(Severity::Warn, "message too long with signature");
+                return Ok(());
+            };
+            if let Err(e) = check_body_size(&body, app.opts.msg_max_len, app.opts.msg_max_bytes) {
+                app.set_status(Severity::Warn, e);
+                return Ok(());
+            }
+            let edited = app
+                .store
+                .edit_last_message(&app.opts.channels.events, app.room.id, app.user.id, &body)
+                .await?;
+            let Some(msg) = edited else {
+                app.set_status(Severity::Warn, "nothing to edit in this room");
+                return Ok(());
+            };
+            // the broadcast refreshes the line for everyone else
+            if let Some(m) = app.messages.iter_mut().find(|m| m.id == msg.id) {
+                m.body = msg.body;
+                m.edited = true;
+            }
+            app.set_status(Severity::Success, format!("edited #{}", msg.id));
+            app.dirty = true;
+        }
+        Command::Reply(id, text) => {
+            let (Some(id), false) = (id, text.is_empty()) else {
+                app.set_status(Severity::Warn, "usage: /reply <id> <text>");
+                return Ok(());
+            };
+            let Some(parent) = message_in_room(app, id).await? else {
+                return Ok(());
+            };
+            send_message(app, &text, Some(parent)).await?;
+        }
+        Command::React(id, emoji) => {
+            let (Some(id), false) = (id, emoji.is_empty()) else {
+                app.set_status(Severity::Warn, "usage: /react <id> <emoji>");
+                return Ok(());
+            };
+            if !valid_reaction(&emoji) {
+                app.set_status(Severity::Warn, "reactions are a single emoji");
+                return Ok(());
+            }
+            if message_in_room(app, id).await?.is_none() {
+                return Ok(());
+            }
+            let toggled = app
+                .store
+                .toggle_reaction(&app.opts.channels.events, id, app.user.id, &emoji)
+                .await?;
+            match toggled {
+                Some(true) => {
+                    app.set_status(Severity::Success, format!("reacted {} to #{}", emoji, id))
+                }
+                Some(false) => {
+                    app.set_status(Severity::Info, format!("removed {} from #{}", emoji, id))
+                }
+                None => app.set_status(Severity::Warn, format!("message #{} not found", id)),
+            }
+        }
+        Command::Pin(id) | Command::Unpin(id) => {
+            let pin = matches!(cmd, Command::Pin(_));
+            let verb = if pin { "pin" } else { "unpin" };
+            let Some(id) = id else {
+                app.set_status(Severity::Warn, format!("usage: /{} <id>", verb));
+                return Ok(());
+            };
+            if message_in_room(app, id).await?.is_none() {
+                return Ok(());
+            }
+            let moderator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            let allowed = match moderator {
+                None => true,
+                Some(u) => app.store.is_moderator(app.room.id, u).await?,
+            };
+            let outcome = match allowed {
+                true => {
+                    app.store
+                        .set_pin(&app.opts.channels.events, app.room.id, id, pin, moderator)
+                        .await?
+                }
+                false => PinOutcome::Refused,
+            };
+            match outcome {
+                PinOutcome::Refused => app.set_status(
+                    Severity::Warn,
+                    format!("only the room creator or a moderator can {} messages", verb),
+                ),
+                PinOutcome::Unchanged => {
+                    let state = if pin { "already pinned" } else { "not pinned" };
+                    app.set_status(Severity::Info, format!("#{} is {}", id, state));
+                }
+                PinOutcome::Changed => {
+                    load_pins(app).await?;
+                    let done = if pin { "pinned" } else { "unpinned" };
+                    app.set_status(Severity::Success, format!("{} #{}", done, id));
+                }
+            }
+        }
+        Command::Kick(nick) => {
+            if nick.is_empty() {
+                app.set_status(Severity::Warn, "usage: /kick <nick>");
+                return Ok(());
+            }
+            let moderator = if app.opts.is_admin {
+                None
+            } else {
+                Some(app.user.id)
+            };
+            let allowed = match moderator {
+                None => true,
+                Some(u) => app.store.is_moderator(app.room.id, u).await?,
+            };
+            if !allowed {
+                app.set_status(
+                    Severity::Warn,
+                    "only the room creator or a moderator can kick",
+                );
+                return Ok(());
+            }
+            // everyone lands in the default room, so a kick there wouldn't stick
+            if app.room.name == app.opts.default_room {
+                app.set_status(Severity::Warn, "nobody can be kicked from the default room");
+                return Ok(());
+            }
+            let Some(u) = app.store.get_user_by_handle(&nick).await? else {
+                app.set_status(Severity::Warn, "no such user");
+                return Ok(());
+            };
+            if u.id == app.user.id {
+                app.set_status(Severity::Warn, "use /leave to leave the room");
+                return Ok(());
+            }
+            if u.id == app.room.created_by {
+                app.set_status(Severity::Warn, "the room creator can't be kicked");
+                return Ok(());
+            }
+            let kicked = app
+                .store
+                .kick_member(
+                    &app.opts.channels.events,
+                    app.room.id,
+                    u.id,
+                    app.user.id,
+                    moderator,
+                )
+                .await?;
+            match kicked {
+                true => app.set_status(
+                    Severity::Success,
+                    format!("removed {} from {}", u.handle, app.room.name),
+                ),
+                false => app.set_status(Severity::Info, format!("{} isn't in this room", u.handle)),
+            }
+        }
+        Command::Mute(nick, minutes) => {
+            if nick.is_empty() || minutes == 0 || minutes > data::MUTE_MAX_MINS {
+                app.set_status(
+                    Severity::Warn,
+                    format!("usage: /mute <nick> <minutes> (1-{})", data::MUTE_MAX_MINS),
+                );
+                return Ok(());
+            }
+            set_mute(app, &nick, minutes).await?;
+        }
+        Command::Unmute(nick) => {
+            if nick.is_empty() {
+                app.set_status(Severity::Warn, "usage: /unmute <nick>");
+                return Ok(());
+            }
+            set_mute(app, &nick, 0).await?;
+        }
+        Command::Mod(nick) => {
+            set_moderator(app, &nick, true).await?;
+        }
+        Command::Unmod(nick) => {
+            set_moderator(app, &nick, false).await?;
+        }
+        Command::Pins => {
+            let msg = match app.pins.is_empty() {
+                true => "no pinned messages".to_string(),
+                false => {
+                    let ids: Vec<String> = app
+                        .pins
+                        .iter()
+                        .map(|p| format!("#{} {}", p.id, p.user_handle))
+                        .collect();
+                    format!("pinned: {} (/goto <id>)", ids.join(", "))
+                }
+            };
+            app.set_status(Severity::Info, msg);
+        }
+        Command::Goto(id) => match id {
+            Some(id) => goto_message(app, id).await?,
+            None => app.set_status(Severity::Warn, "usage: /goto <id>"),
+        },
+        Command::Del(id) => {
+            let Some(id) = id else {
+                app.set_status(Severity::Warn, "usage: /del <id>");
+                return Ok(());
+            };
+            let deleted = app
+                .store
+                .soft_delete_message(&app.opts.channels.events, id, app.user.id)
+                .await?;
+            if !deleted {
+                app.set_status(Severity::Warn, "not your message");
+                return Ok(());
+            }
+            drop_messages(app, &[id]);
+            app.last_deletion = Some(LastDeletion {
+                kind: Deleted::Message { id },
+                at: Instant::now(),
+            });
+            app.set_status(
+                Severity::Success,
+                format!("message #{} deleted (/undo within 60s)", id),
+            );
+        }
+        Command::Undo => {
+            let Some(last) = app.last_deletion.take() else {
+                app.set_status(Severity::Warn, "nothing to undo");
+                return Ok(());
+            };
+            if last.at.elapsed() > UNDO_WINDOW {
+                app.set_status(Severity::Warn, "too late to undo (60s window)");
+                return Ok(());
+            }
+            match last.kind {
+                Deleted::Room { id, name } => {
+                    let creator = if app.opts.is_admin {
+                        None
+                    } else {
+                        Some(app.user.id)
+                    };
+                    let restored = app
+                        .store
+                        .restore_room(&app.opts.channels.events, id, creator, UNDO_WINDOW)
+                        .await?;
+                    if !restored {
+                        app.set_status(Severity::Warn, "couldn't restore room (too late?)");
+                        return Ok(());
+                    }
+                    room_restored(app, id).await?;
+                    app.set_status(Severity::Success, format!("room '{}' restored", name));
+                }
+                Deleted::Message { id } => {
+                    let restored = app
+                        .store
+                        .restore_message(&app.opts.channels.events, id, app.user.id, UNDO_WINDOW)
+                        .await?;
+                    if !restored {
+                        app.set_status(Severity::Warn, "couldn't restore message (too late?)");
+                        return Ok(());
+                    }
+                    // the broadcast puts it back for everyone else
+                    if let Some(v) = app.store.message_view_by_id(id).await? {
+                        if v.room_id == app.room.id {
+                            insert_in_order(app, v);
+                        }
+                    }
+                    app.set_status(Severity::Success, format!("message #{} restored", id));
+                }
+            }
+        }
+        Command::Invites => {
+            if !app.opts.is_admin {
+                app.set_status(Severity::Error, "admin only");
+                return Ok(());
+            }
+            let invs = app.store.list_invites(20).await?;
+            if invs.is_empty() {
+                app.set_status(Severity::Info, "invites: (none)");
+            } else {
+                let now = chrono::Utc::now();
+                let s = invs
+                    .into_iter()
+                    .map(|i| {
+                        let mut notes = Vec::new();
+                        if i.uses_left != 1 {
+                            notes.push(format!("{} uses", i.uses_left));
+                        }
+                        match i.expires_at {
+                            Some(t) if t < now => notes.push("expired".into()),
+                            Some(t) => notes.push(format!("expires in {}", age_short(t - now))),
+                            None => {}
+                        }
+                        if notes.is_empty() {
+                            i.code
+                        } else {
+                            format!("{} ({})", i.code, notes.join(", "))
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                app.set_status(Severity::Info, format!("invites: {}", s));
+            }
+        }
+        Command::SetMotd(arg) => {
+            if !app.opts.is_admin {
+                app.set_status(Severity::Error, "admin only");
+                return Ok(());
+            }
+            let arg = arg.trim();
+            if arg.is_empty() {
+                app.set_status(Severity::Warn, "usage: /setmotd <text|off>");
+                return Ok(());
+            }
+            if arg == "off" {
+                app.store.set_setting(MOTD_KEY, None).await?;
+                app.set_status(Severity::Success, "motd cleared");
+                return Ok(());
+            }
+            let motd = match parse_motd(arg) {
+                Ok(m) => m,
+                Err(e) => {
+                    app.set_status(Severity::Warn, e);
+                    return Ok(());
+                }
+            };
+            app.store.set_setting(MOTD_KEY, Some(&motd)).await?;
+            show_motd(app).await?;
+            app.set_status(Severity::Success, "motd set");
+        }
+        Command::Shout(text) => {
+            if !app.opts.is_admin {
+                app.set_status(Severity::Error, "admin only");
+                return Ok(());
+            }
+            let text = normalize_message(text.trim());
+            if text.is_empty() {
+                app.set_status(Severity::Warn, "usage: /shout <text>");
+                return Ok(());
+            }
+            if text.chars().count() > app.opts.msg_max_len {
+                app.set_status(Severity::Warn, "announcement too long");
+                return Ok(());
+            }
+            // our own listener delivers the banner along with everyone else's
+            app.store
+                .insert_announcement(&app.opts.channels.announce, app.user.id, &text)
+                .await?;
+            app.set_status(Severity::Success, "announcement sent");
+        }
+        Command::Feedback(arg) => {
+            let arg = arg.trim();
+            // admins get two subcommands; everyone else's text is always a report
+            if app.opts.is_admin && arg == "review" {
+                let open = app.store.list_open_feedback(FEEDBACK_REVIEW_LIMIT).await?;
+                if open.is_empty() {
+                    app.set_status(Severity::Info, "feedback: (none open)");
+                    return Ok(());
+                }
+                let mut lines = Vec::new();
+                for f in &open {
+                    lines.push(Line::from(format!(
+                        "#{} [{}] {}: {}",
+                        f.id,
+                        f.created_at.format("%Y-%m-%d %H:%M"),
+                        f.user_handle,
+                        sanitize(&f.body)
+                    )));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::from("/feedback done <id> marks an entry handled"));
+                app.overlay = Some(Overlay::new("feedback", lines));
+                app.set_status(Severity::Info, format!("feedback: {} open", open.len()));
+                return Ok(());
+            }
+            if let Some(id) = arg.strip_prefix("done ").filter(|_| app.opts.is_admin) {
+                let Ok(id) = id.trim().trim_start_matches('#').parse::<i64>() else {
+                    app.set_status(Severity::Warn, "usage: /feedback done <id>");
+                    return Ok(());
+                };
+                if app.store.mark_feedback_handled(id).await? {
+                    app.set_status(Severity::Success, format!("feedback #{} handled", id));
+                } else {
+                    app.set_status(Severity::Warn, "no open feedback with that id");
+                }
+                return Ok(());
+            }
+            let text = normalize_message(arg);
+            if text.trim().is_empty() {
+                app.set_status(Severity::Warn, "usage: /feedback <text>");
+                return Ok(());
+            }
+            if text.chars().count() > FEEDBACK_MAX_LEN {
+                app.set_status(
+                    Severity::Warn,
+                    format!("feedback too long (max {} chars)", FEEDBACK_MAX_LEN),
+                );
+                return Ok(());
+            }
+            app.store.submit_feedback(app.user.id, &text).await?;
+            app.set_status(Severity::Success, "thanks, logged");
+        }
+        Command::Sig(arg) => {
+            let arg = arg.trim();
+            if arg.is_empty() {
+                let text = match &app.signature {
+                    Some(sig) => format!("signature: {}", sig),
+                    None => "signature: (none)".into(),
+                };
+                app.set_status(Severity::Info, text);
+                return Ok(());
+            }
+            if arg == "off" {
+                app.store.set_signature(app.user.id, None).await?;
+                app.signature = None;
+                app.set_status(Severity::Success, "signature cleared");
+                return Ok(());
+            }
+            let sig = normalize_message(arg);
+            if sig.contains('\n') {
+                app.set_status(Severity::Warn, "signature cannot contain newlines");
+                return Ok(());
+            }
+            let sig = sig.trim();
+            if sig.chars().count() > SIGNATURE_MAX_LEN {
+                app.set_status(
+                    Severity::Warn,
+                    format!("signature too long (max {})", SIGNATURE_MAX_LEN),
+                );
+                return Ok(());
+            }
+            app.store.set_signature(app.user.id, Some(sig)).await?;
+            app.signature = Some(sig.to_string());
+            app.set_status(Severity::Success, "signature set");
+        }
+        Command::Status(arg) => {
+            let arg = arg.trim();
+            if arg.is_empty() {
+                let text = match &app.tagline {
+                    Some(tag) => format!("status: {}", tag),
+                    None => "status: (none)".into(),
+                };
+                app.set_status(Severity::Info, text);
+                return Ok(());
+            }
+            if arg == "off" {
+                app.store.set_tagline(app.user.id, None).await?;
+                app.tagline = None;
+                app.set_status(Severity::Success, "status cleared");
+                return Ok(());
+            }
+            let tag = normalize_message(arg).replace(['\n', '\t'], " ");
+            let tag = tag.trim();
+            if tag.chars().count() > TAGLINE_MAX_LEN {
+                app.set_status(
+                    Severity::Warn,
+                    format!("status too long (max {})", TAGLINE_MAX_LEN),
+                );
+                return Ok(());
+            }
+            app.store.set_tagline(app.user.id, Some(tag)).await?;
+            app.tagline = Some(tag.to_string());
+            app.set_status(Severity::Success, format!("status set: {}", tag));
+        }
+        Command::Time => {
+            app.opts.relative_time = !app.opts.relative_time;
+            app.dirty = true;
+            let mode = if app.opts.relative_time {
+                "relative"
+            } else {
+                "clock"
+            };
+            app.set_status(Severity::Info, format!("timestamps: {}", mode));
+        }
+        Command::Clear => {
+            // view only: history stays in the DB and comes back with PgUp or a room switch
+            let newest = app.seen_ids.iter().copied().max().unwrap_or(0);
+            app.clear_floor = app.clear_floor.max(newest);
+            app.messages.clear();
+            app.seen_ids.clear();
+            app.scroll_offset = 0;
+            app.link_sel = None;
+            app.dirty = true;
+            app.set_status(Severity::Success, "cleared (local)");
+        }
+        Command::Away(arg) => {
+            let Some(arg) = arg else {
+                if app.user.away_reason.take().is_some() {
+                    app.store.set_away(app.user.id, None).await?;
+                    app.set_status(Severity::Success, "welcome back");
+                } else {
+                    app.set_status(
+                        Severity::Info,
+                        "usage: /away <reason> (/away again to return)",
+                    );
+                }
+                return Ok(());
+            };
+            let reason = normalize_message(&arg).replace(['\n', '\t'], " ");
+            let reason = reason.trim();
+            if reason.chars().count() > AWAY_MAX_LEN {
+                app.set_status(
+                    Severity::Warn,
+                    format!("away reason too long (max {})", AWAY_MAX_LEN),
+                );
+                return Ok(());
+            }
+            app.store.set_away(app.user.id, Some(reason)).await?;
+            app.user.away_reason = Some(reason.to_string());
+            app.set_status(Severity::Success, format!("away: {}", reason));
+        }
+        Command::ServerInfo => {
+            let info = app.store.server_info().await?;
+            let oldest = info
+                .oldest_message_at
+                .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+                .unwrap_or_else(|| "(none)".into());
+            let lines = vec![
+                Line::from(format!("  users              {}", info.users)),
+                Line::from(format!("  rooms              {}", info.rooms)),
+                Line::from(format!("  messages           {}", info.messages)),
+                Line::from(format!("  messages (1h)      {}", info.messages_last_hour)),
+                Line::from(format!("  oldest message     {}", oldest)),
+                Line::from(format!(
+                    "  retention          {} days",
+                    app.opts.retention_days
+                )),
+            ];
+            app.overlay = Some(Overlay::new("server info", lines));
+            app.set_status(Severity::Info, "serverinfo");
+        }
+        Command::More => {
+            load_older(app).await?;
+        }
+        Command::MyMessages => {
+            let page = app.store.user_messages(app.user.id, 0, MINE_PAGE).await?;
+            if page.is_empty() {
+                app.set_status(Severity::Info, "you haven't posted anything yet");
+                return Ok(());
+            }
+            let got = page.len() as i64;
+            let mut ov = Overlay::new("my messages", page.iter().map(mine_line).collect());
+            if got == MINE_PAGE {
+                ov.more = Some(OverlayMore::Mine { offset: got });
+            }
+            app.overlay = Some(ov);
+            app.set_status(Severity::Info, "mine (newest first)");
+        }
+        Command::Stats => {
+            let st = app.store.room_stats(app.room.id).await?;
+            let lines = vec![
+                Line::from(format!("  messages           {}", st.messages)),
+                Line::from(format!("  posters            {}", st.posters)),
+                Line::from(format!(
+                    "  avg length         {:.1}",
+                    st.avg_len.unwrap_or(0.0)
+                )),
+                Line::from(format!("  max length         {}", st.max_len.unwrap_or(0))),
+            ];
+            app.overlay = Some(Overlay::new(format!("stats for {}", app.room.name), lines));
+            app.set_status(Severity::Info, "stats");
+        }
+        Command::Audit(arg) => {
+            if !app.opts.is_admin {
+                app.set_status(Severity::Error, "admin only");
+                return Ok(());
+            }
+            let rows = app.store.room_member_fingerprints(app.room.id).await?;
+            match arg.as_deref() {
+                None => {
+                    let mut lines: Vec<Line> = rows
+                        .iter()
+                        .map(|r| {
+                            Line::from(format!(
+                                "  {:<16} {:<12} {}",
+                                r.handle,
+                                sanitize(&r.pubkey_type),
+                                sanitize(&r.fingerprint_sha256)
+                            ))
+                        })
+                        .collect();
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(
+                        "  /audit save writes this list to BBS_AUDIT_DIR",
+                    ));
+                    app.overlay = Some(Overlay::new(
+                        format!("audit {} ({} members)", app.room.name, rows.len()),
+                        lines,
+                    ));
+                    app.set_status(Severity::Info, "audit");
+                    return Ok(());
+                }
+                Some("save") => {
+                    let Some(dir) = &app.opts.audit_dir else {
+                        app.set_status(Severity::Warn, "set BBS_AUDIT_DIR to enable /audit save");
+                        return Ok(());
+                    };
+                    let path = dir.join(format!(
+                        "audit-{}-{}.tsv",
+                        app.room.name,
+                        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+                    ));
+                    let mut out = String::from("handle\tpubkey_type\tfingerprint_sha256\n");
+                    for r in &rows {
+                        out.push_str(&format!(
+                            "{}\t{}\t{}\n",
+                            tsv_field(&r.handle),
+                            tsv_field(&r.pubkey_type),
+                            tsv_field(&r.fingerprint_sha256)
+                        ));
+                    }
+                    match std::fs::write(&path, out) {
+                        Ok(()) => app.set_status(
+                            Severity::Success,
+                            format!("audit saved to {}", path.display()),
+                        ),
+                        Err(e) => {
+                            app.set_status(Severity::Error, format!("audit save failed: {}", e))
+                        }
+                    }
+                }
+                Some(_) => app.set_status(Severity::Warn, "usage: /audit [save]"),
+            }
+        }
+        Command::Whois(handle) => {
+            let handle = handle.trim();
+            if handle.is_empty() {
+                app.set_status(Severity::Warn, "usage: /whois <nick>");
+                return Ok(());
+            }
+            let Some(u) = app.store.get_user_by_handle(handle).await? else {
+                app.set_status(Severity::Warn, "no such user");
+                return Ok(());
+            };
+            // key comment is a client-supplied hint, never an identity
+            let hint = u
+                .key_comment
+                .as_deref()
+                .map(sanitize)
+                .unwrap_or_else(|| "(none)".into());
+            let lines = vec![
+                Line::from(format!("  handle       {}", u.handle)),
+                Line::from(format!(
+                    "  fingerprint  {}",
+                    fp_short(&u.fingerprint_sha256)
+                )),
+                Line::from(format!("  key type     {}", u.pubkey_type)),
+                Line::from(format!("  key hint     {}", hint)),
+                Line::from(format!(
+                    "  since        {}",
+                    u.created_at.format("%Y-%m-%d")
+                )),
+                Line::from(format!(
+                    "  last seen    {}",
+                    u.last_seen_at.format("%Y-%m-%d %H:%M UTC")
+                )),
+            ];
+            app.overlay = Some(Overlay::new(format!("whois {}", u.handle), lines));
+            app.set_status(Severity::Info, "whois");
+        }
+        Command::Filter(arg) => {
+            let arg = arg.trim();
+            if arg.is_empty() {
+                let msg = match &app.filter {
+                    Some(f) => format!("showing only {} (/filter off to clear)", f.handle),
+                    None => "usage: /filter <nick|off>".to_string(),
+                };
+                app.set_status(Severity::Info, msg);
+                return Ok(());
+            }
+            if arg == "off" {
+                app.filter = None;
+                app.set_status(Severity::Success, "filter cleared");
+                return Ok(());
+            }
+            let Some(u) = app.store.get_user_by_handle(arg).await? else {
+                app.set_status(Severity::Warn, "no such user");
+                return Ok(());
+            };
+            app.filter = Some(ViewFilter {
+                user_id: u.id,
+                handle: u.handle,
+            });
+            app.link_sel = None;
+            app.set_status(Severity::Success, format!("showing only {}", arg));
+        }
+        Command::Ignore(arg) => {
+            if arg.is_empty() {
+                let mut names = Vec::new();
+                for id in &app.ignored {
+                    if let Some(h) = app.store.user_handle(*id).await? {
+                        names.push(h);
+                    }
+                }
+                names.sort();
+                let msg = if names.is_empty() {
+                    "usage: /ignore <nick> (/unignore <nick> to undo)".to_string()
+                } else {
+                    format!("ignoring: {}", names.join(", "))
+                };
+                app.set_status(Severity::Info, msg);
+                return Ok(());
+            }
+            let Some(u) = app.store.get_user_by_handle(&arg).await? else {
+                app.set_status(Severity::Warn, "no such user");
+                return Ok(());
+            };
+            if u.id == app.user.id {
+                app.set_status(Severity::Warn, "you can't ignore yourself");
+                return Ok(());
+            }
+            app.store.add_ignore(app.user.id, u.id).await?;
+            app.ignored.insert(u.id);
+            app.link_sel = None;
+            app.scroll_offset = app.scroll_offset.min(max_scroll(app));
+            app.dirty = true;
+            app.set_status(
+                Severity::Success,
+                format!("ignoring {} (/unignore {} to undo)", u.handle, u.handle),
+            );
+        }
+        Command::Unignore(arg) => {
+            if arg.is_empty() {
+                app.set_status(Severity::Warn, "usage: /unignore <nick>");
+                return Ok(());
+            }
+            let Some(u) = app.store.get_user_by_handle(&arg).await? else {
+                app.set_status(Severity::Warn, "no such user");
+                return Ok(());
+            };
+            let removed = app.store.remove_ignore(app.user.id, u.id).await?;
+            if app.ignored.remove(&u.id) || removed {
+                app.dirty = true;
+                app.set_status(
+                    Severity::Success,
+                    format!("no longer ignoring {}", u.handle),
+                );
+            } else {
+                app.set_status(Severity::Info, format!("{} isn't ignored", u.handle));
+            }
+        }
+        Command::WhoAmI => {
+            // all from the session; the account is the key, the handle is just a label
+            let fp = if app.opts.fp_short.is_empty() {
+                "(none; local session)".to_string()
+            } else {
+                app.opts.fp_short.clone()
+            };
+            let mut lines = vec![
+                Line::from(format!("  handle       {}", app.user.handle)),
+                Line::from(format!("  fingerprint  {}", fp)),
+                Line::from(format!("  key type     {}", app.user.pubkey_type)),
+                Line::from(format!(
+                    "  since        {}",
+                    app.user.created_at.format("%Y-%m-%d")
+                )),
+                Line::from(format!("  room         {}", app.room.name)),
+            ];
+            if app.opts.is_admin {
+                lines.push(Line::from("  role         admin"));
+            }
+            lines.extend([
+                Line::from(""),
+                Line::from("  Your account is your SSH key: the same key always logs in as you."),
+                Line::from("  /nick renames you; a different key is a different account."),
+            ]);
+            app.overlay = Some(Overlay::new("whoami", lines));
+            app.set_status(Severity::Info, "whoami");
+        }
+        Command::Find(handle) => {
+            let handle = handle.trim();
+            if handle.is_empty() {
+                app.set_status(Severity::Warn, "usage: /find <nick>");
+                return Ok(());
+            }
+            let Some(u) = app.store.get_user_by_handle(handle).await? else {
+                app.set_status(Severity::Warn, "no such user");
+                return Ok(());
+            };
+            let rooms = app.store.user_rooms(u.id, app.user.id).await?;
+            if rooms.is_empty() {
+                app.set_status(Severity::Info, format!("{} isn't in any rooms", u.handle));
+                return Ok(());
+            }
+            // mark rooms we share so it's obvious where to say hi
+            let lines = rooms
+                .iter()
+                .map(|r| {
+                    let mark = if app.rooms.iter().any(|j| j.id == r.id) {
+                        '*'
+                    } else {
+                        ' '
+                    };
+                    Line::from(format!(" {} {}", mark, r.name))
+                })
+                .collect();
+            app.overlay = Some(Overlay::new(
+                format!("{} is in {} room(s)", u.handle, rooms.len()),
+                lines,
+            ));
+            app.set_status(Severity::Info, "find (* = you're in it too)");
+        }
+    }
+    Ok(())
+}
+
+fn random_code(n: usize) -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+    let s: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| (c as char).to_ascii_lowercase())
+        .take(n)
+        .collect();
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_expires_after_ttl() {
+        let now = Instant::now();
+        assert!(!Status::hint().expired(now + Severity::Error.ttl()));
+        let ttl = Severity::Success.ttl();
+        let st = Status {
+            severity: Severity::Success,
+            text: "sent".into(),
+            expires_at: Some(now + ttl),
+        };
+        assert!(!st.expired(now));
+        assert!(st.expired(now + ttl));
+        assert!(Severity::Error.ttl() > Severity::Success.ttl());
+    }
+
+    #[test]
+    fn min_size_covers_fixed_layout() {
+        use ratatui::layout::Rect;
+        let min = min_size();
+        assert_eq!((min.width, min.height), (34, 5));
+        assert!(min.fits(Rect::new(0, 0, 34, 5)));
+        assert!(min.fits(Rect::new(0, 0, 100, 30)));
+        assert!(!min.fits(Rect::new(0, 0, 33, 30)));
+        assert!(!min.fits(Rect::new(0, 0, 100, 4)));
+        assert!(!min.fits(Rect::new(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn sidebar_line_caps_and_truncates() {
+        assert_eq!(sidebar_line('>', "lobby", 0, "", 0, 22), "> lobby");
+        assert_eq!(sidebar_line('>', "lobby", 3, "", 0, 22), "> lobby [3]");
+        assert_eq!(
+            sidebar_line(' ', "lobby", 0, "", 5, 22),
+            "  lobby (5 unread)"
+        );
+        assert_eq!(
+            sidebar_line(' ', "lobby", 3, "", 2, 26),
+            "  lobby [3] (2 unread)"
+        );
+        assert_eq!(
+            sidebar_line(' ', "lobby", 0, "▁▄█", 5, 26),
+            "  lobby ▁▄█ (5 unread)"
+        );
+        let line = sidebar_line(' ', "a_very_long_room_name_xx", 0, "", 12345, 22);
+        assert_eq!(line, "  a_very… (99+ unread)");
+        assert_eq!(line.chars().count(), 22);
+    }
+
+    // UI-level tests run against the in-memory store: no terminal, no Postgres.
+    use crate::memstore::MemStore;
+
+    fn test_opts() -> UiOpts {
+        UiOpts {
+            history_load: 50,
+            msg_max_len: 1000,
+            msg_max_bytes: 3000,
+            fp_short: "abcd1234".into(),
+            rate_per_min: 10,
+            rate_burst: None,
+            is_admin: false,
+            retention_days: 30,
+            color_level: ColorLevel::NoColor,
+            idle_timeout_secs: 0,
+            screensaver_secs: 0,
+            sparkline: false,
+            show_tagline: false,
+            catch_up: false,
+            msg_format: MsgFormat::default(),
+            relative_time: false,
+            default_room: "lobby".into(),
+            audit_dir: None,
+            channels: realtime::Channels::new(realtime::DEFAULT_CHANNEL),
+            greeting: None,
+        }
+    }
+
+    // alice, joined to lobby, with a fresh session
+    async fn test_app(store: MemStore) -> App<MemStore> {
+        let user = store.add_user("alice");
+        let (room, _) = store.ensure_room_exists("lobby", user.id).await.unwrap();
+        store.join_room("", room.id, user.id).await.unwrap();
+        store.take_events();
+        App::load(store, user, room, test_opts()).await.unwrap()
+    }
+
+    async fn submit(app: &mut App<MemStore>, line: &str) {
+        app.input = line.to_string();
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        handle_key(app, enter).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn undo_brings_a_deleted_room_back_alone() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/join dev").await;
+        submit(&mut app, "/join lobby").await;
+        app.rooms[0].unread = 2;
+        submit(&mut app, "/room-del dev").await;
+        assert_eq!(app.status.text, "room 'dev' deleted (/undo within 60s)");
+        assert!(!app.rooms.iter().any(|r| r.name == "dev"));
+        app.store.take_events();
+
+        submit(&mut app, "/undo").await;
+        assert_eq!(app.status.text, "room 'dev' restored");
+        let names: Vec<&str> = app.rooms.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["lobby", "dev"]);
+        assert_eq!(app.rooms[0].unread, 2);
+        // other members hear it and get the room back too
+        let events = app.store.take_events();
+        let dev = app.rooms[1].id;
+        assert!(
+            matches!(events[..], [realtime::Event::RoomRestored { room_id }] if room_id == dev)
+        );
+        app.rooms.pop();
+        apply_event(&mut app, events[0]).await.unwrap();
+        assert_eq!(app.rooms.last().map(|r| r.id), Some(dev));
+        assert_eq!(app.rooms[0].unread, 2);
+    }
+
+    #[tokio::test]
+    async fn own_message_shows_once() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "hello").await;
+        assert_eq!(app.status.text, "sent");
+        assert!(app.input.is_empty());
+        // our own NOTIFY comes back too; it must not duplicate the line
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let bodies: Vec<&str> = app.messages.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, ["hello"]);
+    }
+
+    #[tokio::test]
+    async fn filter_hides_but_keeps_other_users() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        submit(&mut app, "/filter bob").await;
+        assert_eq!(app.status.text, "showing only bob");
+        submit(&mut app, "mine").await;
+        let room = app.room.id;
+        app.store
+            .insert_message(ch, room, bob.id, "bob's")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let shown: Vec<&str> = app.visible_messages().map(|m| m.body.as_str()).collect();
+        assert_eq!(shown, ["bob's"]);
+        submit(&mut app, "/filter off").await;
+        assert_eq!(app.visible_messages().count(), 2);
+        submit(&mut app, "/filter nobody").await;
+        assert_eq!(app.status.text, "no such user");
+        assert!(app.filter.is_none());
+    }
+
+    #[tokio::test]
+    async fn shift_enter_composes_multiline_message() {
+        let mut app = test_app(MemStore::new()).await;
+        let key = |code, mods| KeyEvent::new(code, mods);
+        for (code, mods) in [
+            (KeyCode::Char('a'), KeyModifiers::NONE),
+            (KeyCode::Enter, KeyModifiers::SHIFT),
+            (KeyCode::Enter, KeyModifiers::ALT),
+            (KeyCode::Backspace, KeyModifiers::NONE),
+            (KeyCode::Char('b'), KeyModifiers::NONE),
+        ] {
+            handle_key(&mut app, key(code, mods)).await.unwrap();
+        }
+        assert_eq!(app.input, "a\nb");
+        assert!(app.messages.is_empty());
+        assert_eq!(input_rows(&app.input), 4);
+        assert_eq!(input_rows(""), INPUT_ROWS);
+        assert_eq!(input_rows(&"x\n".repeat(20)), INPUT_MAX_LINES + 2);
+
+        handle_key(&mut app, key(KeyCode::Enter, KeyModifiers::NONE))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, "sent");
+        assert_eq!(app.messages[0].body, "a\nb");
+
+        // the length check covers every line
+        app.input = format!("{}\n{}", "x".repeat(600), "y".repeat(600));
+        handle_key(&mut app, key(KeyCode::Enter, KeyModifiers::NONE))
+            .await
+            .unwrap();
+        assert!(app.status.text.starts_with("message too long"));
+    }
+
+    #[tokio::test]
+    async fn arrows_edit_mid_line_across_multibyte_chars() {
+        let mut app = test_app(MemStore::new()).await;
+        async fn press(app: &mut App<MemStore>, code: KeyCode) {
+            let k = KeyEvent::new(code, KeyModifiers::NONE);
+            handle_key(app, k).await.unwrap();
+        }
+        for ch in "hé日o".chars() {
+            press(&mut app, KeyCode::Char(ch)).await;
+        }
+        assert_eq!(cursor_cell(&app), (0, 5));
+        press(&mut app, KeyCode::Left).await;
+        press(&mut app, KeyCode::Backspace).await;
+        assert_eq!(app.input, "héo");
+        press(&mut app, KeyCode::Char('l')).await;
+        press(&mut app, KeyCode::Char('l')).await;
+        assert_eq!(app.input, "héllo");
+        press(&mut app, KeyCode::Home).await;
+        press(&mut app, KeyCode::Delete).await;
+        press(&mut app, KeyCode::Right).await;
+        press(&mut app, KeyCode::Delete).await;
+        assert_eq!(app.input, "élo");
+        assert_eq!(cursor_cell(&app), (0, 1));
+        // past either end is a no-op
+        press(&mut app, KeyCode::End).await;
+        press(&mut app, KeyCode::Right).await;
+        press(&mut app, KeyCode::Delete).await;
+        assert_eq!(app.input, "élo");
+
+        // Home/End stay on the caret's line
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT);
+        handle_key(&mut app, enter).await.unwrap();
+        press(&mut app, KeyCode::Char('x')).await;
+        press(&mut app, KeyCode::Home).await;
+        assert_eq!(cursor_cell(&app), (1, 0));
+        press(&mut app, KeyCode::Left).await;
+        press(&mut app, KeyCode::Home).await;
+        handle_paste(&mut app, "> ");
+        assert_eq!(app.input, "> élo\nx");
+        press(&mut app, KeyCode::End).await;
+        assert_eq!(cursor_cell(&app), (0, 5));
+
+        // a cleared input puts the caret back at the start
+        submit(&mut app, "sent").await;
+        assert_eq!(cursor_cell(&app), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn up_down_recall_sent_lines() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        handle_key(&mut app, key(KeyCode::Up)).await.unwrap();
+        assert!(app.input.is_empty());
+        for line in ["one", "/whoami", "two", "two"] {
+            submit(&mut app, line).await;
+        }
+        assert_eq!(app.sent_history, ["one", "/whoami", "two"]);
+
+        handle_key(&mut app, key(KeyCode::Up)).await.unwrap();
+        assert_eq!(app.input, "two");
+        handle_key(&mut app, key(KeyCode::Up)).await.unwrap();
+        handle_key(&mut app, key(KeyCode::Up)).await.unwrap();
+        handle_key(&mut app, key(KeyCode::Up)).await.unwrap();
+        assert_eq!(app.input, "one");
+        handle_key(&mut app, key(KeyCode::Down)).await.unwrap();
+        assert_eq!(app.input, "/whoami");
+        assert_eq!(cursor_pos(&app), app.input.len());
+
+        // editing a recalled line starts over from the newest entry
+        handle_key(&mut app, key(KeyCode::Char('!'))).await.unwrap();
+        assert_eq!(app.history_pos, None);
+        handle_key(&mut app, key(KeyCode::Up)).await.unwrap();
+        assert_eq!(app.input, "two");
+        handle_key(&mut app, key(KeyCode::Down)).await.unwrap();
+        assert!(app.input.is_empty());
+
+        for i in 0..SENT_HISTORY_MAX + 5 {
+            remember_sent(&mut app, &format!("line {}", i));
+        }
+        assert_eq!(app.sent_history.len(), SENT_HISTORY_MAX);
+        assert_eq!(app.sent_history[0], "line 5");
+    }
+
+    #[tokio::test]
+    async fn big_paste_waits_for_confirmation() {
+        let mut app = test_app(MemStore::new()).await;
+        handle_paste(&mut app, "a\r\nb");
+        assert_eq!(app.input, "a b");
+        assert!(app.pending_paste.is_none());
+
+        app.input = "see: ".into();
+        handle_paste(&mut app, &"line\n".repeat(10));
+        assert!(app.messages.is_empty());
+        assert_eq!(
+            paste_prompt(app.pending_paste.as_deref().unwrap()),
+            "paste is 10 lines / 55 chars — Enter to send, Esc to cancel"
+        );
+        // typing doesn't leak into the input while the prompt is up
+        let x = KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE);
+        handle_key(&mut app, x).await.unwrap();
+        assert_eq!(app.input, "see: ");
+        submit(&mut app, "see: ").await;
+        assert!(app.pending_paste.is_none());
+        assert!(app.messages[0].body.starts_with("see: line\nline"));
+
+        handle_paste(&mut app, &"y".repeat(PASTE_MAX_CHARS + 1));
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        handle_key(&mut app, esc).await.unwrap();
+        assert!(app.pending_paste.is_none());
+        assert_eq!(app.messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn server_rate_limit_is_reported() {
+        let mut app = test_app(MemStore::new().with_rate(1)).await;
+        // another session of the same user spent the server allowance
+        let (room, user) = (app.room.id, app.user.id);
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_message(ch, room, user, "elsewhere")
+            .await
+            .unwrap();
+        submit(&mut app, "one").await;
+        assert_eq!(app.status.text, "rate limited (server)");
+        assert!(app.messages.is_empty());
+        // the client bucket follows the server's count, so the next try stays local
+        assert!(app.bucket.peek_tokens() < 1.0);
+        submit(&mut app, "two").await;
+        // 10/min refills a token every 6s
+        assert_eq!(app.status.text, "rate limited — retry in 6s");
+    }
+
+    #[tokio::test]
+    async fn join_creates_and_switches_room() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/join dev").await;
+        assert_eq!(app.room.name, "dev");
+        assert!(app.status.text.starts_with("created room 'dev'"));
+        let names: Vec<&str> = app.rooms.iter().map(|r| r.name.as_str()).collect();
+        assert!(names.contains(&"dev") && names.contains(&"lobby"));
+        submit(&mut app, "/join Bad Name").await;
+        assert_eq!(app.room.name, "dev");
+    }
+
+    #[tokio::test]
+    async fn protected_rooms_need_the_password_once() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        submit(&mut app, "/join vault hunter2").await;
+        assert!(app.status.text.starts_with("created room 'vault'"));
+        submit(&mut app, "/join vault two words").await;
+        assert_eq!(
+            app.status.text,
+            "usage: /join <room> [password] (one word, max 64)"
+        );
+
+        let alice = app.user.clone();
+        app.user = bob;
+        submit(&mut app, "/join lobby").await;
+        // outsiders don't see it listed
+        submit(&mut app, "/list").await;
+        let ov = app.overlay.take().expect("list overlay");
+        assert!(!ov.lines.iter().any(|l| l.to_string().contains("vault")));
+        submit(&mut app, "/join vault").await;
+        assert_eq!(app.status.text, "wrong password");
+        submit(&mut app, "/join vault hunter3").await;
+        assert_eq!(app.status.text, "wrong password");
+        assert_eq!(app.room.name, "lobby");
+        submit(&mut app, "/join vault hunter2").await;
+        assert_eq!(app.status.text, "joined 'vault'");
+        submit(&mut app, "/list").await;
+        let ov = app.overlay.take().expect("list overlay");
+        assert!(ov.lines.iter().any(|l| l.to_string().contains("vault")));
+        // members don't need it again
+        submit(&mut app, "/join lobby").await;
+        submit(&mut app, "/join vault").await;
+        assert_eq!(app.room.name, "vault");
+
+        app.user = alice;
+        submit(&mut app, "/leave vault").await;
+        submit(&mut app, "/join vault").await;
+        assert_eq!(app.room.name, "vault");
+    }
+
+    #[tokio::test]
+    async fn invite_rooms_need_a_room_code() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        submit(&mut app, "/access invite").await;
+        assert_eq!(app.status.text, "the default room stays public");
+        submit(&mut app, "/join dev").await;
+        submit(&mut app, "/access").await;
+        assert_eq!(app.status.text, "room access: public");
+        submit(&mut app, "/access invite").await;
+        assert!(app.status.text.starts_with("room is invite-only"));
+        submit(&mut app, "/roominvite").await;
+        let code = app
+            .status
+            .text
+            .strip_prefix("invite code for dev: ")
+            .and_then(|s| s.split_whitespace().next())
+            .unwrap()
+            .to_string();
+
+        app.user = bob;
+        submit(&mut app, "/join lobby").await;
+        submit(&mut app, "/access public").await;
+        assert_eq!(app.status.text, "the default room stays public");
+        submit(&mut app, "/join dev").await;
+        assert_eq!(app.status.text, "wrong password");
+        // a room code can't found a new room as its password
+        submit(&mut app, &format!("/join devv {}", code)).await;
+        assert_eq!(app.status.text, "wrong password");
+        assert_eq!(app.room.name, "lobby");
+        submit(&mut app, &format!("/join dev {}", code)).await;
+        assert_eq!(app.status.text, "joined 'dev'");
+        submit(&mut app, "/roominvite").await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator or a moderator can invite"
+        );
+        // permission comes first, so a bad ttl from anyone is just refused
+        submit(&mut app, "/roominvite 1 5日").await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator or a moderator can invite"
+        );
+        submit(&mut app, "/access public").await;
+        assert_eq!(app.status.text, "only the room creator can set its access");
+
+        // spent: a second newcomer can't reuse it
+        app.user = app.store.add_user("carol");
+        submit(&mut app, "/join lobby").await;
+        submit(&mut app, &format!("/join dev {}", code)).await;
+        assert_eq!(app.room.name, "lobby");
+    }
+
+    #[tokio::test]
+    async fn leave_resolves_rooms_missing_from_sidebar() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/join dev").await;
+        submit(&mut app, "/join lobby").await;
+        app.rooms.retain(|r| r.name != "dev");
+        submit(&mut app, "/part dev").await;
+        assert_eq!(app.status.text, "left 'dev'");
+        let joined = app.store.list_joined_rooms(app.user.id).await.unwrap();
+        assert!(joined.iter().all(|r| r.name != "dev"));
+        submit(&mut app, "/leave dev").await;
+        assert_eq!(app.status.text, "not a member of 'dev'");
+    }
+
+    #[tokio::test]
+    async fn catch_up_summarizes_messages_since_last_visit() {
+        let store = MemStore::new();
+        let alice = store.add_user("alice");
+        let (lobby, _) = store.ensure_room_exists("lobby", alice.id).await.unwrap();
+        let (dev, _) = store.ensure_room_exists("dev", alice.id).await.unwrap();
+        store.join_room("", lobby.id, alice.id).await.unwrap();
+        store.join_room("", dev.id, alice.id).await.unwrap();
+        let mut opts = test_opts();
+        opts.catch_up = true;
+        let dev_id = dev.id;
+        let mut app = App::load(store, alice, dev, opts).await.unwrap();
+        assert!(app.catch_up.is_none());
+
+        submit(&mut app, "/join lobby").await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        for handle in ["bob", "carol", "bob", "dave", "erin"] {
+            let u = app
+                .store
+                .get_user_by_handle(handle)
+                .await
+                .unwrap()
+                .unwrap_or_else(|| app.store.add_user(handle));
+            app.store
+                .insert_message(ch, dev_id, u.id, "hi")
+                .await
+                .unwrap();
+        }
+        submit(&mut app, "/join dev").await;
+        let text = &app.catch_up.as_ref().expect("summary").text;
+        assert!(
+            text.starts_with("5 new messages from bob, carol, dave +1 more since "),
+            "{}",
+            text
+        );
+
+        // seen now; coming back again has nothing new
+        submit(&mut app, "/join lobby").await;
+        submit(&mut app, "/join dev").await;
+        assert!(app.catch_up.is_none());
+    }
+
+    #[tokio::test]
+    async fn page_keys_scroll_and_stay_pinned() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let room = app.room.id;
+        for i in 0..30 {
+            app.store
+                .insert_message(ch, room, bob.id, &format!("m{}", i))
+                .await
+                .unwrap();
+        }
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        app.pane_rows.set(10);
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        handle_key(&mut app, key(KeyCode::PageUp)).await.unwrap();
+        assert_eq!(app.scroll_offset, 9);
+        for _ in 0..5 {
+            handle_key(&mut app, key(KeyCode::PageUp)).await.unwrap();
+        }
+        // clamped at the first loaded message
+        assert_eq!(app.scroll_offset, 20);
+
+        // new traffic doesn't move the view while scrolled up
+        app.store
+            .insert_message(ch, room, bob.id, "late")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(app.scroll_offset, 21);
+
+        let half = KeyEvent::new(KeyCode::Char('d'), KeyModifiers::CONTROL);
+        handle_key(&mut app, half).await.unwrap();
+        assert_eq!(app.scroll_offset, 16);
+        for _ in 0..3 {
+            handle_key(&mut app, key(KeyCode::PageDown)).await.unwrap();
+        }
+        assert_eq!(app.scroll_offset, 0);
+        app.store
+            .insert_message(ch, room, bob.id, "later")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(app.scroll_offset, 0);
+    }
+
+    #[tokio::test]
+    async fn page_up_at_top_loads_older_history() {
+        let store = MemStore::new().with_rate(100);
+        let alice = store.add_user("alice");
+        let (room, _) = store.ensure_room_exists("lobby", alice.id).await.unwrap();
+        store.join_room("", room.id, alice.id).await.unwrap();
+        let mut ids = vec![];
+        for i in 0..12 {
+            let body = format!("m{}", i);
+            let out = store.insert_message("", room.id, alice.id, &body).await;
+            ids.push(out.unwrap().message.unwrap().id);
+        }
+        store.delete_message(ids[1]);
+        let mut opts = test_opts();
+        opts.history_load = 5;
+        let mut app = App::load(store, alice, room, opts).await.unwrap();
+        app.pane_rows.set(4);
+        let page_up = KeyEvent::new(KeyCode::PageUp, KeyModifiers::NONE);
+
+        // 5 rows in a 4-row pane: the first press only scrolls
+        handle_key(&mut app, page_up).await.unwrap();
+        assert_eq!(app.messages.len(), 5);
+        assert_eq!(app.scroll_offset, 1);
+        handle_key(&mut app, page_up).await.unwrap();
+        assert_eq!(app.status.text, "loaded 5 older messages");
+        assert_eq!(app.messages.len(), 10);
+        assert_eq!(app.scroll_offset, 4);
+
+        while app.status.text != "no more history" {
+            handle_key(&mut app, page_up).await.unwrap();
+        }
+        // the soft-deleted row stays out
+        let bodies: Vec<&str> = app.messages.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies.len(), 11);
+        assert!(!bodies.contains(&"m1"), "{:?}", bodies);
+        assert_eq!(app.seen_ids.len(), 11);
+    }
+
+    #[tokio::test]
+    async fn msg_sends_and_receives_direct_messages() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        app.store.add_user("carol");
+        submit(&mut app, "/msg nobody hi").await;
+        assert_eq!(app.status.text, "no such user");
+        submit(&mut app, "/msg bob").await;
+        assert_eq!(app.status.text, "usage: /msg <nick> <text>");
+        submit(&mut app, "/msg bob  psst").await;
+        assert_eq!(app.status.text, "sent to bob");
+        // the recipient's notification isn't ours to show
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(app.dms.len(), 1);
+
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_dm(ch, bob.id, "alice", "yo")
+            .await
+            .unwrap();
+        app.store
+            .insert_dm(ch, bob.id, "carol", "secret")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert!(
+            app.status.text.starts_with("dm from bob"),
+            "{}",
+            app.status.text
+        );
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert_eq!(rows, ["[dm] you \u{2192} bob: psst", "[dm] bob: yo"]);
+    }
+
+    #[tokio::test]
+    async fn code_blocks_render_as_verbatim_rows() {
+        let app = test_app(MemStore::new()).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        let body = "see #1:\n```rust\nif x {\n\ty();   // #2\n}\n```\ndone";
+        app.store
+            .insert_message(ch, app.room.id, app.user.id, body)
+            .await
+            .unwrap();
+        let msgs = app
+            .store
+            .recent_messages_view(app.room.id, 5)
+            .await
+            .unwrap();
+        let lines = message_lines(&msgs[0], &test_opts(), None, 0);
+        let rows: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        assert!(rows[0].ends_with("alice: see #1:"), "{}", rows[0]);
+        assert_eq!(
+            &rows[1..],
+            ["   if x { ", "       y();   // #2 ", "   } ", "done"]
+        );
+        assert!(lines[2].spans[1].style.add_modifier.contains(Modifier::DIM));
+        // references inside the block aren't links
+        assert_eq!(text_refs(&msgs[0].body), [(4, 1)]);
+    }
+
+    #[test]
+    fn wrap_breaks_at_spaces_and_splits_long_words() {
+        let rows = |line: Line<'static>, cols| -> Vec<String> {
+            wrap_line(line, cols)
+                .iter()
+                .map(|l| l.to_string())
+                .collect()
+        };
+        assert_eq!(rows(Line::from("aaa bbb ccc"), 7), ["aaa bbb", "  ccc"]);
+        assert_eq!(rows(Line::from("aaa bbb ccc"), 0), ["aaa bbb ccc"]);
+        assert_eq!(
+            rows(Line::from("see https://example.com/x"), 10),
+            ["see", "  https://", "  example.", "  com/x"]
+        );
+        // wide chars count double
+        assert_eq!(rows(Line::from("日本語です"), 6), ["日本語", "  です"]);
+        let styled = Line::from(vec![
+            Span::raw("abc "),
+            Span::styled("de", Style::default().add_modifier(Modifier::BOLD)),
+        ]);
+        let wrapped = wrap_line(styled, 5);
+        assert_eq!(wrapped[1].spans[1].content, "de");
+        assert!(wrapped[1].spans[1]
+            .style
+            .add_modifier
+            .contains(Modifier::BOLD));
+    }
+
+    #[tokio::test]
+    async fn long_messages_wrap_but_code_rows_dont() {
+        let mut app = test_app(MemStore::new()).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        let body = "one two three four five six\n```\nlet verbatim_code_row = 1;\n```";
+        app.store
+            .insert_message(ch, app.room.id, app.user.id, body)
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        app.pane_cols.set(20);
+        app.pane_rows.set(2);
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert!(rows[0].contains("alice:"), "{:?}", rows);
+        let code = "   let verbatim_code_row = 1; ";
+        assert_eq!(rows.last().unwrap(), code);
+        for r in &rows[1..rows.len() - 1] {
+            assert!(r.starts_with("  ") && r.len() <= 20, "{:?}", rows);
+        }
+        // scrolling counts the wrapped rows
+        assert_eq!(max_scroll(&app), rows.len() - 2);
+        assert!(rows.len() > 3);
+    }
+
+    #[tokio::test]
+    async fn time_toggles_relative_timestamps() {
+        let mut app = test_app(MemStore::new()).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_message(ch, app.room.id, app.user.id, "hi")
+            .await
+            .unwrap();
+        let mut msgs = app
+            .store
+            .recent_messages_view(app.room.id, 5)
+            .await
+            .unwrap();
+        msgs[0].created_at = chrono::Utc::now() - chrono::Duration::hours(2);
+        let clock = msgs[0].created_at.format("%H:%M:%S").to_string();
+        let line = message_lines(&msgs[0], &app.opts, None, 0)[0].to_string();
+        assert!(line.starts_with(&format!("[{}]", clock)), "{}", line);
+        handle_command(&mut app, Command::Time).await.unwrap();
+        assert_eq!(app.status.text, "timestamps: relative");
+        let line = message_lines(&msgs[0], &app.opts, None, 0)[0].to_string();
+        assert!(line.starts_with("[2h]"), "{}", line);
+        handle_command(&mut app, Command::Time).await.unwrap();
+        assert_eq!(app.status.text, "timestamps: clock");
+    }
+
+    #[tokio::test]
+    async fn day_boundaries_get_a_separator() {
+        let mut app = test_app(MemStore::new()).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        for body in ["mon", "late mon", "tue"] {
+            app.store
+                .insert_message(ch, app.room.id, app.user.id, body)
+                .await
+                .unwrap();
+        }
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let monday = chrono::NaiveDate::from_ymd_opt(2025, 1, 13)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+            .and_utc();
+        app.messages[0].created_at = monday;
+        app.messages[1].created_at = monday + chrono::Duration::hours(14);
+        app.messages[2].created_at = monday + chrono::Duration::hours(15);
+        app.pane_cols.set(30);
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert_eq!(rows.len(), 4, "{:?}", rows);
+        assert_eq!(
+            rows[2],
+            "       \u{2500}\u{2500} Tue Jan 14 \u{2500}\u{2500}"
+        );
+        assert!(rows[3].ends_with("alice: tue"), "{:?}", rows);
+    }
+
+    #[tokio::test]
+    async fn deleted_authors_render_with_placeholder() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_message(ch, app.room.id, bob.id, "still here")
+            .await
+            .unwrap();
+        app.store.delete_user(bob.id);
+        app.messages = app
+            .store
+            .recent_messages_view(app.room.id, 50)
+            .await
+            .unwrap();
+        let m = app.messages.last().expect("message kept");
+        assert_eq!(m.user_id, None);
+        let line = message_lines(m, &test_opts(), None, 0)[0].to_string();
+        assert!(line.contains("[deleted-user]: still here"), "{}", line);
+    }
+
+    #[tokio::test]
+    async fn mentions_are_counted_and_highlighted() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        for body in ["hi @Alice", "aliceb isn't you", "alice: ping"] {
+            app.store
+                .insert_message(ch, app.room.id, bob.id, body)
+                .await
+                .unwrap();
+        }
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        // naming yourself doesn't count
+        submit(&mut app, "I am alice").await;
+        assert_eq!(app.mentions, 2);
+        let bold: Vec<bool> = pane_lines(&app)
+            .iter()
+            .map(|l| l.style.add_modifier.contains(Modifier::BOLD))
+            .collect();
+        assert_eq!(bold, [true, false, true, false]);
+
+        submit(&mut app, "/join dev").await;
+        assert_eq!(app.mentions, 0);
+    }
+
+    #[tokio::test]
+    async fn only_the_handle_is_colored() {
+        let app = test_app(MemStore::new()).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_message(ch, app.room.id, app.user.id, "hi")
+            .await
+            .unwrap();
+        let msgs = app
+            .store
+            .recent_messages_view(app.room.id, 5)
+            .await
+            .unwrap();
+        let mut opts = test_opts();
+        opts.color_level = ColorLevel::TrueColor;
+        let line = &message_lines(&msgs[0], &opts, None, 0)[0];
+        let styled: Vec<(&str, Option<Color>)> = line
+            .spans
+            .iter()
+            .filter(|s| s.style.fg.is_some())
+            .map(|s| (s.content.as_ref(), s.style.fg))
+            .collect();
+        assert_eq!(styled, [("alice", Some(theme::handle_color("alice")))]);
+        // no-color terminals get plain text
+        let plain = &message_lines(&msgs[0], &test_opts(), None, 0)[0];
+        assert!(plain.spans.iter().all(|s| s.style.fg.is_none()));
+    }
+
+    #[tokio::test]
+    async fn feedback_is_logged_and_reviewed_by_admins() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/feedback review is broken").await;
+        assert_eq!(app.status.text, "thanks, logged");
+        app.opts.is_admin = true;
+        submit(&mut app, "/feedback review").await;
+        let overlay = app.overlay.take().expect("review overlay");
+        let first = overlay.lines[0].to_string();
+        assert!(first.contains("review is broken"), "{}", first);
+        let id = app.store.list_open_feedback(10).await.unwrap()[0].id;
+        submit(&mut app, &format!("/feedback done {}", id)).await;
+        assert_eq!(app.status.text, format!("feedback #{} handled", id));
+        submit(&mut app, "/feedback review").await;
+        assert_eq!(app.status.text, "feedback: (none open)");
+    }
+
+    #[tokio::test]
+    async fn more_prepends_older_history() {
+        let store = MemStore::new().with_rate(100);
+        let mut app = test_app(store).await;
+        for i in 0..5 {
+            let ch = realtime::DEFAULT_CHANNEL;
+            let body = format!("m{}", i);
+            app.store
+                .insert_message(ch, app.room.id, app.user.id, &body)
+                .await
+                .unwrap();
+        }
+        app.opts.history_load = 2;
+        app.messages = app
+            .store
+            .recent_messages_view(app.room.id, 2)
+            .await
+            .unwrap();
+        submit(&mut app, "/more").await;
+        submit(&mut app, "/more").await;
+        let bodies: Vec<&str> = app.messages.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, ["m0", "m1", "m2", "m3", "m4"]);
+        submit(&mut app, "/more").await;
+        assert_eq!(app.status.text, "no more history");
+    }
+
+    #[tokio::test]
+    async fn status_tagline_shows_in_who() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/status  they/them ").await;
+        assert_eq!(app.status.text, "status set: they/them");
+        submit(&mut app, "/who").await;
+        assert_eq!(
+            app.status.text,
+            "who: *alice (they/them, 0m ago, member 0m)"
+        );
+        submit(
+            &mut app,
+            &format!("/status {}", "x".repeat(TAGLINE_MAX_LEN + 1)),
+        )
+        .await;
+        assert!(app.status.text.starts_with("status too long"));
+        submit(&mut app, "/status off").await;
+        submit(&mut app, "/who").await;
+        assert_eq!(app.status.text, "who: *alice (0m ago, member 0m)");
+    }
+
+    #[tokio::test]
+    async fn who_marks_recently_seen_members() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let carol = app.store.add_user("carol");
+        for u in [&bob, &carol] {
+            app.store.join_room("", app.room.id, u.id).await.unwrap();
+        }
+        let now = chrono::Utc::now();
+        app.store
+            .set_last_seen(bob.id, now - chrono::Duration::hours(3));
+        app.store
+            .set_last_seen(carol.id, now - chrono::Duration::minutes(2));
+        app.store
+            .set_first_joined(app.room.id, bob.id, now - chrono::Duration::days(3));
+        submit(&mut app, "/who").await;
+        // most recently seen first, each with how long they've been a member
+        assert_eq!(
+            app.status.text,
+            "who: *alice (0m ago, member 0m), *carol (2m ago, member 0m), bob (3h ago, member 3d)"
+        );
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_block_non_admin_sends() {
+        let mut app = test_app(MemStore::new()).await;
+        let now = chrono::Utc::now();
+        let start = (now - chrono::Duration::hours(1)).format("%H:%M");
+        let end = (now + chrono::Duration::hours(1))
+            .format("%H:%M")
+            .to_string();
+        submit(&mut app, &format!("/quiet {} {}", start, end)).await;
+        assert!(
+            app.status.text.starts_with("quiet hours set"),
+            "{}",
+            app.status.text
+        );
+        submit(&mut app, "/quiet").await;
+        assert!(
+            app.status.text.ends_with("(now quiet)"),
+            "{}",
+            app.status.text
+        );
+
+        submit(&mut app, "hello").await;
+        assert_eq!(app.status.text, format!("room is quiet until {}", end));
+        assert!(app.messages.is_empty());
+
+        // the admin key posts through
+        app.store.make_admin(app.user.id);
+        submit(&mut app, "hello").await;
+        assert_eq!(app.status.text, "sent");
+
+        submit(&mut app, "/quiet off").await;
+        assert_eq!(app.status.text, "quiet hours off");
+        assert!(app.room.quiet_hours().is_none());
+        submit(&mut app, "/quiet 25:00 07:00").await;
+        assert!(app.status.text.starts_with("usage: /quiet"));
+    }
+
+    #[tokio::test]
+    async fn list_shows_every_room_with_member_counts() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let carol = app.store.add_user("carol");
+        let (dev, _) = app.store.ensure_room_exists("dev", bob.id).await.unwrap();
+        for u in [&bob, &carol] {
+            app.store.join_room("", dev.id, u.id).await.unwrap();
+        }
+        app.store
+            .set_room_topic(dev.id, Some("builds"), None)
+            .await
+            .unwrap();
+        app.store.ensure_room_exists("gone", bob.id).await.unwrap();
+        app.store
+            .soft_delete_room_by_moderator("gone", bob.id)
+            .await
+            .unwrap();
+
+        submit(&mut app, "/list").await;
+        let ov = app.overlay.take().expect("list overlay");
+        assert_eq!(ov.title, "rooms (2) \u{2014} /join <name>");
+        let rows: Vec<String> = ov.lines.iter().map(|l| l.to_string()).collect();
+        assert_eq!(
+            rows,
+            [
+                format!("  {:<24}    2 members  \u{2014} builds", "dev"),
+                format!("> {:<24}    1 member", "lobby"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn topic_is_creator_only_and_shown() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/topic").await;
+        assert_eq!(app.status.text, "no topic set (/topic <text>)");
+        submit(&mut app, "/topic  all things\trust ").await;
+        assert_eq!(app.status.text, "topic set: all things rust");
+        submit(&mut app, "/topic").await;
+        assert_eq!(app.status.text, "topic: all things rust");
+        // survives a reload of the room row
+        let (room, _) = app
+            .store
+            .ensure_room_exists("lobby", app.user.id)
+            .await
+            .unwrap();
+        assert_eq!(room.topic.as_deref(), Some("all things rust"));
+        submit(&mut app, &format!("/topic {}", "x".repeat(121))).await;
+        assert_eq!(app.status.text, "topic too long (max 120 chars)");
+        submit(&mut app, "/topic off").await;
+        assert_eq!(app.status.text, "topic cleared");
+        assert!(app.room.topic.is_none());
+
+        let bob = app.store.add_user("bob");
+        let (other, _) = app.store.ensure_room_exists("bobs", bob.id).await.unwrap();
+        app.room = other;
+        submit(&mut app, "/topic mine now").await;
+        assert_eq!(app.status.text, "only the room creator can set the topic");
+    }
+
+    #[tokio::test]
+    async fn purge_deletes_recent_messages_for_creator() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        for body in ["a", "b", "c"] {
+            app.store
+                .insert_message(ch, app.room.id, bob.id, body)
+                .await
+                .unwrap();
+        }
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        submit(&mut app, "mine").await;
+        app.store.take_events();
+
+        submit(&mut app, "/purge 2").await;
+        assert_eq!(app.status.text, "purged 2 messages");
+        let bodies: Vec<&str> = app.messages.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, ["a", "b"]);
+        // every deletion is broadcast
+        assert_eq!(app.store.take_events().len(), 2);
+
+        submit(&mut app, "/purge user bob").await;
+        assert_eq!(app.status.text, "purged 2 messages");
+        assert!(app.messages.is_empty());
+        submit(&mut app, "/purge user nobody").await;
+        assert_eq!(app.status.text, "no such user 'nobody'");
+        submit(&mut app, "/purge lots").await;
+        assert!(app.status.text.starts_with("usage: /purge"));
+
+        // someone else's room is off limits
+        let (other, _) = app.store.ensure_room_exists("bobs", bob.id).await.unwrap();
+        app.room = other;
+        submit(&mut app, "/purge 5").await;
+        assert_eq!(app.status.text, "only the room creator can purge");
+    }
+
+    #[tokio::test]
+    async fn edit_rewrites_last_own_message() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let bob = app.store.add_user("bob");
+        submit(&mut app, "/edit nope").await;
+        assert_eq!(app.status.text, "nothing to edit in this room");
+
+        submit(&mut app, "first").await;
+        submit(&mut app, "secnod").await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_message(ch, app.room.id, bob.id, "bob's")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+
+        submit(&mut app, "/edit  second").await;
+        assert!(
+            app.status.text.starts_with("edited #"),
+            "{}",
+            app.status.text
+        );
+        let shown: Vec<(&str, bool)> = app
+            .messages
+            .iter()
+            .map(|m| (m.body.as_str(), m.edited))
+            .collect();
+        assert_eq!(
+            shown,
+            [("first", false), ("second", true), ("bob's", false)]
+        );
+        // other clients refetch the row and see the marker too
+        let ev = app.store.take_events().pop().unwrap();
+        assert!(matches!(ev, realtime::Event::Edited { .. }));
+        app.messages[1].edited = false;
+        apply_event(&mut app, ev).await.unwrap();
+        assert!(app.messages[1].edited);
+        let line = message_lines(&app.messages[1], &test_opts(), None, 0)[0].to_string();
+        assert!(line.ends_with("second (edited)"), "{}", line);
+
+        submit(&mut app, &format!("/edit {}", "x".repeat(1001))).await;
+        assert!(app.status.text.starts_with("message too long"));
+        submit(&mut app, "/edit").await;
+        assert_eq!(app.status.text, "usage: /edit <text>");
+    }
+
+    #[tokio::test]
+    async fn del_removes_own_message_and_undo_restores_it() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let mut ids = vec![];
+        for (user, body) in [
+            (app.user.id, "one"),
+            (app.user.id, "two"),
+            (bob.id, "three"),
+        ] {
+            let m = app
+                .store
+                .insert_message(ch, app.room.id, user, body)
+                .await
+                .unwrap();
+            ids.push(m.message.unwrap().id);
+        }
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let bodies = |app: &App<MemStore>| -> Vec<String> {
+            app.messages.iter().map(|m| m.body.clone()).collect()
+        };
+
+        submit(&mut app, &format!("/del {}", ids[2])).await;
+        assert_eq!(app.status.text, "not your message");
+        submit(&mut app, "/del x").await;
+        assert_eq!(app.status.text, "usage: /del <id>");
+
+        submit(&mut app, &format!("/del #{}", ids[0])).await;
+        assert_eq!(
+            app.status.text,
+            format!("message #{} deleted (/undo within 60s)", ids[0])
+        );
+        assert_eq!(bodies(&app), ["two", "three"]);
+        // other clients drop it from the broadcast too (here: a no-op)
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(bodies(&app), ["two", "three"]);
+        submit(&mut app, &format!("/del {}", ids[0])).await;
+        assert_eq!(app.status.text, "not your message");
+
+        submit(&mut app, "/undo").await;
+        assert_eq!(app.status.text, format!("message #{} restored", ids[0]));
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        // back at the top even though it's older than the newest message
+        assert_eq!(bodies(&app), ["one", "two", "three"]);
+
+        // a delete from another client arrives as a broadcast only
+        app.store.delete_message(ids[2]);
+        let ev = realtime::Event::Deleted {
+            id: ids[2],
+            room_id: app.room.id,
+        };
+        apply_event(&mut app, ev).await.unwrap();
+        assert_eq!(bodies(&app), ["one", "two"]);
+    }
+
+    #[tokio::test]
+    async fn audit_lists_full_fingerprints_for_admins() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/audit").await;
+        assert_eq!(app.status.text, "admin only");
+        assert!(app.overlay.is_none());
+
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store.join_room(ch, app.room.id, bob.id).await.unwrap();
+        app.opts.is_admin = true;
+        submit(&mut app, "/audit").await;
+        let ov = app.overlay.take().expect("audit overlay");
+        assert_eq!(ov.title, "audit lobby (2 members)");
+        assert!(ov.lines[1].to_string().contains("fp-bob"));
+
+        let dir = std::env::temp_dir().join(format!("bbs-audit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        app.opts.audit_dir = Some(dir.clone());
+        submit(&mut app, "/audit save").await;
+        assert!(
+            app.status.text.starts_with("audit saved"),
+            "{}",
+            app.status.text
+        );
+        let saved = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let text = std::fs::read_to_string(&saved).unwrap();
+        assert!(text.contains("bob\ted25519\tfp-bob"), "{}", text);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn audit_save_keeps_one_row_per_member() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        // the key type comes from the client
+        app.store.set_pubkey_type(bob.id, "ed25519\tfake\nroot\tx");
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store.join_room(ch, app.room.id, bob.id).await.unwrap();
+        app.opts.is_admin = true;
+        let dir = std::env::temp_dir().join(format!("bbs-audit-tsv-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        app.opts.audit_dir = Some(dir.clone());
+        submit(&mut app, "/audit save").await;
+        let saved = std::fs::read_dir(&dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let text = std::fs::read_to_string(&saved).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), 3, "{}", text);
+        assert!(rows.iter().all(|r| r.split('\t').count() == 3), "{}", text);
+        assert!(text.contains("bob\ted25519fakerootx\tfp-bob"), "{}", text);
+    }
+
+    #[tokio::test]
+    async fn realtime_events_append_or_count_unread() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let (dev, _) = app.store.ensure_room_exists("dev", bob.id).await.unwrap();
+        app.rooms.push(RoomEntry::new(dev.id, "dev".into()));
+        let lobby = app.room.id;
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store
+            .insert_message(ch, lobby, bob.id, "hi")
+            .await
+            .unwrap();
+        app.store
+            .insert_message(ch, dev.id, bob.id, "elsewhere")
+            .await
+            .unwrap();
+        let gone = app
+            .store
+            .insert_message(ch, lobby, bob.id, "oops")
+            .await
+            .unwrap();
+        // deleted before the UI got to fetch it
+        app.store.delete_message(gone.message.unwrap().id);
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let bodies: Vec<&str> = app.messages.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, ["hi"]);
+        assert_eq!(app.rooms.iter().find(|r| r.id == dev.id).unwrap().unread, 1);
+    }
+
+    #[tokio::test]
+    async fn overlapping_listen_and_poll_count_unread_once() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let (dev, _) = app.store.ensure_room_exists("dev", bob.id).await.unwrap();
+        app.rooms.push(RoomEntry::new(dev.id, "dev".into()));
+        let ins = app
+            .store
+            .insert_message(realtime::DEFAULT_CHANNEL, dev.id, bob.id, "hi")
+            .await
+            .unwrap();
+        let id = ins.message.unwrap().id;
+        // the NOTIFY, then the poll fallback finding the same row after a reconnect
+        let (tx, mut rx) = mpsc::channel(8);
+        let mut delivered = realtime::Delivered::default();
+        for ev in app.store.take_events() {
+            realtime::deliver(&tx, &mut delivered, ev).await;
+        }
+        let polled = realtime::Event::Message {
+            id,
+            room_id: dev.id,
+        };
+        realtime::deliver(&tx, &mut delivered, polled).await;
+        drop(tx);
+        while let Some(ev) = rx.recv().await {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(app.rooms.iter().find(|r| r.id == dev.id).unwrap().unread, 1);
+    }
+
+    #[tokio::test]
+    async fn sidebar_member_counts_follow_joins() {
+        let mut app = test_app(MemStore::new()).await;
+        let lobby = app.room.id;
+        let count =
+            |app: &App<MemStore>, id: i64| app.rooms.iter().find(|r| r.id == id).unwrap().members;
+        assert_eq!(count(&app, lobby), 1);
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        app.store.join_room(ch, lobby, bob.id).await.unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(count(&app, lobby), 2);
+        app.store.leave_room(ch, lobby, bob.id).await.unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(count(&app, lobby), 1);
+        // a room joined later gets its count straight away
+        let (dev, _) = app.store.ensure_room_exists("dev", bob.id).await.unwrap();
+        app.store.join_room(ch, dev.id, bob.id).await.unwrap();
+        app.store.take_events();
+        handle_command(&mut app, Command::Join("dev".into(), None))
+            .await
+            .unwrap();
+        assert_eq!(count(&app, app.room.id), 2);
+    }
+
+    #[tokio::test]
+    async fn invite_mints_multi_use_codes() {
+        let mut app = test_app(MemStore::new()).await;
+        handle_command(&mut app, Command::Invite(None, None))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, "admin only");
+        app.opts.is_admin = true;
+        handle_command(&mut app, Command::Invite(Some(0), None))
+            .await
+            .unwrap();
+        assert!(app.status.text.starts_with("usage"), "{}", app.status.text);
+        handle_command(&mut app, Command::Invite(None, Some("soon".into())))
+            .await
+            .unwrap();
+        assert!(app.status.text.starts_with("usage"), "{}", app.status.text);
+        handle_command(&mut app, Command::Invite(Some(3), None))
+            .await
+            .unwrap();
+        let invs = app.store.list_invites(5).await.unwrap();
+        assert_eq!(invs.len(), 1);
+        assert_eq!(invs[0].uses_left, 3);
+        assert_eq!(
+            app.status.text,
+            format!("invite code: {} (3 uses)", invs[0].code)
+        );
+        handle_command(&mut app, Command::Invite(None, Some("2d".into())))
+            .await
+            .unwrap();
+        assert!(app.status.text.ends_with("(1 use, expires in 2d)"));
+        let invs = app.store.list_invites(5).await.unwrap();
+        assert!(invs[0].expires_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn room_rate_limits_each_room() {
+        let mut app = test_app(MemStore::new()).await;
+        handle_command(&mut app, Command::RoomRate(Some("2".into())))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, "room rate set to 2/min");
+        assert_eq!(app.bucket.capacity(), 2.0);
+        submit(&mut app, "one").await;
+        submit(&mut app, "two").await;
+        submit(&mut app, "three").await;
+        assert!(
+            app.status.text.starts_with("rate limited"),
+            "{}",
+            app.status.text
+        );
+        // another room has its own window and the default limit
+        let bob = app.store.add_user("bob");
+        let (dev, _) = app.store.ensure_room_exists("dev", bob.id).await.unwrap();
+        open_room(&mut app, dev).await.unwrap();
+        assert_eq!(app.bucket.capacity(), 10.0);
+        submit(&mut app, "elsewhere").await;
+        assert_eq!(app.messages.len(), 1);
+        handle_command(&mut app, Command::RoomRate(Some("5".into())))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, "only the room creator can set its rate");
+        let (lobby, _) = app.store.ensure_room_exists("lobby", bob.id).await.unwrap();
+        open_room(&mut app, lobby).await.unwrap();
+        assert_eq!(app.bucket.capacity(), 2.0);
+    }
+
+    #[tokio::test]
+    async fn switching_rooms_records_last_room() {
+        let mut app = test_app(MemStore::new()).await;
+        let (dev, _) = app
+            .store
+            .ensure_room_exists("dev", app.user.id)
+            .await
+            .unwrap();
+        let dev_id = dev.id;
+        open_room(&mut app, dev).await.unwrap();
+        assert_eq!(app.store.last_room_id(app.user.id), Some(dev_id));
+    }
+
+    #[tokio::test]
+    async fn burst_is_capped_by_room_rate() {
+        let mut app = test_app(MemStore::new()).await;
+        app.opts.rate_burst = Some(3);
+        let mut room = app.room.clone();
+        assert_eq!(room_bucket(&room, &app.opts).capacity(), 3.0);
+        room.rate_per_min = Some(2);
+        assert_eq!(room_bucket(&room, &app.opts).capacity(), 2.0);
+    }
+
+    #[tokio::test]
+    async fn ignore_hides_messages_and_unread() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let carol = app.store.add_user("carol");
+        let (dev, _) = app.store.ensure_room_exists("dev", bob.id).await.unwrap();
+        app.rooms.push(RoomEntry::new(dev.id, "dev".into()));
+        submit(&mut app, "/ignore bob").await;
+        assert_eq!(app.status.text, "ignoring bob (/unignore bob to undo)");
+        submit(&mut app, "/ignore alice").await;
+        assert_eq!(app.status.text, "you can't ignore yourself");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let lobby = app.room.id;
+        app.store
+            .insert_message(ch, lobby, bob.id, "noise")
+            .await
+            .unwrap();
+        app.store
+            .insert_message(ch, lobby, carol.id, "hello")
+            .await
+            .unwrap();
+        app.store
+            .insert_message(ch, dev.id, bob.id, "more noise")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let shown: Vec<&str> = app.visible_messages().map(|m| m.body.as_str()).collect();
+        assert_eq!(shown, ["hello"]);
+        assert_eq!(app.rooms.iter().find(|r| r.id == dev.id).unwrap().unread, 0);
+        // persisted for the next session
+        assert_eq!(app.store.list_ignores(app.user.id).await.unwrap(), [bob.id]);
+        submit(&mut app, "/unignore bob").await;
+        assert_eq!(app.status.text, "no longer ignoring bob");
+        assert_eq!(app.visible_messages().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn away_shows_in_who_until_next_message() {
+        let mut app = test_app(MemStore::new()).await;
+        submit(&mut app, "/away bbl").await;
+        assert_eq!(app.status.text, "away: bbl");
+        submit(&mut app, "/who").await;
+        assert!(
+            app.status
+                .text
+                .ends_with("alice (0m ago, member 0m) (away: bbl)"),
+            "{}",
+            app.status.text
+        );
+        submit(&mut app, "back now").await;
+        assert_eq!(app.status.text, "sent (no longer away)");
+        assert!(app.user.away_reason.is_none());
+        submit(&mut app, "/who").await;
+        assert!(!app.status.text.contains("away"), "{}", app.status.text);
+        submit(&mut app, "/away lunch").await;
+        submit(&mut app, "/away").await;
+        assert_eq!(app.status.text, "welcome back");
+        let who = app.store.list_recent_members(app.room.id, 5).await.unwrap();
+        assert!(who[0].away_reason.is_none());
+    }
+
+    #[tokio::test]
+    async fn clear_empties_the_pane_until_new_traffic() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let lobby = app.room.id;
+        let old = app
+            .store
+            .insert_message(ch, lobby, bob.id, "old")
+            .await
+            .unwrap()
+            .message
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        submit(&mut app, "/clear").await;
+        assert_eq!(app.status.text, "cleared (local)");
+        assert!(app.messages.is_empty() && app.seen_ids.is_empty());
+        // an edit to a cleared message doesn't bring it back; new ones show
+        app.store
+            .edit_last_message(ch, lobby, bob.id, "old!")
+            .await
+            .unwrap();
+        app.store
+            .insert_message(ch, lobby, bob.id, "new")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let bodies: Vec<&str> = app.messages.iter().map(|m| m.body.as_str()).collect();
+        assert_eq!(bodies, ["new"]);
+        assert_eq!(app.room.id, lobby);
+        // reopening the room reloads history
+        let (room, _) = app.store.ensure_room_exists("lobby", bob.id).await.unwrap();
+        open_room(&mut app, room).await.unwrap();
+        assert!(app.messages.iter().any(|m| m.id == old.id));
+    }
+
+    #[tokio::test]
+    async fn conn_state_flips_feed_indicator() {
+        let mut app = test_app(MemStore::new()).await;
+        assert!(app.feed_live);
+        apply_event(&mut app, realtime::Event::ConnState(false))
+            .await
+            .unwrap();
+        assert!(!app.feed_live);
+        // polling alone doesn't mean sends are failing
+        assert!(app.online);
+        apply_event(&mut app, realtime::Event::ConnState(true))
+            .await
+            .unwrap();
+        assert!(app.feed_live);
+    }
+
+    #[tokio::test]
+    async fn idle_ticks_leave_the_frame_clean() {
+        let mut app = test_app(MemStore::new()).await;
+        app.status = Status::hint();
+        app.dirty = false;
+        tick(&mut app, Instant::now());
+        // a repeat of the feed state we already show isn't a change either
+        apply_event(&mut app, realtime::Event::ConnState(true))
+            .await
+            .unwrap();
+        assert!(!app.dirty);
+        app.set_status(Severity::Info, "hello");
+        app.dirty = false;
+        tick(&mut app, Instant::now());
+        assert!(!app.dirty);
+        tick(&mut app, Instant::now() + Duration::from_secs(3600));
+        assert!(app.dirty);
+        assert_eq!(app.status.text, STATUS_HINT);
+    }
+
+    #[tokio::test]
+    async fn offline_sends_queue_and_flush_within_the_bucket() {
+        let mut app = test_app(MemStore::new().with_rate(3)).await;
+        let health = |online| realtime::Event::Health { online };
+        let bodies = |app: &App<MemStore>| -> Vec<String> {
+            app.messages.iter().map(|m| m.body.clone()).collect()
+        };
+        apply_event(&mut app, health(false)).await.unwrap();
+        assert_eq!(app.status.text, "offline; messages will be queued");
+        submit(&mut app, "one").await;
+        submit(&mut app, "two").await;
+        assert_eq!(app.status.text, "offline; message queued (2)");
+        assert!(app.messages.is_empty());
+        apply_event(&mut app, health(true)).await.unwrap();
+        assert_eq!(app.status.text, "back online; sent 2 queued");
+        assert_eq!(bodies(&app), ["one", "two"]);
+        assert!(app.outbox.is_empty());
+
+        // flushing spends the same tokens typing would; once they run out the rest
+        // waits without another insert
+        apply_event(&mut app, health(false)).await.unwrap();
+        submit(&mut app, "three").await;
+        submit(&mut app, "four").await;
+        apply_event(&mut app, health(true)).await.unwrap();
+        assert!(app
+            .status
+            .text
+            .starts_with("back online; rate limited — retry in"));
+        assert!(app.status.text.ends_with("s, 1 still queued"));
+        assert_eq!(bodies(&app), ["one", "two", "three"]);
+        assert_eq!(app.outbox.len(), 1);
+
+        // with local tokens back, the server's own limit turns it away; still queued
+        app.bucket = room_bucket(&app.room, &app.opts);
+        apply_event(&mut app, health(false)).await.unwrap();
+        apply_event(&mut app, health(true)).await.unwrap();
+        assert_eq!(app.status.text, "back online; rate limited, 1 still queued");
+        assert_eq!(app.outbox.len(), 1);
+        assert_eq!(bodies(&app).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn following_a_link_jumps_like_goto() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        let room = app.room.clone();
+        let mut ids = vec![];
+        for i in 0..10 {
+            let ins = app
+                .store
+                .insert_message(ch, room.id, app.user.id, &format!("m{}", i))
+                .await
+                .unwrap();
+            ids.push(ins.message.unwrap().id);
+        }
+        app.store
+            .insert_message(ch, room.id, app.user.id, &format!("see #{}", ids[2]))
+            .await
+            .unwrap();
+        app.opts.history_load = 4;
+        open_room(&mut app, room).await.unwrap();
+        app.pane_rows.set(5);
+        assert!(!app.seen_ids.contains(&ids[2]));
+        let key = |code, mods| KeyEvent::new(code, mods);
+        handle_key(&mut app, key(KeyCode::Char('k'), KeyModifiers::CONTROL))
+            .await
+            .unwrap();
+        handle_key(&mut app, key(KeyCode::Enter, KeyModifiers::NONE))
+            .await
+            .unwrap();
+        // the target is fetched into the pane and highlighted, no overlay
+        assert_eq!(app.status.text, format!("jumped to #{}", ids[2]));
+        assert!(app.overlay.is_none() && app.link_sel.is_none());
+        assert_eq!(app.goto.map(|(id, _)| id), Some(ids[2]));
+        assert!(app.messages.iter().any(|m| m.id == ids[2]));
+    }
+
+    #[tokio::test]
+    async fn goto_loads_and_centers_old_messages() {
+        let mut app = test_app(MemStore::new().with_rate(100)).await;
+        let ch = realtime::DEFAULT_CHANNEL;
+        let room = app.room.clone();
+        let mut ids = vec![];
+        for i in 0..30 {
+            let ins = app
+                .store
+                .insert_message(ch, room.id, app.user.id, &format!("m{}", i))
+                .await
+                .unwrap();
+            ids.push(ins.message.unwrap().id);
+        }
+        app.opts.history_load = 4;
+        open_room(&mut app, room).await.unwrap();
+        app.pane_rows.set(5);
+        handle_command(&mut app, Command::Goto(Some(ids[5])))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, format!("jumped to #{}", ids[5]));
+        // a window of 2 either side replaces the buffer; no hole before the newest
+        let loaded = |app: &App<MemStore>| app.messages.iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(loaded(&app), &ids[3..8]);
+        assert!(app.detached);
+        let (lines, rows) = pane_layout(&app, Some(ids[5]));
+        let top = lines.len() - app.pane_rows.get() - app.scroll_offset;
+        assert_eq!(rows.unwrap().start, top + 2);
+        // highlighted (bold without color), the rows around it aren't
+        let bold = |i: usize| lines[i].style.add_modifier.contains(Modifier::BOLD);
+        assert!(bold(top + 2) && !bold(top + 1));
+
+        // live traffic waits below the window...
+        let live = app
+            .store
+            .insert_message(ch, app.room.id, app.user.id, "live")
+            .await
+            .unwrap()
+            .message
+            .unwrap()
+            .id;
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(loaded(&app), &ids[3..8]);
+        // ...until paging back to the bottom swaps the newest page in
+        let page_down = KeyEvent::new(KeyCode::PageDown, KeyModifiers::NONE);
+        while app.detached {
+            handle_key(&mut app, page_down).await.unwrap();
+        }
+        assert_eq!(loaded(&app), [&ids[27..], &[live][..]].concat());
+        assert_eq!(app.scroll_offset, 0);
+
+        // a window that reaches the newest message isn't detached
+        handle_command(&mut app, Command::Goto(Some(ids[23])))
+            .await
+            .unwrap();
+        assert_eq!(loaded(&app), &ids[21..26]);
+        assert!(app.detached);
+        handle_command(&mut app, Command::Goto(Some(ids[29])))
+            .await
+            .unwrap();
+        assert_eq!(loaded(&app), [&ids[27..], &[live][..]].concat());
+        assert!(!app.detached);
+
+        let (dev, _) = app
+            .store
+            .ensure_room_exists("dev", app.user.id)
+            .await
+            .unwrap();
+        let elsewhere = app
+            .store
+            .insert_message(ch, dev.id, app.user.id, "over here")
+            .await
+            .unwrap();
+        handle_command(&mut app, Command::Goto(Some(elsewhere.message.unwrap().id)))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, "message not in this room");
+        handle_command(&mut app, Command::Goto(Some(9999)))
+            .await
+            .unwrap();
+        assert_eq!(app.status.text, "message #9999 not found");
+        handle_command(&mut app, Command::Goto(None)).await.unwrap();
+        assert_eq!(app.status.text, "usage: /goto <id>");
+    }
+
+    #[tokio::test]
+    async fn replies_quote_their_parent() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let parent = app
+            .store
+            .insert_message(ch, app.room.id, bob.id, "lunch at\n noon?")
+            .await
+            .unwrap()
+            .message
+            .unwrap()
+            .id;
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        submit(&mut app, &format!("/reply #{} sounds good", parent)).await;
+        assert_eq!(app.status.text, "sent");
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert_eq!(rows[rows.len() - 2], "\u{256d} bob: lunch at noon?");
+        assert!(
+            rows[rows.len() - 1].ends_with("alice: sounds good"),
+            "{:?}",
+            rows
+        );
+
+        // what other clients fetch carries the same reference
+        let reply = app.messages.last().unwrap().id;
+        let fetched = app.store.message_view_by_id(reply).await.unwrap().unwrap();
+        assert_eq!(fetched.reply_to, Some(parent));
+        assert_eq!(fetched.quote_handle.as_deref(), Some("bob"));
+
+        app.store.take_events();
+        assert!(app
+            .store
+            .soft_delete_message(ch, parent, bob.id)
+            .await
+            .unwrap());
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert_eq!(rows[0], "\u{256d} (quoting deleted message)");
+
+        submit(&mut app, &format!("/reply {} again", parent)).await;
+        assert_eq!(app.status.text, format!("message #{} not found", parent));
+        submit(&mut app, "/reply 12").await;
+        assert_eq!(app.status.text, "usage: /reply <id> <text>");
+    }
+
+    #[tokio::test]
+    async fn reactions_toggle_and_show_under_the_message() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let id = app
+            .store
+            .insert_message(ch, app.room.id, bob.id, "shipped!")
+            .await
+            .unwrap()
+            .message
+            .unwrap()
+            .id;
+        app.store
+            .toggle_reaction(ch, id, bob.id, "🎉")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        submit(&mut app, &format!("/react {} 👍", id)).await;
+        assert_eq!(app.status.text, format!("reacted 👍 to #{}", id));
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert_eq!(rows.last().unwrap(), "  🎉1 👍1");
+
+        submit(&mut app, &format!("/react #{} 👍", id)).await;
+        assert_eq!(app.status.text, format!("removed 👍 from #{}", id));
+        app.store
+            .toggle_reaction(ch, id, bob.id, "🎉")
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        let rows: Vec<String> = pane_lines(&app).iter().map(|l| l.to_string()).collect();
+        assert!(
+            rows.last().unwrap().ends_with("bob: shipped!"),
+            "{:?}",
+            rows
+        );
+
+        submit(&mut app, &format!("/react {} lol", id)).await;
+        assert_eq!(app.status.text, "reactions are a single emoji");
+        submit(&mut app, "/react 999 👍").await;
+        assert_eq!(app.status.text, "message #999 not found");
+        submit(&mut app, "/react").await;
+        assert_eq!(app.status.text, "usage: /react <id> <emoji>");
+    }
+
+    #[tokio::test]
+    async fn pins_show_above_the_pane_for_the_creator_only() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let mut ids = vec![];
+        for body in ["rules: be nice", "standup at 10"] {
+            let m = app
+                .store
+                .insert_message(ch, app.room.id, bob.id, body)
+                .await
+                .unwrap();
+            ids.push(m.message.unwrap().id);
+        }
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        submit(&mut app, "/pins").await;
+        assert_eq!(app.status.text, "no pinned messages");
+        submit(&mut app, &format!("/pin {}", ids[0])).await;
+        assert_eq!(app.status.text, format!("pinned #{}", ids[0]));
+        submit(&mut app, &format!("/pin #{}", ids[1])).await;
+        // a repeat changes nothing and doesn't reach the other sessions
+        app.store.take_events();
+        submit(&mut app, &format!("/pin {}", ids[1])).await;
+        assert_eq!(app.status.text, format!("#{} is already pinned", ids[1]));
+        assert!(app.store.take_events().is_empty());
+        assert_eq!(
+            pin_bar(&app.pins).unwrap(),
+            format!(
+                " \u{1f4cc} #{} bob: standup at 10  (+1 more: /pins)",
+                ids[1]
+            )
+        );
+        submit(&mut app, "/pins").await;
+        assert_eq!(
+            app.status.text,
+            format!("pinned: #{} bob, #{} bob (/goto <id>)", ids[1], ids[0])
+        );
+
+        // edits and deletes elsewhere keep the bar current
+        app.store.take_events();
+        app.store
+            .soft_delete_message(ch, ids[1], bob.id)
+            .await
+            .unwrap();
+        for ev in app.store.take_events() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(
+            pin_bar(&app.pins).unwrap(),
+            format!(" \u{1f4cc} #{} bob: rules: be nice", ids[0])
+        );
+        submit(&mut app, &format!("/unpin {}", ids[0])).await;
+        assert_eq!(app.status.text, format!("unpinned #{}", ids[0]));
+        assert!(pin_bar(&app.pins).is_none());
+        submit(&mut app, &format!("/unpin {}", ids[0])).await;
+        assert_eq!(app.status.text, format!("#{} is not pinned", ids[0]));
+
+        // bob didn't create the lobby
+        app.user = bob.clone();
+        submit(&mut app, &format!("/pin {}", ids[0])).await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator or a moderator can pin messages"
+        );
+        submit(&mut app, "/pin").await;
+        assert_eq!(app.status.text, "usage: /pin <id>");
+    }
+
+    #[tokio::test]
+    async fn creators_delegate_moderation() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        let ch = realtime::DEFAULT_CHANNEL;
+        let id = app
+            .store
+            .insert_message(ch, app.room.id, app.user.id, "read the faq")
+            .await
+            .unwrap()
+            .message
+            .unwrap()
+            .id;
+        submit(&mut app, "/mod").await;
+        assert_eq!(app.status.text, "usage: /mod <nick>");
+        submit(&mut app, "/mod nobody").await;
+        assert_eq!(app.status.text, "no such user");
+        submit(&mut app, "/mod bob").await;
+        assert_eq!(app.status.text, "bob is now a moderator of lobby");
+        submit(&mut app, "/mod bob").await;
+        assert_eq!(app.status.text, "bob is already a moderator");
+
+        let alice = app.user.clone();
+        app.user = bob.clone();
+        submit(&mut app, &format!("/pin {}", id)).await;
+        assert_eq!(app.status.text, format!("pinned #{}", id));
+        submit(&mut app, "/mod alice").await;
+        assert_eq!(app.status.text, "only the room creator can grant moderator");
+        submit(&mut app, "/unmod bob").await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator can revoke moderator"
+        );
+
+        app.user = alice;
+        submit(&mut app, "/unmod bob").await;
+        assert_eq!(app.status.text, "bob is no longer a moderator of lobby");
+        submit(&mut app, "/unmod bob").await;
+        assert_eq!(app.status.text, "bob isn't a moderator");
+        app.user = bob;
+        submit(&mut app, &format!("/unpin {}", id)).await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator or a moderator can unpin messages"
+        );
+    }
+
+    #[tokio::test]
+    async fn kicked_users_land_in_the_default_room() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        submit(&mut app, "/kick bob").await;
+        assert_eq!(
+            app.status.text,
+            "nobody can be kicked from the default room"
+        );
+        submit(&mut app, "/join dev").await;
+        let dev = app.room.id;
+        app.store.join_room("", dev, bob.id).await.unwrap();
+        app.store.take_events();
+        submit(&mut app, "/kick alice").await;
+        assert_eq!(app.status.text, "use /leave to leave the room");
+        submit(&mut app, "/kick bob").await;
+        assert_eq!(app.status.text, "removed bob from dev");
+        submit(&mut app, "/kick bob").await;
+        assert_eq!(app.status.text, "bob isn't in this room");
+
+        let events = app.store.take_events();
+        for ev in events.clone() {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(app.status.text, "bob was removed from dev");
+
+        // the same NOTIFY, as bob's client sees it while looking at dev
+        app.user = bob.clone();
+        for ev in events {
+            apply_event(&mut app, ev).await.unwrap();
+        }
+        assert_eq!(app.status.text, "you were removed from dev");
+        assert_eq!(app.room.name, "lobby");
+        assert!(app.rooms.iter().all(|r| r.id != dev));
+
+        submit(&mut app, "/join dev").await;
+        submit(&mut app, "/kick alice").await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator or a moderator can kick"
+        );
+    }
+
+    #[tokio::test]
+    async fn muted_users_see_how_long_is_left() {
+        let mut app = test_app(MemStore::new()).await;
+        let bob = app.store.add_user("bob");
+        app.store.join_room("", app.room.id, bob.id).await.unwrap();
+        submit(&mut app, "/mute bob").await;
+        assert_eq!(app.status.text, "usage: /mute <nick> <minutes> (1-1440)");
+        submit(&mut app, "/mute alice 5").await;
+        assert_eq!(app.status.text, "the room creator can't be muted");
+        submit(&mut app, "/mute bob 5").await;
+        assert_eq!(app.status.text, "muted bob in lobby for 5m");
+
+        let alice = app.user.clone();
+        app.user = bob.clone();
+        submit(&mut app, "anyone there?").await;
+        assert_eq!(app.status.text, "you are muted for 5m");
+        assert!(app.messages.is_empty());
+        submit(&mut app, "/unmute bob").await;
+        assert_eq!(
+            app.status.text,
+            "only the room creator or a moderator can mute"
+        );
+
+        app.user = alice;
+        submit(&mut app, "/unmute bob").await;
+        assert_eq!(app.status.text, "bob can talk in lobby again");
+        submit(&mut app, "/unmute bob").await;
+        assert_eq!(app.status.text, "bob isn't muted");
+        app.user = bob;
+        submit(&mut app, "anyone there?").await;
+        assert_eq!(app.status.text, "sent");
+    }
+}
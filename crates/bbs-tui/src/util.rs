@@ -15,3 +15,318 @@ pub fn normalize_message(input: &str) -> String {
         .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
         .collect()
 }
+
+// Signatures are appended client-side, so they count against msg_max_len.
+pub const SIGNATURE_MAX_LEN: usize = 64;
+pub const SIGNATURE_DELIM: &str = " -- ";
+// Kept short so `/who` stays on one line.
+pub const TAGLINE_MAX_LEN: usize = 24;
+// Matches the users.away_reason check.
+pub const AWAY_MAX_LEN: usize = 40;
+
+// Append a signature to a (normalized) body; None if the result exceeds max_len chars.
+pub fn with_signature(body: &str, sig: Option<&str>, max_len: usize) -> Option<String> {
+    let out = match sig {
+        Some(sig) if !sig.is_empty() => format!("{}{}{}", body, SIGNATURE_DELIM, sig),
+        _ => body.to_string(),
+    };
+    if out.chars().count() > max_len {
+        return None;
+    }
+    Some(out)
+}
+
+// One block character per bucket, scaled to the busiest bucket; empty buckets are
+// blank, and a fully idle room gets no sparkline at all so its name keeps the space.
+pub fn sparkline(counts: &[i64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = counts.iter().copied().max().unwrap_or(0);
+    if max <= 0 {
+        return String::new();
+    }
+    counts
+        .iter()
+        .map(|&c| {
+            if c <= 0 {
+                ' '
+            } else {
+                BARS[((c * 7 + max / 2) / max) as usize]
+            }
+        })
+        .collect()
+}
+
+// Bound a final body both ways: chars keep normal text friendly, bytes stop
+// short-but-huge payloads (astral emoji are 4 bytes each). Err is the status text.
+pub fn check_body_size(body: &str, max_chars: usize, max_bytes: usize) -> Result<(), String> {
+    let chars = body.chars().count();
+    if chars > max_chars {
+        return Err(format!(
+            "message too long ({} chars, max {})",
+            chars, max_chars
+        ));
+    }
+    if body.len() > max_bytes {
+        return Err(format!(
+            "message too large ({} bytes, max {})",
+            body.len(),
+            max_bytes
+        ));
+    }
+    Ok(())
+}
+
+// Compact "how long ago" for member-since displays: 5m, 3h, 12d.
+pub fn age_short(d: chrono::Duration) -> String {
+    let mins = d.num_minutes().max(0);
+    if mins < 60 {
+        format!("{}m", mins)
+    } else if mins < 60 * 24 {
+        format!("{}h", mins / 60)
+    } else {
+        format!("{}d", mins / (60 * 24))
+    }
+}
+
+// First-login greeting (BBS_WELCOME); {handle} and {fp} are filled in per user.
+pub const WELCOME_TEMPLATE: &str = "Welcome, your handle is {handle} ({fp}) — change it with /nick";
+
+pub fn render_welcome(template: &str, handle: &str, fp_short: &str) -> String {
+    let fp = if fp_short.is_empty() {
+        "local"
+    } else {
+        fp_short
+    };
+    template.replace("{handle}", handle).replace("{fp}", fp)
+}
+
+// Generated invite codes: the full width of the invite prompt's box, lowercase
+// letters and digits without the easily confused 0/o, 1/l/i.
+pub const INVITE_CODE_LEN: usize = 16;
+pub const INVITE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+pub const INVITE_MAX_USES: u32 = 100;
+
+// Invite lifetimes: `30m`, `12h`, `7d`; at most a year.
+pub fn parse_ttl(s: &str) -> Option<chrono::Duration> {
+    let (at, unit) = s.char_indices().next_back()?;
+    let n: i64 = s[..at].parse().ok().filter(|&n| n > 0)?;
+    let d = match unit {
+        'm' => chrono::Duration::try_minutes(n),
+        'h' => chrono::Duration::try_hours(n),
+        'd' => chrono::Duration::try_days(n),
+        _ => None,
+    }?;
+    (d <= chrono::Duration::days(365)).then_some(d)
+}
+
+pub fn random_invite_code() -> String {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    (0..INVITE_CODE_LEN)
+        .map(|_| INVITE_ALPHABET[rng.gen_range(0..INVITE_ALPHABET.len())] as char)
+        .collect()
+}
+
+// A reaction is one emoji-like grapheme: not plain ASCII (so `/react 12 lol` is
+// refused rather than stored), within the reactions.emoji byte check.
+pub const REACTION_MAX_BYTES: usize = 32;
+
+pub fn valid_reaction(s: &str) -> bool {
+    use unicode_segmentation::UnicodeSegmentation;
+    !s.is_ascii()
+        && s.len() <= REACTION_MAX_BYTES
+        && !s.chars().any(char::is_whitespace)
+        && s.graphemes(true).count() == 1
+}
+
+pub const MOTD_MAX_LEN: usize = 1000;
+pub const MOTD_MAX_LINES: usize = 12;
+
+// The input line is single-line, so /setmotd takes a literal `\n` as a line break.
+pub fn parse_motd(arg: &str) -> Result<String, String> {
+    let text = normalize_message(&arg.replace("\\n", "\n"));
+    let lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    let text = lines.join("\n").trim().to_string();
+    if text.is_empty() {
+        return Err("motd is empty".into());
+    }
+    if text.chars().count() > MOTD_MAX_LEN {
+        return Err(format!("motd too long (max {} chars)", MOTD_MAX_LEN));
+    }
+    if text.lines().count() > MOTD_MAX_LINES {
+        return Err(format!("motd too long (max {} lines)", MOTD_MAX_LINES));
+    }
+    Ok(text)
+}
+
+// Find `#<id>` message references: '#' followed by digits, not embedded in a word.
+// Returns the byte range of each reference (including '#') and the referenced id.
+pub fn message_refs(body: &str) -> Vec<(std::ops::Range<usize>, i64)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = Vec::new();
+    let mut prev: Option<char> = None;
+    let mut iter = body.char_indices().peekable();
+    while let Some((i, c)) = iter.next() {
+        if c == '#' && !prev.is_some_and(is_word) {
+            let start = i;
+            let mut end = i + 1;
+            while let Some(&(j, d)) = iter.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                end = j + 1;
+                iter.next();
+            }
+            let next = body[end..].chars().next();
+            if end > start + 1 && !next.is_some_and(is_word) {
+                if let Ok(id) = body[start + 1..end].parse::<i64>() {
+                    out.push((start..end, id));
+                }
+            }
+            prev = body[..end].chars().next_back();
+            continue;
+        }
+        prev = Some(c);
+    }
+    out
+}
+
+// Whether `body` names `handle` as a whole word (`@alice` or `Alice,`), ignoring
+// case; handle characters on either side (`aliceb`, `bob-alice`) don't count.
+pub fn mentions(body: &str, handle: &str) -> bool {
+    if handle.is_empty() {
+        return false;
+    }
+    let body = body.to_lowercase();
+    let handle = handle.to_lowercase();
+    let is_handle_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    body.match_indices(&handle).any(|(i, _)| {
+        !body[..i].chars().next_back().is_some_and(is_handle_char)
+            && !body[i + handle.len()..]
+                .chars()
+                .next()
+                .is_some_and(is_handle_char)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mentions_whole_words_only() {
+        assert!(mentions("hey @alice", "alice"));
+        assert!(mentions("ALICE: look", "alice"));
+        assert!(mentions("ping alice.", "alice"));
+        assert!(mentions("aliceb and alice", "alice"));
+        assert!(!mentions("aliceb", "alice"));
+        assert!(!mentions("bob-alice", "alice"));
+        assert!(!mentions("malice", "alice"));
+        assert!(!mentions("anything", ""));
+    }
+
+    #[test]
+    fn parses_invite_ttls() {
+        use chrono::Duration;
+        assert_eq!(parse_ttl("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_ttl("12h"), Some(Duration::hours(12)));
+        assert_eq!(parse_ttl("7d"), Some(Duration::days(7)));
+        assert_eq!(parse_ttl("0h"), None);
+        assert_eq!(parse_ttl("366d"), None);
+        assert_eq!(parse_ttl("5"), None);
+        assert_eq!(parse_ttl("h"), None);
+        assert_eq!(parse_ttl(""), None);
+        assert_eq!(parse_ttl("é"), None);
+        assert_eq!(parse_ttl("5日"), None);
+        assert_eq!(parse_ttl("99999999999999999d"), None);
+    }
+
+    #[test]
+    fn invite_codes_avoid_ambiguous_chars() {
+        let code = random_invite_code();
+        assert_eq!(code.len(), INVITE_CODE_LEN);
+        assert!(
+            code.bytes().all(|b| INVITE_ALPHABET.contains(&b)),
+            "{}",
+            code
+        );
+        assert!(!code.contains(['0', 'o', '1', 'l', 'i']));
+    }
+
+    #[test]
+    fn reactions_are_single_emoji() {
+        assert!(valid_reaction("👍"));
+        assert!(valid_reaction("👍🏽"));
+        assert!(valid_reaction("❤️"));
+        assert!(!valid_reaction("+1"));
+        assert!(!valid_reaction("👍👍"));
+        assert!(!valid_reaction(""));
+        assert!(!valid_reaction("👍 "));
+    }
+
+    #[test]
+    fn finds_message_refs() {
+        let refs = message_refs("see #12, and (#7) but not a#3 or #4x or #");
+        assert_eq!(refs, vec![(4..7, 12), (14..16, 7)]);
+        assert_eq!(message_refs("#1"), vec![(0..2, 1)]);
+        assert!(message_refs("##").is_empty());
+    }
+
+    #[test]
+    fn signature_within_budget() {
+        assert_eq!(
+            with_signature("hi", Some("they/them"), 100),
+            Some("hi -- they/them".into())
+        );
+        assert_eq!(with_signature("hi", None, 2), Some("hi".into()));
+        assert_eq!(with_signature("hi", Some("x"), 6), None);
+        assert_eq!(with_signature("hi", Some("x"), 7), Some("hi -- x".into()));
+    }
+
+    #[test]
+    fn body_size_checks_chars_then_bytes() {
+        assert!(check_body_size("hello", 10, 10).is_ok());
+        let long = check_body_size(&"a".repeat(11), 10, 100).unwrap_err();
+        assert!(long.contains("chars"), "{}", long);
+        // 10 chars but 40 bytes
+        let emoji = "😀".repeat(10);
+        assert!(check_body_size(&emoji, 10, 40).is_ok());
+        let big = check_body_size(&emoji, 10, 39).unwrap_err();
+        assert!(big.contains("40 bytes"), "{}", big);
+    }
+
+    #[test]
+    fn age_short_picks_largest_unit() {
+        use chrono::Duration;
+        assert_eq!(age_short(Duration::seconds(30)), "0m");
+        assert_eq!(age_short(Duration::minutes(59)), "59m");
+        assert_eq!(age_short(Duration::hours(5)), "5h");
+        assert_eq!(age_short(Duration::days(3) + Duration::hours(2)), "3d");
+        assert_eq!(age_short(Duration::seconds(-5)), "0m");
+    }
+
+    #[test]
+    fn welcome_fills_placeholders() {
+        assert_eq!(
+            render_welcome(WELCOME_TEMPLATE, "quiet-otter", "abcd1234"),
+            "Welcome, your handle is quiet-otter (abcd1234) — change it with /nick"
+        );
+        assert_eq!(render_welcome("hi {handle} {fp}", "x", ""), "hi x local");
+    }
+
+    #[test]
+    fn parses_motd_line_breaks() {
+        assert_eq!(parse_motd("hi\\nthere  ").unwrap(), "hi\nthere");
+        assert!(parse_motd("  ").is_err());
+        assert!(parse_motd(&"x".repeat(MOTD_MAX_LEN + 1)).is_err());
+        assert!(parse_motd(&"a\\n".repeat(MOTD_MAX_LINES + 1)).is_err());
+    }
+
+    #[test]
+    fn sparkline_scales_to_max() {
+        assert_eq!(sparkline(&[]), "");
+        assert_eq!(sparkline(&[0, 0]), "");
+        assert_eq!(sparkline(&[0, 1, 4, 8]), " ▂▅█");
+        assert_eq!(sparkline(&[3, 3]), "██");
+    }
+}